@@ -0,0 +1,64 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use titanium_rose::crypto::dh::{Initiator, Responder};
+use titanium_rose::crypto::elgamal::{Alice, Bob};
+use titanium_rose::crypto::group::GroupId;
+use titanium_rose::crypto::x25519;
+
+const ITERATIONS: u32 = 10;
+
+fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    // warm up caches and lazily initialized state before measuring
+    black_box(f());
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    let average: Duration = start.elapsed() / ITERATIONS;
+
+    println!("{name:<32} {average:>12.2?}");
+}
+
+fn main() {
+    for group in GroupId::ALL {
+        println!("{group}");
+
+        let alice = Alice::generate(group);
+        let bob = Bob::generate();
+        let eph = bob.encrypt_for_alice(alice.get_public());
+        let eph_bytes = bincode::serialize(&eph).unwrap();
+
+        bench("Alice::generate", || Alice::generate(group));
+        bench("Bob::encrypt_for_alice", || {
+            bob.encrypt_for_alice(alice.get_public())
+        });
+        bench("Alice::extract_shared_secret", || {
+            alice.extract_shared_secret(bincode::deserialize(&eph_bytes).unwrap())
+        });
+
+        let initiator = Initiator::generate(group);
+        let responder = Responder::respond(initiator.get_public());
+
+        bench("Initiator::generate", || Initiator::generate(group));
+        bench("Responder::respond", || {
+            Responder::respond(initiator.get_public())
+        });
+        bench("Initiator::extract_shared_secret", || {
+            initiator.extract_shared_secret(responder.get_public())
+        });
+    }
+
+    println!("x25519");
+    let initiator = x25519::Initiator::generate();
+    let responder = x25519::Responder::respond(initiator.get_public());
+
+    bench("Initiator::generate", x25519::Initiator::generate);
+    bench("Responder::respond", || {
+        x25519::Responder::respond(initiator.get_public())
+    });
+    bench("Initiator::extract_shared_secret", || {
+        initiator.extract_shared_secret(responder.get_public())
+    });
+}
@@ -0,0 +1,380 @@
+use crypto_bigint::Uint;
+use rand_core::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::drbg::ThreadDrbg;
+use super::group::{
+    uint_from_be_bytes, with_group, Group, GroupId, InvalidElement, GENERATOR_SEED,
+};
+use super::hash::HashFunction;
+use super::sha256::{self, Sha256};
+
+// commitment schemes: a commitment binds its creator to a value without showing it, until the
+// opening is sent along. on top of them, a commit-reveal exchange between two sides, which is
+// what fair coin flipping (or any game of simultaneous moves) over a slow channel needs.
+
+const HASH_TAG: &[u8] = b"titanium-rose commitment";
+pub const NONCE_BYTES: usize = 32;
+
+pub trait Scheme {
+    type Commitment: Serialize + DeserializeOwned + Clone;
+    type Opening: Serialize + DeserializeOwned + Clone;
+
+    fn commit(&self, value: &[u8]) -> (Self::Commitment, Self::Opening);
+    // the form that a value comes out of `open` in. values with the same form commit to the same
+    // thing, so the form is what was committed to, not the bytes given to `commit`.
+    fn canonical(&self, value: &[u8]) -> Vec<u8>;
+    // the committed value in its canonical form, if the opening belongs to the commitment
+    fn open(&self, commitment: &Self::Commitment, opening: &Self::Opening) -> Option<Vec<u8>>;
+}
+
+// SHA-256 of a random nonce and the value. this hides the value as well as the hash hides its
+// input, and binds as well as it resists collisions.
+pub struct HashCommitments;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct HashCommitment(sha256::Digest);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct HashOpening {
+    nonce: [u8; NONCE_BYTES],
+    value: Vec<u8>,
+}
+
+fn hash_commitment(nonce: &[u8; NONCE_BYTES], value: &[u8]) -> HashCommitment {
+    let mut hasher = Sha256::new();
+    hasher.update(HASH_TAG);
+    hasher.update(nonce);
+    hasher.update(value);
+    HashCommitment(hasher.finalize())
+}
+
+impl Scheme for HashCommitments {
+    type Commitment = HashCommitment;
+    type Opening = HashOpening;
+
+    fn commit(&self, value: &[u8]) -> (HashCommitment, HashOpening) {
+        let mut nonce = [0u8; NONCE_BYTES];
+        ThreadDrbg.fill_bytes(&mut nonce);
+
+        let opening = HashOpening {
+            nonce,
+            value: value.to_vec(),
+        };
+        (hash_commitment(&nonce, value), opening)
+    }
+
+    // the value itself
+    fn canonical(&self, value: &[u8]) -> Vec<u8> {
+        value.to_vec()
+    }
+
+    fn open(&self, commitment: &HashCommitment, opening: &HashOpening) -> Option<Vec<u8>> {
+        (hash_commitment(&opening.nonce, &opening.value) == *commitment)
+            .then(|| opening.value.clone())
+    }
+}
+
+// Pedersen commitments g^m h^r in a group, where h is derived from a public seed so that nobody
+// knows its discrete logarithm to g. every m fits a given commitment with some r, so it hides m
+// perfectly, and it binds unless that logarithm can be found. commitments can be multiplied,
+// which commits to the sum of the values.
+pub struct Pedersen(pub GroupId);
+
+fn pedersen_h<const LIMBS: usize>(group: &Group<LIMBS>) -> Uint<LIMBS> {
+    group.derive_generator(GENERATOR_SEED, 1)
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(GroupId, Vec<u8>)")]
+pub struct PedersenCommitment(GroupId, Vec<u8>);
+
+impl TryFrom<(GroupId, Vec<u8>)> for PedersenCommitment {
+    type Error = InvalidElement;
+
+    fn try_from((group_id, c): (GroupId, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| group.check_subgroup(&group.decode(&c)?))?;
+        Ok(Self(group_id, c))
+    }
+}
+
+// m and r, both below q and as long as a group element
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(GroupId, Vec<u8>, Vec<u8>)")]
+pub struct PedersenOpening(GroupId, Vec<u8>, Vec<u8>);
+
+impl TryFrom<(GroupId, Vec<u8>, Vec<u8>)> for PedersenOpening {
+    type Error = InvalidElement;
+
+    fn try_from((group_id, m, r): (GroupId, Vec<u8>, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            for x in [&m, &r] {
+                if &group.decode(x)? >= group.q() {
+                    return Err(InvalidElement::OutOfRange);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(Self(group_id, m, r))
+    }
+}
+
+impl PedersenCommitment {
+    pub fn group(&self) -> GroupId {
+        self.0
+    }
+
+    // a commitment to the sum of both values, which the sum of the openings opens
+    pub fn add(&self, other: &Self) -> Result<Self, InvalidElement> {
+        if self.0 != other.0 {
+            return Err(InvalidElement::GroupMismatch);
+        }
+
+        with_group!(self.0, |group| {
+            // both were validated when they were created
+            let [a, b] = [&self.1, &other.1].map(|x| group.decode(x).unwrap());
+            Ok(Self(self.0, group.encode(&group.mul(&a, &b))))
+        })
+    }
+}
+
+impl PedersenOpening {
+    // m, big-endian and as long as a group element
+    pub fn value(&self) -> &[u8] {
+        &self.1
+    }
+
+    pub fn add(&self, other: &Self) -> Result<Self, InvalidElement> {
+        if self.0 != other.0 {
+            return Err(InvalidElement::GroupMismatch);
+        }
+
+        with_group!(self.0, |group| {
+            let [m1, r1, m2, r2] =
+                [&self.1, &self.2, &other.1, &other.2].map(|x| group.decode(x).unwrap());
+            Ok(Self(
+                self.0,
+                group.encode(&group.add_exponents(&m1, &m2)),
+                group.encode(&group.add_exponents(&r1, &r2)),
+            ))
+        })
+    }
+}
+
+impl Scheme for Pedersen {
+    type Commitment = PedersenCommitment;
+    type Opening = PedersenOpening;
+
+    fn commit(&self, value: &[u8]) -> (PedersenCommitment, PedersenOpening) {
+        with_group!(self.0, |group| {
+            let m = pedersen_m(group, value);
+            let r = group.generate_exponent();
+            let c = group.mul(&group.pow_g(&m), &group.pow(&pedersen_h(group), &r));
+
+            (
+                PedersenCommitment(self.0, group.encode(&c)),
+                PedersenOpening(self.0, group.encode(&m), group.encode(&r)),
+            )
+        })
+    }
+
+    fn open(&self, commitment: &PedersenCommitment, opening: &PedersenOpening) -> Option<Vec<u8>> {
+        if commitment.0 != self.0 || opening.0 != self.0 {
+            return None;
+        }
+
+        with_group!(self.0, |group| {
+            // both were validated when they were created
+            let c = group.decode(&commitment.1).unwrap();
+            let m = group.decode(&opening.1).unwrap();
+            let r = group.decode(&opening.2).unwrap();
+
+            let expected = group.mul(&group.pow_g(&m), &group.pow(&pedersen_h(group), &r));
+            (expected == c).then(|| opening.1.clone())
+        })
+    }
+
+    // m in the form of PedersenOpening::value: leading zeros don't count, and a value that's too
+    // long is the same as its hash
+    fn canonical(&self, value: &[u8]) -> Vec<u8> {
+        with_group!(self.0, |group| group.encode(&pedersen_m(group, value)))
+    }
+}
+
+// the value read as a big-endian number and taken modulo q. values that might not fit in an
+// exponent are committed to by their hash instead, which keeps sums of small values meaningful.
+fn pedersen_m<const LIMBS: usize>(group: &Group<LIMBS>, value: &[u8]) -> Uint<LIMBS> {
+    let m = if value.len() < group.element_bytes() {
+        uint_from_be_bytes(value)
+    } else {
+        uint_from_be_bytes(&sha256::hash(value))
+    };
+    group.reduce_exponent(&m)
+}
+
+// what both sides end up with: the value that was committed to, and the one sent in the clear
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Outcome {
+    pub committed: Vec<u8>,
+    pub revealed: Vec<u8>,
+}
+
+// the commit-reveal exchange: Alice commits to a value, Bob answers with another in the clear, and
+// then Alice opens the commitment. Bob picks a value without knowing Alice's, and Alice can't
+// change the committed one after seeing Bob's.
+pub struct Committer<S: Scheme> {
+    opening: S::Opening,
+    value: Vec<u8>,
+}
+
+impl<S: Scheme> Committer<S> {
+    // the commitment is sent to Bob
+    pub fn start(scheme: &S, value: &[u8]) -> (Self, S::Commitment) {
+        let (commitment, opening) = scheme.commit(value);
+        // as it'll come out on Bob's side
+        let value = scheme.canonical(value);
+
+        (Self { opening, value }, commitment)
+    }
+
+    // Bob's value is all that's needed to finish, and the opening is sent to Bob
+    pub fn finish(&self, response: &[u8]) -> (S::Opening, Outcome) {
+        let outcome = Outcome {
+            committed: self.value.clone(),
+            revealed: response.to_vec(),
+        };
+        (self.opening.clone(), outcome)
+    }
+}
+
+pub struct Responder<S: Scheme> {
+    commitment: S::Commitment,
+    value: Vec<u8>,
+}
+
+impl<S: Scheme> Responder<S> {
+    pub fn respond(commitment: S::Commitment, value: &[u8]) -> Self {
+        Self {
+            commitment,
+            value: value.to_vec(),
+        }
+    }
+
+    // what's sent to Alice
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    // None if the opening doesn't belong to Alice's commitment
+    pub fn finish(&self, scheme: &S, opening: &S::Opening) -> Option<Outcome> {
+        let committed = scheme.open(&self.commitment, opening)?;
+        Some(Outcome {
+            committed,
+            revealed: self.value.clone(),
+        })
+    }
+}
+
+// a fair coin flip: each side picks a random bit, and the coin is their xor, which neither side
+// can bias on its own.
+pub fn coin_flip_value() -> [u8; 1] {
+    let mut value = [0u8];
+    ThreadDrbg.fill_bytes(&mut value);
+    [value[0] & 1]
+}
+
+// heads is true. only the last bit of each value counts, so any encoding of it works.
+pub fn coin_flip(outcome: &Outcome) -> bool {
+    let bit = |value: &[u8]| value.last().map_or(0, |&b| b & 1);
+    bit(&outcome.committed) ^ bit(&outcome.revealed) == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_scheme<S: Scheme>(scheme: &S) {
+        let (commitment, opening) = scheme.commit(b"\x2a");
+        let value = scheme.open(&commitment, &opening).unwrap();
+        assert_eq!(value, scheme.canonical(b"\x2a"));
+
+        // an opening only fits its own commitment
+        let (other_commitment, other_opening) = scheme.commit(b"\x2a");
+        assert!(scheme.open(&other_commitment, &opening).is_none());
+        assert!(scheme.open(&commitment, &other_opening).is_none());
+
+        let bytes = bincode::serialize(&(commitment, opening)).unwrap();
+        let (commitment, opening): (S::Commitment, S::Opening) =
+            bincode::deserialize(&bytes).unwrap();
+        assert_eq!(scheme.open(&commitment, &opening), Some(value));
+    }
+
+    #[test]
+    fn hash_commitments() {
+        check_scheme(&HashCommitments);
+        assert_eq!(HashCommitments.canonical(b"\x00\x2a"), b"\x00\x2a");
+
+        let (commitment, mut opening) = HashCommitments.commit(b"heads");
+        opening.value = b"tails".to_vec();
+        assert!(HashCommitments.open(&commitment, &opening).is_none());
+    }
+
+    #[test]
+    fn pedersen_commitments() {
+        let scheme = Pedersen(GroupId::default());
+        check_scheme(&scheme);
+
+        // the product of two commitments is opened by the sum of their openings
+        let (c1, o1) = scheme.commit(&[200]);
+        let (c2, o2) = scheme.commit(&[100]);
+        let sum = scheme.open(&c1.add(&c2).unwrap(), &o1.add(&o2).unwrap());
+        assert_eq!(sum, Some(scheme.canonical(&[1, 44])));
+
+        // leading zeros and hashing a long value are part of the canonical form
+        assert_eq!(scheme.canonical(&[0x2a]), scheme.canonical(&[0, 0x2a]));
+        let long = [0x2a; 512];
+        assert_eq!(
+            scheme.canonical(&long),
+            scheme.canonical(&sha256::hash(&long))
+        );
+        let (commitment, opening) = scheme.commit(&long);
+        assert_eq!(
+            scheme.open(&commitment, &opening),
+            Some(scheme.canonical(&sha256::hash(&long)))
+        );
+
+        // the same value under another r doesn't open it
+        let (_, o3) = scheme.commit(&[200]);
+        assert_eq!(o1.value(), o3.value());
+        assert!(scheme.open(&c1, &o3).is_none());
+        assert!(Pedersen(GroupId::Modp3072).open(&c1, &o1).is_none());
+
+        with_group!(GroupId::default(), |group| {
+            assert_ne!(&pedersen_h(group), group.g());
+            let q = group.encode(group.q());
+            let opening = bincode::serialize(&(GroupId::default(), q.clone(), q)).unwrap();
+            assert!(bincode::deserialize::<PedersenOpening>(&opening).is_err());
+        });
+    }
+
+    #[test]
+    fn coin_flipping() {
+        let scheme = HashCommitments;
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let (alice, commitment) = Committer::start(&scheme, &[a]);
+            let bob = Responder::respond(commitment, &[b]);
+            let (opening, alice_outcome) = alice.finish(bob.value());
+            let bob_outcome = bob.finish(&scheme, &opening).unwrap();
+
+            assert_eq!(alice_outcome, bob_outcome);
+            assert_eq!(coin_flip(&alice_outcome), a != b);
+        }
+
+        // Alice can't claim another value after seeing Bob's
+        let (_, commitment) = Committer::start(&scheme, &[0]);
+        let bob = Responder::respond(commitment, &coin_flip_value());
+        let (_, fake) = HashCommitments.commit(&[1]);
+        assert!(bob.finish(&scheme, &fake).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+use super::group::{with_group, GroupId, InvalidElement};
+use super::SymmetricKey;
+
+// ephemeral Diffie-Hellman over the same groups as elgamal. unlike ElGamal key transport, where
+// Bob picks the secret alone, both sides contribute an exponent, so a weak random number
+// generator on one of them doesn't give the key away.
+
+// g^x for a secret x, sent together with its group
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "(GroupId, Vec<u8>)")]
+pub struct DhPublic(GroupId, Vec<u8>);
+
+impl TryFrom<(GroupId, Vec<u8>)> for DhPublic {
+    type Error = InvalidElement;
+
+    fn try_from((group_id, public): (GroupId, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            group.check_subgroup(&group.decode(&public)?)
+        })?;
+        Ok(Self(group_id, public))
+    }
+}
+
+impl DhPublic {
+    pub fn group(&self) -> GroupId {
+        self.0
+    }
+
+    pub(crate) fn encoded(&self) -> &[u8] {
+        &self.1
+    }
+}
+
+// an ephemeral exponent and its public value, which the authenticated exchange in sigma also uses
+#[derive(Clone)]
+pub(crate) struct KeyPair {
+    secret: Vec<u8>,
+    pub(crate) public: DhPublic,
+}
+
+impl KeyPair {
+    pub(crate) fn generate(group_id: GroupId) -> Self {
+        with_group!(group_id, |group| {
+            let secret = group.generate_exponent();
+            let public = DhPublic(group_id, group.encode(&group.pow_g(&secret)));
+
+            Self {
+                secret: group.encode(&secret),
+                public,
+            }
+        })
+    }
+
+    // g^xy, with x our secret and g^y the peer's public value
+    pub(crate) fn agree(&self, peer: &DhPublic) -> Result<Vec<u8>, InvalidElement> {
+        if peer.0 != self.public.0 {
+            return Err(InvalidElement::GroupMismatch);
+        }
+
+        with_group!(peer.0, |group| {
+            let secret = group.decode(&self.secret)?;
+            Ok(group.encode(&group.pow(&group.decode(&peer.1)?, &secret)))
+        })
+    }
+}
+
+// the shared value is hashed together with both public values, so the keys are bound to this
+// exchange
+fn session_key(shared: &[u8], initiator: &DhPublic, responder: &DhPublic) -> SymmetricKey {
+    SymmetricKey::from_shared_secret(&[shared, &initiator.1, &responder.1].concat())
+}
+
+// the side that starts the exchange, i.e. Alice
+#[derive(Clone)]
+pub struct Initiator(KeyPair);
+
+impl Initiator {
+    pub fn generate(group_id: GroupId) -> Self {
+        Self(KeyPair::generate(group_id))
+    }
+
+    pub fn get_public(&self) -> &DhPublic {
+        &self.0.public
+    }
+
+    pub fn extract_shared_secret(
+        &self,
+        response: &DhPublic,
+    ) -> Result<SymmetricKey, InvalidElement> {
+        let shared = self.0.agree(response)?;
+        Ok(session_key(&shared, &self.0.public, response))
+    }
+}
+
+// the side that answers, i.e. Bob. it knows the key as soon as it has answered.
+pub struct Responder {
+    public: DhPublic,
+    key: SymmetricKey,
+}
+
+impl Responder {
+    pub fn respond(initiator: &DhPublic) -> Self {
+        let pair = KeyPair::generate(initiator.0);
+        // the initiator's public value was validated, and ours is in the same group
+        let shared = pair.agree(initiator).unwrap();
+        let key = session_key(&shared, initiator, &pair.public);
+
+        Self {
+            public: pair.public,
+            key,
+        }
+    }
+
+    pub fn get_public(&self) -> &DhPublic {
+        &self.public
+    }
+
+    pub fn extract_shared_secret(&self) -> SymmetricKey {
+        self.key.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let initiator = Initiator::generate(GroupId::Ffdhe3072);
+        let responder = Responder::respond(initiator.get_public());
+
+        let key = initiator
+            .extract_shared_secret(responder.get_public())
+            .unwrap();
+        assert_eq!(key, responder.extract_shared_secret());
+    }
+
+    #[test]
+    fn keys_depend_on_both_sides() {
+        let initiator = Initiator::generate(GroupId::Ffdhe3072);
+        let responder = Responder::respond(initiator.get_public());
+        let other = Responder::respond(initiator.get_public());
+
+        let key = initiator.extract_shared_secret(other.get_public()).unwrap();
+        assert_ne!(key, responder.extract_shared_secret());
+        assert_ne!(
+            responder.extract_shared_secret(),
+            other.extract_shared_secret()
+        );
+
+        let other_group = Responder::respond(Initiator::generate(GroupId::Modp3072).get_public());
+        assert_eq!(
+            initiator
+                .extract_shared_secret(other_group.get_public())
+                .err(),
+            Some(InvalidElement::GroupMismatch)
+        );
+    }
+
+    #[test]
+    fn deserialization_validates() {
+        let initiator = Initiator::generate(GroupId::Ffdhe3072);
+        let bytes = bincode::serialize(initiator.get_public()).unwrap();
+        assert!(bincode::deserialize::<DhPublic>(&bytes).is_ok());
+
+        let mut one = vec![0u8; initiator.get_public().1.len()];
+        *one.last_mut().unwrap() = 1;
+        let bytes = bincode::serialize(&DhPublic(GroupId::Ffdhe3072, one)).unwrap();
+        let error = bincode::deserialize::<DhPublic>(&bytes).err().unwrap();
+        assert_eq!(error.to_string(), InvalidElement::Trivial.to_string());
+    }
+}
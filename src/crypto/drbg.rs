@@ -0,0 +1,347 @@
+use std::cell::RefCell;
+
+use rand_core::{CryptoRng, OsRng, RngCore};
+
+use super::hmac::{self, hmac};
+use super::sha256::{Digest, Sha256, DIGEST_BYTES};
+
+// HMAC_DRBG with SHA-256 (NIST SP 800-90A rev. 1, section 10.1.2)
+
+pub const SECURITY_STRENGTH_BYTES: usize = 256 / 8;
+const ENTROPY_BYTES: usize = SECURITY_STRENGTH_BYTES;
+const NONCE_BYTES: usize = SECURITY_STRENGTH_BYTES / 2;
+
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+pub const MAX_BYTES_PER_REQUEST: usize = (1 << 19) / 8;
+
+// `E` is the entropy source. it is consulted on instantiation and on every reseed, including the
+// ones done automatically when the reseed interval runs out or prediction resistance is enabled.
+pub struct HmacDrbg<E: RngCore = OsRng> {
+    key: Digest,
+    v: Digest,
+    reseed_counter: u64,
+    prediction_resistance: bool,
+    entropy: E,
+}
+
+impl HmacDrbg {
+    pub fn new(prediction_resistance: bool) -> Self {
+        let mut nonce = [0u8; NONCE_BYTES];
+        OsRng.fill_bytes(&mut nonce);
+
+        Self::instantiate(OsRng, &nonce, &[], prediction_resistance)
+    }
+}
+
+impl<E: RngCore> HmacDrbg<E> {
+    pub fn instantiate(
+        mut entropy: E,
+        nonce: &[u8],
+        personalization: &[u8],
+        prediction_resistance: bool,
+    ) -> Self {
+        let mut seed = vec![0u8; ENTROPY_BYTES];
+        entropy.fill_bytes(&mut seed);
+        seed.extend_from_slice(nonce);
+        seed.extend_from_slice(personalization);
+
+        let mut drbg = Self {
+            key: [0x00; DIGEST_BYTES],
+            v: [0x01; DIGEST_BYTES],
+            reseed_counter: 1,
+            prediction_resistance,
+            entropy,
+        };
+        drbg.update(&seed);
+
+        drbg
+    }
+
+    pub fn reseed(&mut self, additional: &[u8]) {
+        let mut seed = vec![0u8; ENTROPY_BYTES];
+        self.entropy.fill_bytes(&mut seed);
+        seed.extend_from_slice(additional);
+
+        self.update(&seed);
+        self.reseed_counter = 1;
+    }
+
+    pub fn generate(&mut self, output: &mut [u8], mut additional: &[u8]) {
+        assert!(output.len() <= MAX_BYTES_PER_REQUEST);
+
+        if self.prediction_resistance || self.reseed_counter > RESEED_INTERVAL {
+            self.reseed(additional);
+            additional = &[];
+        }
+
+        if !additional.is_empty() {
+            self.update(additional);
+        }
+
+        for chunk in output.chunks_mut(DIGEST_BYTES) {
+            self.v = hmac(&hmac::Key::<Sha256>::from_bytes(&self.key), &self.v);
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+
+        self.update(additional);
+        self.reseed_counter += 1;
+    }
+
+    fn update(&mut self, provided: &[u8]) {
+        self.update_round(0x00, provided);
+        if !provided.is_empty() {
+            self.update_round(0x01, provided);
+        }
+    }
+
+    fn update_round(&mut self, separator: u8, provided: &[u8]) {
+        let mut input = Vec::with_capacity(DIGEST_BYTES + 1 + provided.len());
+        input.extend_from_slice(&self.v);
+        input.push(separator);
+        input.extend_from_slice(provided);
+
+        self.key = hmac(&hmac::Key::<Sha256>::from_bytes(&self.key), &input);
+        self.v = hmac(&hmac::Key::<Sha256>::from_bytes(&self.key), &self.v);
+    }
+}
+
+impl<E: RngCore> RngCore for HmacDrbg<E> {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(MAX_BYTES_PER_REQUEST) {
+            self.generate(chunk, &[]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<E: RngCore + CryptoRng> CryptoRng for HmacDrbg<E> {}
+
+thread_local! {
+    static THREAD_DRBG: RefCell<HmacDrbg> = RefCell::new(HmacDrbg::new(false));
+}
+
+// a handle to a per-thread HmacDrbg seeded from the OS, which is what the rest of the crate draws
+// its randomness from
+#[derive(Clone, Copy, Default)]
+pub struct ThreadDrbg;
+
+impl RngCore for ThreadDrbg {
+    fn next_u32(&mut self) -> u32 {
+        THREAD_DRBG.with_borrow_mut(|drbg| drbg.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        THREAD_DRBG.with_borrow_mut(|drbg| drbg.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        THREAD_DRBG.with_borrow_mut(|drbg| drbg.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        THREAD_DRBG.with_borrow_mut(|drbg| drbg.try_fill_bytes(dest))
+    }
+}
+
+impl CryptoRng for ThreadDrbg {}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // hands out pre-recorded entropy, in order
+    struct FixedEntropy(Vec<u8>);
+
+    impl RngCore for FixedEntropy {
+        fn next_u32(&mut self) -> u32 {
+            rand_core::impls::next_u32_via_fill(self)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            rand_core::impls::next_u64_via_fill(self)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            let rest = self.0.split_off(dest.len());
+            dest.copy_from_slice(&self.0);
+            self.0 = rest;
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    // the CAVP procedure for prediction resistance off and no reseeding: instantiate, generate
+    // twice and compare only the output of the second call
+    fn cavp(entropy: &[u8], nonce: &[u8], additional: [&[u8]; 2], expected: &[u8]) {
+        let mut drbg = HmacDrbg::instantiate(FixedEntropy(entropy.to_vec()), nonce, &[], false);
+
+        let mut output = vec![0u8; expected.len()];
+        drbg.generate(&mut output, additional[0]);
+        drbg.generate(&mut output, additional[1]);
+
+        assert_eq!(output, expected);
+    }
+
+    // CAVP HMAC_DRBG.rsp, [SHA-256], [PredictionResistance = False], COUNT = 0
+    #[test]
+    fn cavp_no_additional_input() {
+        cavp(
+            &hex!("ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488"),
+            &hex!("659ba96c601dc69fc902940805ec0ca8"),
+            [&[], &[]],
+            &hex!(
+                "e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697d04d5b89"
+                "d54fbb978a15b5c443c9ec21036d2460b6f73ebad0dc2aba6e624abf07745bc1"
+                "07694bb7547bb0995f70de25d6b29e2d3011bb19d27676c07162c8b5ccde0668"
+                "961df86803482cb37ed6d5c0bb8d50cf1f50d476aa0458bdaba806f48be9dcb8"
+            ),
+        );
+    }
+
+    // CAVP HMAC_DRBG.rsp, [SHA-256], [PredictionResistance = False], COUNT = 1
+    #[test]
+    fn cavp_no_additional_input_2() {
+        cavp(
+            &hex!("79737479ba4e7642a221fcfd1b820b134e9e3540a35bb48ffae29c20f5418ea3"),
+            &hex!("3593259c092bef4129bc2c6c9e19f343"),
+            [&[], &[]],
+            &hex!(
+                "cf5ad5984f9e43917aa9087380dac46e410ddc8a7731859c84e9d0f31bd43655"
+                "b924159413e2293b17610f211e09f770f172b8fb693a35b85d3b9e5e63b1dc25"
+                "2ac0e115002e9bedfb4b5b6fd43f33b8e0eafb2d072e1a6fee1f159df9b51e6c"
+                "8da737e60d5032dd30544ec51558c6f080bdbdab1de8a939e961e06b5f1aca37"
+            ),
+        );
+    }
+
+    // CAVP HMAC_DRBG.rsp, [SHA-256], [PredictionResistance = False], COUNT = 0 with
+    // AdditionalInputLen = 256
+    #[test]
+    fn cavp_additional_input() {
+        cavp(
+            &hex!("d3cc4d1acf3dde0c4bd2290d262337042dc632948223d3a2eaab87da44295fbd"),
+            &hex!("0109b0e729f457328aa18569a9224921"),
+            [
+                &hex!("3c311848183c9a212a26f27f8c6647e40375e466a0857cc39c4e47575d53f1f6"),
+                &hex!("fcb9abd19ccfbccef88c9c39bfb3dd7b1c12266c9808992e305bc3cff566e4e4"),
+            ],
+            &hex!(
+                "9c7b758b212cd0fcecd5daa489821712e3cdea4467b560ef5ddc24ab47749a1f"
+                "1ffdbbb118f4e62fcfca3371b8fbfc5b0646b83e06bfbbab5fac30ea09ea2bc7"
+                "6f1ea568c9be0444b2cc90517b20ca825f2d0eccd88e7175538b85d90ab39018"
+                "3ca6395535d34473af6b5a5b88f5a59ee7561573337ea819da0dcc3573a22974"
+            ),
+        );
+    }
+
+    // CAVP HMAC_DRBG.rsp, [SHA-256], [PredictionResistance = False], COUNT = 1 with
+    // AdditionalInputLen = 256
+    #[test]
+    fn cavp_additional_input_2() {
+        cavp(
+            &hex!("f97a3cfd91faa046b9e61b9493d436c4931f604b22f1081521b3419151e8ff06"),
+            &hex!("11f3a7d43595357d58120bd1e2dd8aed"),
+            [
+                &hex!("517289afe444a0fe5ed1a41dbbb5eb17150079bdd31e29cf2ff30034d8268e3b"),
+                &hex!("88028d29ef80b4e6f0fe12f91d7449fe75062682e89c571440c0c9b52c42a6e0"),
+            ],
+            &hex!(
+                "c6871cff0824fe55ea7689a52229886730450e5d362da5bf590dcf9acd67fed4"
+                "cb32107df5d03969a66b1f6494fdf5d63d5b4d0d34ea7399a07d0116126d0d51"
+                "8c7c55ba46e12f62efc8fe28a51c9d428e6d371d7397ab319fc73ded4722e5b4"
+                "f30004032a6128df5e7497ecf82ca7b0a50e867ef6728a4f509a8c859087039c"
+            ),
+        );
+    }
+
+    // CAVP HMAC_DRBG.rsp with reseeding, [SHA-256], [PredictionResistance = False], COUNT = 0:
+    // instantiate, reseed, then generate twice
+    #[test]
+    fn cavp_reseed() {
+        let entropy = hex!(
+            "06032cd5eed33f39265f49ecb142c511da9aff2af71203bffaf34a9ca5bd9c0d"
+            "01920a4e669ed3a85ae8a33b35a74ad7fb2a6bb4cf395ce00334a9c9a5a5d552"
+        );
+        let nonce = hex!("0e66f71edc43e42a45ad3c6fc6cdc4df");
+        let mut drbg = HmacDrbg::instantiate(FixedEntropy(entropy.to_vec()), &nonce, &[], false);
+        drbg.reseed(&[]);
+
+        let mut output = [0u8; 128];
+        drbg.generate(&mut output, &[]);
+        drbg.generate(&mut output, &[]);
+        assert_eq!(
+            output,
+            hex!(
+                "76fc79fe9b50beccc991a11b5635783a83536add03c157fb30645e611c2898bb"
+                "2b1bc215000209208cd506cb28da2a51bdb03826aaf2bd2335d576d519160842"
+                "e7158ad0949d1a9ec3e66ea1b1a064b005de914eac2e9d4f2d72a8616a802254"
+                "22918250ff66a41bd2f864a6a38cc5b6499dc43f7f2bd09e1e0f8f5885935124"
+            )
+        );
+    }
+
+    // the CAVP procedure for prediction resistance: instantiate, then generate twice with fresh
+    // entropy each time. the inputs are reused from the tests above; the expected output comes
+    // from a separate HMAC_DRBG written against Python's hmac module, not from the CAVP file.
+    #[test]
+    fn prediction_resistance_known_answer() {
+        let entropy = hex!(
+            "06032cd5eed33f39265f49ecb142c511da9aff2af71203bffaf34a9ca5bd9c0d"
+            "01920a4e669ed3a85ae8a33b35a74ad7fb2a6bb4cf395ce00334a9c9a5a5d552"
+            "ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488"
+        );
+        let nonce = hex!("0e66f71edc43e42a45ad3c6fc6cdc4df");
+        let mut drbg = HmacDrbg::instantiate(FixedEntropy(entropy.to_vec()), &nonce, &[], true);
+
+        let mut output = [0u8; 128];
+        drbg.generate(&mut output, &[]);
+        drbg.generate(&mut output, &[]);
+        assert_eq!(
+            output,
+            hex!(
+                "5ac01e99c086afab436584fde7a4a9b52cd894d55d8ea85c28534c0759c220a4"
+                "ef53ff13e12e5e9b49282ae45a5cb70de34fb4dfccf1ed620950ee25c56a78c6"
+                "aaff738a9bae055eb73871a23090b8484ae613965f7043b5da8851aaf835eb75"
+                "ae0177236de119641e4ced3a747adef882d436e13acdc82ee9e3ccf6b9edec8d"
+            )
+        );
+    }
+
+    #[test]
+    fn prediction_resistance_reseeds_every_call() {
+        let entropy: Vec<u8> = (0..3 * ENTROPY_BYTES as u8).collect();
+        let nonce = [0x42; NONCE_BYTES];
+
+        let mut with_pr = HmacDrbg::instantiate(FixedEntropy(entropy.clone()), &nonce, &[], true);
+        let mut without_pr = HmacDrbg::instantiate(FixedEntropy(entropy), &nonce, &[], false);
+
+        for additional in [&b"first"[..], &b"second"[..]] {
+            let mut a = [0u8; 64];
+            with_pr.generate(&mut a, additional);
+
+            let mut b = [0u8; 64];
+            without_pr.reseed(additional);
+            without_pr.generate(&mut b, &[]);
+
+            assert_eq!(a, b);
+        }
+        assert_eq!(with_pr.reseed_counter, 2);
+    }
+}
@@ -0,0 +1,714 @@
+use std::fmt;
+use std::sync::Mutex;
+
+use crypto_bigint::{Uint, U1024, U2048, U3072};
+use serde::{Deserialize, Serialize};
+
+use super::group::{uint_from_be_bytes, uint_to_be_bytes, Group, InvalidElement};
+use super::primality::is_probable_prime;
+use super::rfc6979::{bits2int, generate_k_until};
+use super::sha256::{self, Sha256};
+
+// DSA (FIPS 186-4) with SHA-256, for counterparts that don't accept Schnorr signatures. unlike the
+// key exchange groups, q is much smaller than p and only divides p - 1, so the domain parameters
+// are sent along with the keys. nonces are derived as in RFC 6979.
+
+// the (L, N) pairs of FIPS 186-4, section 4.2: the sizes of p and q in bits. 1024-bit p is only
+// there to verify old signatures.
+pub const SIZES: [(usize, usize); 4] = [(1024, 160), (2048, 224), (2048, 256), (3072, 256)];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvalidParams {
+    UnsupportedSize,
+    NotPrime,
+    WrongOrder,
+    BadGenerator,
+}
+
+impl fmt::Display for InvalidParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedSize => write!(f, "p and q are not of an approved size"),
+            Self::NotPrime => write!(f, "p or q is not prime"),
+            Self::WrongOrder => write!(f, "q does not divide p - 1"),
+            Self::BadGenerator => write!(f, "g does not generate the order-q subgroup"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidParams {}
+
+fn bit_len(bytes: &[u8]) -> usize {
+    match bytes.first() {
+        Some(&first) => bytes.len() * 8 - first.leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+// p, q and g, big-endian with p and g L / 8 bytes long and q N / 8 bytes long
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(Vec<u8>, Vec<u8>, Vec<u8>)")]
+pub struct DomainParams {
+    p: Vec<u8>,
+    q: Vec<u8>,
+    g: Vec<u8>,
+}
+
+// evaluates the body with `$group` bound to the Group for some domain parameters, with the integer
+// width that fits L
+macro_rules! with_dsa_group {
+    ($params:expr, |$group:ident| $body:expr) => {{
+        let params: &DomainParams = $params;
+        match params.sizes().0 {
+            1024 => {
+                let $group = &params.group::<{ U1024::LIMBS }>();
+                $body
+            }
+            2048 => {
+                let $group = &params.group::<{ U2048::LIMBS }>();
+                $body
+            }
+            _ => {
+                let $group = &params.group::<{ U3072::LIMBS }>();
+                $body
+            }
+        }
+    }};
+}
+
+// the digests of the domain parameters that passed the checks so far. they come along with every
+// key, and the primality tests would otherwise be repeated whenever one is deserialized.
+static CHECKED_PARAMS: Mutex<Vec<sha256::Digest>> = Mutex::new(Vec::new());
+
+impl TryFrom<(Vec<u8>, Vec<u8>, Vec<u8>)> for DomainParams {
+    type Error = InvalidParams;
+
+    fn try_from((p, q, g): (Vec<u8>, Vec<u8>, Vec<u8>)) -> Result<Self, Self::Error> {
+        let sizes = (bit_len(&p), bit_len(&q));
+        if !SIZES.contains(&sizes) || p.len() != sizes.0 / 8 || q.len() != sizes.1 / 8 {
+            return Err(InvalidParams::UnsupportedSize);
+        }
+        if g.len() != p.len() {
+            return Err(InvalidParams::BadGenerator);
+        }
+
+        let params = Self { p, q, g };
+        let digest = params.digest();
+        if CHECKED_PARAMS.lock().unwrap().contains(&digest) {
+            return Ok(params);
+        }
+
+        with_dsa_group!(&params, |group| params.check(group))?;
+        CHECKED_PARAMS.lock().unwrap().push(digest);
+        Ok(params)
+    }
+}
+
+impl DomainParams {
+    pub fn new(p: Vec<u8>, q: Vec<u8>, g: Vec<u8>) -> Result<Self, InvalidParams> {
+        Self::try_from((p, q, g))
+    }
+
+    // p, q and g have fixed lengths for given sizes, so they can simply be concatenated
+    fn digest(&self) -> sha256::Digest {
+        sha256::hash(&[&self.p[..], &self.q, &self.g].concat())
+    }
+
+    // (L, N)
+    pub fn sizes(&self) -> (usize, usize) {
+        (self.p.len() * 8, self.q.len() * 8)
+    }
+
+    // a group for a single operation. its fixed-base tables for g would take longer to build than
+    // they save, so g^x is computed with pow instead of pow_g here.
+    fn group<const LIMBS: usize>(&self) -> Group<LIMBS> {
+        Group::with_order(
+            "dsa",
+            uint_from_be_bytes(&self.p),
+            uint_from_be_bytes(&self.q),
+            uint_from_be_bytes(&self.g),
+        )
+    }
+
+    fn check<const LIMBS: usize>(&self, group: &Group<LIMBS>) -> Result<(), InvalidParams> {
+        if !is_probable_prime(group.p()) || !is_probable_prime(group.q()) {
+            return Err(InvalidParams::NotPrime);
+        }
+        if group.p().wrapping_sub(&Uint::ONE).wrapping_rem(group.q()) != Uint::ZERO {
+            return Err(InvalidParams::WrongOrder);
+        }
+
+        group
+            .check_subgroup(group.g())
+            .map_err(|_| InvalidParams::BadGenerator)
+    }
+
+    // big-endian and N / 8 bytes long, for anything smaller than q
+    fn encode_exponent<const LIMBS: usize>(&self, x: &Uint<LIMBS>) -> Vec<u8> {
+        let mut bytes = uint_to_be_bytes(x);
+        bytes.split_off(bytes.len() - self.q.len())
+    }
+
+    // the leftmost N bits of the message hash, reduced modulo q
+    fn hash_message<const LIMBS: usize>(
+        &self,
+        group: &Group<LIMBS>,
+        message: &[u8],
+    ) -> Uint<LIMBS> {
+        group.reduce_exponent(&bits2int(&sha256::hash(message), self.sizes().1))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Signature {
+    r: Vec<u8>,
+    s: Vec<u8>,
+}
+
+// g^x, together with the domain parameters
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(DomainParams, Vec<u8>)")]
+pub struct VerifyingKey {
+    params: DomainParams,
+    y: Vec<u8>,
+}
+
+impl TryFrom<(DomainParams, Vec<u8>)> for VerifyingKey {
+    type Error = InvalidElement;
+
+    fn try_from((params, y): (DomainParams, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_dsa_group!(&params, |group| group.check_subgroup(&group.decode(&y)?))?;
+        Ok(Self { params, y })
+    }
+}
+
+impl VerifyingKey {
+    pub fn params(&self) -> &DomainParams {
+        &self.params
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+        let params = &self.params;
+        if signature.r.len() != params.q.len() || signature.s.len() != params.q.len() {
+            return false;
+        }
+
+        with_dsa_group!(params, |group| {
+            let r = uint_from_be_bytes(&signature.r);
+            let s = uint_from_be_bytes(&signature.s);
+            if [r, s].iter().any(|x| x == &Uint::ZERO || x >= group.q()) {
+                return false;
+            }
+
+            // the public key was validated when it was created
+            let y = group.decode(&self.y).unwrap();
+            let z = params.hash_message(group, message);
+
+            let w = group.invert_exponent(&s);
+            let u1 = group.mul_exponents(&z, &w);
+            let u2 = group.mul_exponents(&r, &w);
+            let v = group.mul(&group.pow(group.g(), &u1), &group.pow(&y, &u2));
+            group.reduce_exponent(&v) == r
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct SigningKey {
+    secret: Vec<u8>,
+    public: VerifyingKey,
+}
+
+impl SigningKey {
+    pub fn generate(params: &DomainParams) -> Self {
+        with_dsa_group!(params, |group| {
+            Self::from_exponent(params, group, &group.generate_exponent())
+        })
+    }
+
+    // loads a private key, which has to be in [1, q - 1]
+    pub fn from_bytes(params: &DomainParams, secret: &[u8]) -> Result<Self, InvalidElement> {
+        if secret.len() != params.q.len() {
+            return Err(InvalidElement::WrongLength);
+        }
+
+        with_dsa_group!(params, |group| {
+            let x = uint_from_be_bytes(secret);
+            if x == Uint::ZERO || &x >= group.q() {
+                return Err(InvalidElement::OutOfRange);
+            }
+
+            Ok(Self::from_exponent(params, group, &x))
+        })
+    }
+
+    fn from_exponent<const LIMBS: usize>(
+        params: &DomainParams,
+        group: &Group<LIMBS>,
+        x: &Uint<LIMBS>,
+    ) -> Self {
+        Self {
+            secret: params.encode_exponent(x),
+            public: VerifyingKey {
+                params: params.clone(),
+                y: group.encode(&group.pow(group.g(), x)),
+            },
+        }
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.public
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let params = &self.public.params;
+        with_dsa_group!(params, |group| {
+            let x = uint_from_be_bytes(&self.secret);
+            let z = params.hash_message(group, message);
+
+            // r or s are only zero with negligible probability, but then another k is needed
+            generate_k_until::<Sha256, _, _>(group.q(), &x, &sha256::hash(message), |k| {
+                let r = group.reduce_exponent(&group.pow(group.g(), k));
+                let s = group.mul_exponents(
+                    &group.invert_exponent(k),
+                    &group.add_exponents(&z, &group.mul_exponents(&x, &r)),
+                );
+                if r == Uint::ZERO || s == Uint::ZERO {
+                    return None;
+                }
+
+                Some(Signature {
+                    r: params.encode_exponent(&r),
+                    s: params.encode_exponent(&s),
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // the DSA keys of RFC 6979, appendix A.2
+    fn rfc6979_1024() -> (DomainParams, Vec<u8>, Vec<u8>) {
+        let params = DomainParams::new(
+            hex!(
+                "86F5CA03DCFEB225063FF830A0C769B9DD9D6153AD91D7CE27F787C43278B447"
+                "E6533B86B18BED6E8A48B784A14C252C5BE0DBF60B86D6385BD2F12FB763ED88"
+                "73ABFD3F5BA2E0A8C0A59082EAC056935E529DAF7C610467899C77ADEDFC846C"
+                "881870B7B19B2B58F9BE0521A17002E3BDD6B86685EE90B3D9A1B02B782B1779"
+            )
+            .to_vec(),
+            hex!("996F967F6C8E388D9E28D01E205FBA957A5698B1").to_vec(),
+            hex!(
+                "07B0F92546150B62514BB771E2A0C0CE387F03BDA6C56B505209FF25FD3C133D"
+                "89BBCD97E904E09114D9A7DEFDEADFC9078EA544D2E401AEECC40BB9FBBF78FD"
+                "87995A10A1C27CB7789B594BA7EFB5C4326A9FE59A070E136DB77175464ADCA4"
+                "17BE5DCE2F40D10A46A3A3943F26AB7FD9C0398FF8C76EE0A56826A8A88F1DBD"
+            )
+            .to_vec(),
+        )
+        .unwrap();
+        let x = hex!("411602CB19A6CCC34494D79D98EF1E7ED5AF25F7").to_vec();
+        let y = hex!(
+            "5DF5E01DED31D0297E274E1691C192FE5868FEF9E19A84776454B100CF16F653"
+            "92195A38B90523E2542EE61871C0440CB87C322FC4B4D2EC5E1E7EC766E1BE8D"
+            "4CE935437DC11C3C8FD426338933EBFE739CB3465F4D3668C5E473508253B1E6"
+            "82F65CBDC4FAE93C2EA212390E54905A86E2223170B44EAA7DA5DD9FFCFB7F3B"
+        )
+        .to_vec();
+
+        (params, x, y)
+    }
+
+    fn rfc6979_2048() -> (DomainParams, Vec<u8>, Vec<u8>) {
+        let params = DomainParams::new(
+            hex!(
+                "9DB6FB5951B66BB6FE1E140F1D2CE5502374161FD6538DF1648218642F0B5C48"
+                "C8F7A41AADFA187324B87674FA1822B00F1ECF8136943D7C55757264E5A1A44F"
+                "FE012E9936E00C1D3E9310B01C7D179805D3058B2A9F4BB6F9716BFE6117C6B5"
+                "B3CC4D9BE341104AD4A80AD6C94E005F4B993E14F091EB51743BF33050C38DE2"
+                "35567E1B34C3D6A5C0CEAA1A0F368213C3D19843D0B4B09DCB9FC72D39C8DE41"
+                "F1BF14D4BB4563CA28371621CAD3324B6A2D392145BEBFAC748805236F5CA2FE"
+                "92B871CD8F9C36D3292B5509CA8CAA77A2ADFC7BFD77DDA6F71125A7456FEA15"
+                "3E433256A2261C6A06ED3693797E7995FAD5AABBCFBE3EDA2741E375404AE25B"
+            )
+            .to_vec(),
+            hex!("F2C3119374CE76C9356990B465374A17F23F9ED35089BD969F61C6DDE9998C1F").to_vec(),
+            hex!(
+                "5C7FF6B06F8F143FE8288433493E4769C4D988ACE5BE25A0E24809670716C613"
+                "D7B0CEE6932F8FAA7C44D2CB24523DA53FBE4F6EC3595892D1AA58C4328A06C4"
+                "6A15662E7EAA703A1DECF8BBB2D05DBE2EB956C142A338661D10461C0D135472"
+                "085057F3494309FFA73C611F78B32ADBB5740C361C9F35BE90997DB2014E2EF5"
+                "AA61782F52ABEB8BD6432C4DD097BC5423B285DAFB60DC364E8161F4A2A35ACA"
+                "3A10B1C4D203CC76A470A33AFDCBDD92959859ABD8B56E1725252D78EAC66E71"
+                "BA9AE3F1DD2487199874393CD4D832186800654760E1E34C09E4D155179F9EC0"
+                "DC4473F996BDCE6EED1CABED8B6F116F7AD9CF505DF0F998E34AB27514B0FFE7"
+            )
+            .to_vec(),
+        )
+        .unwrap();
+        let x = hex!("69C7548C21D0DFEA6B9A51C9EAD4E27C33D3B3F180316E5BCAB92C933F0E4DBC").to_vec();
+        let y = hex!(
+            "667098C654426C78D7F8201EAC6C203EF030D43605032C2F1FA937E5237DBD94"
+            "9F34A0A2564FE126DC8B715C5141802CE0979C8246463C40E6B6BDAA2513FA61"
+            "1728716C2E4FD53BC95B89E69949D96512E873B9C8F8DFD499CC312882561ADE"
+            "CB31F658E934C0C197F2C4D96B05CBAD67381E7B768891E4DA3843D24D94CDFB"
+            "5126E9B8BF21E8358EE0E0A30EF13FD6A664C0DCE3731F7FB49A4845A4FD8254"
+            "687972A2D382599C9BAC4E0ED7998193078913032558134976410B89D2C171D1"
+            "23AC35FD977219597AA7D15C1A9A428E59194F75C721EBCBCFAE44696A499AFA"
+            "74E04299F132026601638CB87AB79190D4A0986315DA8EEC6561C938996BEADF"
+        )
+        .to_vec();
+
+        (params, x, y)
+    }
+
+    fn check_vector(
+        (params, x, y): &(DomainParams, Vec<u8>, Vec<u8>),
+        message: &[u8],
+        r: &[u8],
+        s: &[u8],
+    ) {
+        let key = SigningKey::from_bytes(params, x).unwrap();
+        assert_eq!(key.verifying_key().y, *y);
+
+        let signature = key.sign(message);
+        assert_eq!((&*signature.r, &*signature.s), (r, s));
+        assert!(key.verifying_key().verify(message, &signature));
+    }
+
+    // RFC 6979, appendix A.2.1, with SHA-256
+    #[test]
+    fn rfc6979_vectors_1024() {
+        let key = rfc6979_1024();
+        check_vector(
+            &key,
+            b"sample",
+            &hex!("81F2F5850BE5BC123C43F71A3033E9384611C545"),
+            &hex!("4CDD914B65EB6C66A8AAAD27299BEE6B035F5E89"),
+        );
+        check_vector(
+            &key,
+            b"test",
+            &hex!("22518C127299B0F6FDC9872B282B9E70D0790812"),
+            &hex!("6837EC18F150D55DE95B5E29BE7AF5D01E4FE160"),
+        );
+    }
+
+    // RFC 6979, appendix A.2.2, with SHA-256
+    #[test]
+    fn rfc6979_vectors_2048() {
+        let key = rfc6979_2048();
+        check_vector(
+            &key,
+            b"sample",
+            &hex!("EACE8BDBBE353C432A795D9EC556C6D021F7A03F42C36E9BC87E4AC7932CC809"),
+            &hex!("7081E175455F9247B812B74583E9E94F9EA79BD640DC962533B0680793A38D53"),
+        );
+        check_vector(
+            &key,
+            b"test",
+            &hex!("8190012A1969F9957D56FCCAAD223186F423398D58EF5B3CEFD5A4146A4476F0"),
+            &hex!("7452A53F7075D417B4B013B278D1BB8BBD21863F5E7B1CEE679CF2188E1AB19E"),
+        );
+    }
+
+    // a message, r, s, and whether they verify
+    type SigVerCase<'a> = (&'a [u8], &'a [u8], &'a [u8], bool);
+
+    // checks a key and signatures of SHA-256 message hashes against whether they should verify
+    fn check_sig_ver(p: &[u8], q: &[u8], g: &[u8], y: &[u8], cases: &[SigVerCase]) {
+        let params = DomainParams::new(p.into(), q.into(), g.into()).unwrap();
+        let public = VerifyingKey::try_from((params.clone(), y.to_vec())).unwrap();
+        for &(message, r, s, valid) in cases {
+            let signature = Signature {
+                r: r.into(),
+                s: s.into(),
+            };
+            assert_eq!(public.verify(message, &signature), valid);
+        }
+
+        // a valid signature doesn't verify under another key
+        let (message, r, s, _) = cases[0];
+        let other = with_dsa_group!(&params, |group| {
+            group.encode(&group.mul(&group.decode(y).unwrap(), group.g()))
+        });
+        let other = VerifyingKey::try_from((params, other)).unwrap();
+        let signature = Signature {
+            r: r.into(),
+            s: s.into(),
+        };
+        assert!(!other.verify(message, &signature));
+    }
+
+    // the sizes with SHA-256 that FIPS 186-3's SigVer test vectors cover: a valid signature, then
+    // ones with the message, r or s changed, as in their failure cases. made and checked with
+    // OpenSSL, through Python's cryptography package.
+    #[test]
+    fn sig_ver_2048_224() {
+        check_sig_ver(
+            &hex!(
+                "B6E999E28C98CF5910F590481F7EF3D89CD2B82B6ECBB9A60B8ED3C34A106ABB"
+                "BBE5925137E34B1F53C85E21C393C233AF44F6D50F0C6B600B9CB3D169F0D05C"
+                "6A007466C20C952053677A81F923B7B020D81CD2DBD48DCA6E800141A7FFD399"
+                "D785D2CF53AEC142DC65BC306D0DACC87F80C39D30E04B8A901F8FD5C488BD37"
+                "D09BC75877243DC584707C25DEF349F25FA093DFC6A3F5CD44C0987782EBBBCF"
+                "F7AD935DBCD5C3B2E93C521F8DD54705338B65B2239C3477B65B9AC2118D580E"
+                "71A4D15ABC2009DF7EFBDFB790AD318BF61CDDCCAEFA253690B346D7E696E6D6"
+                "209BCC6D880BF8EC769228EB5B333B9F5AD7B4B0CDDD87F1C3BD215F0C4CAAB9"
+            ),
+            &hex!("F16874F2927A95AD15BE115DBB66A32064F54DFB293D04A0C37ED651"),
+            &hex!(
+                "A506FE0317F373B84F186FC21A4B8C37D9C8B4390CCF4ABB37FAD201DDBF97AA"
+                "75275AC5A1F10BB538CD9DEABE880FFBBA9DFD361BFE89F85E8A169376ABB0AB"
+                "3040FED1D9479A6C3C4E91EB3B765B1868F4BACC5A8B340D7FB355D1757F46BC"
+                "3A95FF42A9E880780C06D1E0AD08FF8283FEC5A4CACAF023AA206490A9A0E7BF"
+                "C29C77B3CA7501D559B7A32D222CF28E0135F68C9FADBC744552CC2227D10BFD"
+                "B3D583466CF9AA707CD9F44BF1499E8E3B3F0F1089E64400FD447DB7C6B39059"
+                "F1E26EE48C835B908B2F3BA447727EF95676585281F3252F7B4BB6E228C292DD"
+                "B3A88542F76FDB670D7E29E0648AC3C77E2F542DD634D74FEB33F617EE1A96AE"
+            ),
+            &hex!(
+                "5004280A8CBE6988B23DE45885F23B9B062628D90384A2C9B35E33B73C3CCCBB"
+                "7C1394AC1F391DE6EE8E7ECB5A4DDCEA9198EB150C86F9C7A242AC368C2DA5F1"
+                "1943527D5181B827F8A94ACBAB958B06BF3A0D0D3962F36BB9C06086C0B53BF3"
+                "63DA0C46E11AFC3B218A3F7D151A342EFD717D1181A2E8D74F8A88A3F40B41C1"
+                "03964FDD5C1B2E31292F0713CFF65843F3C1C7CE005C5F805B32E6BFB08AACAE"
+                "4A38DE639D9F078369787F83464782A032993A60DCC36D4CC19644FA932FC61F"
+                "469D8E4BEA6D5604A133EED4ACDAAC195414F85999325D7050130B05AFECB26C"
+                "2DC85491FC70CBF4953FF57E51C082EB3730BCD6E3DFE43AB6CB3D378156BF70"
+            ),
+            &[
+                (
+                    &hex!("5CDA18618C66BC150A0751E89347B288"),
+                    &hex!("D6ED1189364542D10D9E95CFD9BE89368D7715BB963421182F937E09"),
+                    &hex!("3EDD697791E3B8F60C791FA03B9F4F9AF43CBC47A711DF8E687149BA"),
+                    true,
+                ),
+                (
+                    &hex!("432BFA53DAF622EDAB4D0BA22D04D7FA"),
+                    &hex!("711168BCD51CFBA3449FB5506F9D0726A8ED953AA4EDE7CFA572E42D"),
+                    &hex!("A8409F43924D41A80F5D547EBDDE431D1FA1E98940F907084FEDF99A"),
+                    false,
+                ),
+                (
+                    &hex!("78FA8782AF36BF6843F06691C3AEFFE7"),
+                    &hex!("AA8F13FD16E58E45951C7201938DECE2AE15DA5352911962C9325AB1"),
+                    &hex!("301FAD30E3DB09096B720EF66FA11D28BAAC0AB01FF2822F1F8BD9F5"),
+                    false,
+                ),
+                (
+                    &hex!("1713CD7E082688DF5D32534DBE7C6953"),
+                    &hex!("CEEB48C0B00CD52DAEBBE9A2582275F0921ED37413B37BC4B347A89C"),
+                    &hex!("D3218C41329BA47C3BD2107A8410B38A0051D06E9C6DEC71168562C6"),
+                    false,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn sig_ver_2048_256() {
+        check_sig_ver(
+            &hex!(
+                "848D0191A525D83D65DF960B85CE7B704502E7DEBB5D4F813C2C4694169F65A8"
+                "291993E523DD582724B57BC17D22C4B32C4B5668B6B180C195486CBE201C06AD"
+                "4DD4997166602FF4104CA892E41998C4FFB64C1FA4579E63A4A047F1EB22C068"
+                "0F67D17F90F7831A07F09FC89516F058815700CF8F4BEC58C86E1C98F2AC86F1"
+                "BC6D798FE9DA6761C1905F29A5D9478D9763CC073A5DBD8ECAE0718F2E9E3433"
+                "53C05959CB897EC70EFE34F6E07204CBBD776D7A9D40045316ED1BC1C3FA6217"
+                "44CCE508B5F013C4A94F8816EAF571F6D050EE28642AAD0EFDAA2574B94A9F28"
+                "9615BD21C066447FCD110A6A1E1E900F320AA4E71FFC190C904ED57F1B8DE033"
+            ),
+            &hex!("A86383A3C45ADFD62BC34192BFB6E47466338DE33AB17A87DAAF71040B7A1E7B"),
+            &hex!(
+                "78EC1D7BF39B0FD4CFE44027164BCC10FEC798F0FE6779975A131409FFAF3B2D"
+                "C5086B8C33FA28A2A7B95A2A1FDD76C419DA71DFA7A03A237D890B6E7F62E4D6"
+                "4A03C375955264A81F86782D8BF6DB4C836646B13B06BCE9436741C5B82E6303"
+                "DE455B74C3A4A77F26282E9999E2F8270D5730472A697870D0D5C96BE49B2613"
+                "F857F4B59FD05E6DAE4CEBD7270C33790A792EDD055D129FD6C0FFE1CCB0B8AE"
+                "0DCB914F3FAE297F5922869587B98D0FB2269A564E8530AC57CC16C1769AC597"
+                "56A414B28DA446890BA649BC3DCDF38DEE12938770EC0A7972F9607847CDC70B"
+                "D2D40573F20DB9B00D7055C720E5177A851A2815533A94E4A78751BDEB5B218B"
+            ),
+            &hex!(
+                "1A6E9359A35D5BC7A765645F6818315EF0FAD3973DA9E96B24B80B8BC21D72E3"
+                "B989245FF580A01982266175E38CE929750765D1F55CC042AAA7CD6407F8D9F1"
+                "B635D1D3A538693ADFABD5C1A03F404B153B7DB987DB4F1FA8F60620744F770F"
+                "56B56E54CA74712D2AC2D4AE26EB6B438AE7A266E32FF5FAEA14639DF8BADEF5"
+                "47EA5F4BEBED963ACA881CAA19CAEBE061193A1C211127DB1507960E8BA68CE1"
+                "0A0707BB89BBF4DA451675F7A5D0A7C147059337F76648D196B75B848CB5D120"
+                "36FCFBB91F3F363CCA1739D18F7298E14782F35244C23E017DFEE971FA62AF6B"
+                "680A915F4DC33533B9AD5B082ED634DC7601D762F810A2B6E0AADAE67D0E4BDB"
+            ),
+            &[
+                (
+                    &hex!("96B76EA00D29A3AAD8C9819F2DFAB3CD"),
+                    &hex!("95AA362DDB342D93D44434A24EDFD1819D04DCF7E1F2EE90C29CA3BC6C89B17A"),
+                    &hex!("636B27252FB82D2B62E32E9132BC433331C7C1E099B7320ADD2E77C4658B7583"),
+                    true,
+                ),
+                (
+                    &hex!("927C3CA61FD2D3C343EDA5010F2E5D46"),
+                    &hex!("9C6177753A97266CE1E07135BA040C25046727D1CF1DA4CBA08DDBB299698E68"),
+                    &hex!("85D91E44B6922415EFFFBABDBC20C59D6651DE71A4D6FE7339CFEA7E446EECA8"),
+                    false,
+                ),
+                (
+                    &hex!("1A3191AE7EEEACE5BA86629FA6962B41"),
+                    &hex!("14CB817B70D168AC55C527A5ACF62C186EF9D74E7138F7522EA40FDA641EF05B"),
+                    &hex!("2EF35F95FE77852B8740B566D9D33A7BDC87B0247A2D67B108A2E49F3A5FADD0"),
+                    false,
+                ),
+                (
+                    &hex!("01E190C3CD4F9A2668B96DBF674DBC2C"),
+                    &hex!("970B6288105790AC79DD8DADBBDFF34C9C1DEB2CCB39839FFB0C5CD713E13BB6"),
+                    &hex!("422F7C7645943E042DA5F3337096F7507E9139EBFFFBD1DFC0EEC899109342C4"),
+                    false,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn sig_ver_3072_256() {
+        check_sig_ver(
+            &hex!(
+                "FE9B1D12D8ABAC7018D7722B852B6B17F7C7378F262803F92CE104C6BAA08BAA"
+                "DA33A11BF534EAC8D703861936FA78C0BAADBA08D3415C8EBDBCC171C9E08C20"
+                "0E4C72CCA9B834E45F3C239A40AE62723D9500C98A761BAD6F6F91D6CA3488BF"
+                "93A4CA4D9B2006D422A92075AADDA3FC55F009F05C3848424C4DC905072F8CE9"
+                "F5BE430A7BB6FEF7DFDA74AC8A581CDD377EC4A1B1B988C2AE86D3275391728A"
+                "81E1BF03CB883F9BC624CD284A977F71D2A2E408F424A8DF0A8CD7C07E6DFDFC"
+                "A0493711360626447F9B56291C2E55A9DDE2938CF7483B9D567E31C58AF5FC4C"
+                "6DE403BEE0CB00CD882CAE8BFDE936F66B3823F9CEA26484CE2289DAC421B8AE"
+                "B16D02935B5FB560EBCA67011F1E7435D5729EAE61EE9F5443939DD53B66C3A9"
+                "5F24E09BDB813FCEB3A0747450BDB2A17A0C6FE31D402ACAF25993700A77F0EE"
+                "B7AF1C4A2A5738802562CD375DB548A5D05793C6FC3354E4A672BC3A9DFE5AD2"
+                "566204BACAADB384032C9D40E82A0A25C28C5D9512B5FB81279DC7C61641DA4F"
+            ),
+            &hex!("A1160EF73E82A924E563EA065939C00F23E811EA50E802B01511C5734E169859"),
+            &hex!(
+                "2ACF87FF58E6558621D0018FC12BBB72B876A3D2CF8DD0EE0994E7298C9C462F"
+                "109DCBE4696D7F5C6731F803580FEA0C8E7877D1FB27BF186DD204CF34A7071D"
+                "F46278A7F29511C0CB25A789CC1B8454F6DA22D4AC0BF000E5D4654D725E480A"
+                "CD0CCE0641FDF715FF08907B21E95418C50A1C077A3EC34E5CE6F8857D521AFB"
+                "BA5DCA1271032757A09F77B14B24CC5A4E7A9844E663A57E73FF953313A00210"
+                "9A156EDB51F3CED06CDB3DFB97CD66612900A412D41B86E305BB6AD1E924B15D"
+                "710FC8CE546B37BBDE186F18951073E14B26C15EF8B8F90573EC1E16CE689E3B"
+                "32E01498566387BF0C0749D9E7A8545DA9C7A0C3F09A82BB35A264F7E892B99F"
+                "0D027EE370ED3566514009B78165B4B8F7BC75FC2CD9A2566A1348EC740ACDC8"
+                "2C05AFBA12C40DADE3DB93EAD5C60C039166BFFDD706DAB49A4FCAEE6DF118E2"
+                "E2C65968DEF263E1B404B930F72922E8988EDAD7B2B9E5FFD04EE7849A4BAB32"
+                "C01A8B6DF941E6DD86243F61BB8A35DAA9A0B31B72D8C42CC1D0B4421884D89C"
+            ),
+            &hex!(
+                "1CAA51980AE9239DE9A332CDBF0B9D9F2F7B92CF1E78E6D8D5F4A3122B6B5259"
+                "9617CAA321EFFE4B284DA6945E20AD9E93E651AE5AAAD4B1706E46D209FCE200"
+                "081027FD113F65EB6F3635A3B1C0E6ED0E6D511C00C7B67F83D7CE0824D940C2"
+                "664BBB13BB7F6C08F4C2C420D351EFCEE63BC2DF75F99F814D55124521FB1DC4"
+                "2672B647D1DE7D1664C3170D2E55AC8E63F309281B4A468CB55F4AC95764E882"
+                "331C170286A34A3315194A579ADEF8FDC4C36812FF9917777599828FD1B53097"
+                "892A15053B59D76F31AB30BD6E539717FF96F316F0C5679B55C27EEDE4E9F73D"
+                "85E7AC61AED8DCFFB3041147DB48ADC8BEF1585B89445F08CAA23EED4E939373"
+                "1061061C60A1A2D51973570D920697CF621AD0BCEE6339025A185115DFB1480E"
+                "5E4F849A6961338B7BCEFA8E646C194F5C3601F4A21890669582B61C174BE347"
+                "15EADBCAA22488B6C7B656330342AE6CE721694670FDFB1E681E9E43C3A7E943"
+                "9BC2A8F4C2978291ACD86635D36895A54DAFA769EF334937C146A753091F81EC"
+            ),
+            &[
+                (
+                    &hex!("416C17E2345303D36322DA760DC98C06"),
+                    &hex!("1730E56C59AAE77DC5C0D712F464619D371FFE5133D107439C984B575073CB9D"),
+                    &hex!("00F5F25DC7828E51487E6ADFFBE46AEA183242A3A0F37306B833700DD1ADB718"),
+                    true,
+                ),
+                (
+                    &hex!("B129947F26224714F0A3D663FAE5CDB8"),
+                    &hex!("885340E546077A8D00228A4382ECE198B4A5373AE380D807508153859CAD8506"),
+                    &hex!("9E2627A349A2F5567AEADCAE3452B85FC66581D46D0BF7D34E1F0FAC24E9EE16"),
+                    false,
+                ),
+                (
+                    &hex!("2CCB95D4E20FA4E44DA0EDFA0266090B"),
+                    &hex!("0ABD93B26FFBC3AB8801A103BBEFC3C80C4CBFCCE82FDBE6D008610C555156E4"),
+                    &hex!("3BB08C87A62EAD2AA0CCB95C9F078C1719DBB53AB43D0C62A1D904BED2FAAA9C"),
+                    false,
+                ),
+                (
+                    &hex!("9E5D1609EDF0C120D12506399449AC62"),
+                    &hex!("5CF9BD2F2D279631B966ADF9BC8734A258A21314A368008DD3E963DBBA06816B"),
+                    &hex!("2A3227F49CFC741001C6B903911722B811ED5A4C66141D1B69C8A0D8E4D8FA87"),
+                    false,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn rejects_forgeries() {
+        let (params, _, _) = rfc6979_2048();
+        let key = SigningKey::generate(&params);
+        let public = key.verifying_key();
+        let signature = key.sign(b"Hello, World!");
+        assert!(public.verify(b"Hello, World!", &signature));
+        assert!(!public.verify(b"Hello, World?", &signature));
+
+        let mut tampered = signature.clone();
+        tampered.s[0] ^= 1;
+        assert!(!public.verify(b"Hello, World!", &tampered));
+        let zero = Signature {
+            r: vec![0; 32],
+            s: signature.s.clone(),
+        };
+        assert!(!public.verify(b"Hello, World!", &zero));
+        let q = Signature {
+            r: params.q.clone(),
+            s: signature.s.clone(),
+        };
+        assert!(!public.verify(b"Hello, World!", &q));
+    }
+
+    #[test]
+    fn validates_params() {
+        let (params, _, _) = rfc6979_2048();
+        let (small, _, _) = rfc6979_1024();
+        let new = |p: &[u8], q: &[u8], g: &[u8]| DomainParams::new(p.into(), q.into(), g.into());
+
+        // (2048, 160) isn't an approved size
+        assert_eq!(
+            new(&params.p, &small.q, &params.g),
+            Err(InvalidParams::UnsupportedSize)
+        );
+        let mut not_prime = params.q.clone();
+        *not_prime.last_mut().unwrap() ^= 2;
+        assert_eq!(
+            new(&params.p, &not_prime, &params.g),
+            Err(InvalidParams::NotPrime)
+        );
+        // the P-256 field prime is a 256-bit prime, but doesn't divide p - 1
+        let p256 = hex!("FFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFF");
+        assert_eq!(
+            new(&params.p, &p256, &params.g),
+            Err(InvalidParams::WrongOrder)
+        );
+        let mut one = vec![0; params.g.len()];
+        *one.last_mut().unwrap() = 1;
+        assert_eq!(
+            new(&params.p, &params.q, &one),
+            Err(InvalidParams::BadGenerator)
+        );
+
+        // the checks only run the first time
+        assert!(CHECKED_PARAMS.lock().unwrap().contains(&params.digest()));
+        let bytes = bincode::serialize(&params).unwrap();
+        assert_eq!(
+            bincode::deserialize::<DomainParams>(&bytes).ok(),
+            Some(params)
+        );
+    }
+}
@@ -0,0 +1,641 @@
+use std::fmt;
+use std::sync::LazyLock;
+
+use crypto_bigint::{Encoding, NonZero, U256};
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::drbg::ThreadDrbg;
+use super::field25519::{Fe, BYTES};
+use super::hash::HashFunction;
+use super::sha512::{self, Sha512};
+
+// Ed25519 (RFC 8032): Schnorr-style signatures on the twisted Edwards curve
+// -x^2 + y^2 = 1 + d x^2 y^2 over GF(2^255 - 19), which is birationally equivalent to the curve
+// of X25519. keys are 32 bytes and signatures 64, and both the key expansion and the nonce come
+// from SHA-512 of the private seed, so signing needs no randomness.
+//
+// verification is strict: non-canonical encodings, S >= L and points of small order are all
+// rejected, so every message has exactly one valid signature per key and nonce. only points that
+// are entirely torsion count as small order; a key with a torsion component on top of a proper
+// one (mixed order) is accepted, as RFC 8032 allows.
+
+pub const PUBLIC_KEY_BYTES: usize = BYTES;
+pub const SIGNATURE_BYTES: usize = 2 * BYTES;
+
+// the order of the base point, 2^252 + 27742317777372353535851937790883648493
+const L: U256 =
+    U256::from_be_hex("1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed");
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvalidEncoding {
+    // a coordinate or scalar that isn't fully reduced
+    NonCanonical,
+    NotOnCurve,
+    SmallOrder,
+}
+
+impl fmt::Display for InvalidEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonCanonical => write!(f, "value is not in canonical form"),
+            Self::NotOnCurve => write!(f, "point is not on the curve"),
+            Self::SmallOrder => write!(f, "point has small order"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidEncoding {}
+
+struct Constants {
+    d: Fe,
+    d2: Fe,
+    sqrt_minus_one: Fe,
+    base: Point,
+}
+
+static CONSTANTS: LazyLock<Constants> = LazyLock::new(|| {
+    // -121665 / 121666
+    let d = Fe::from_u64(121665)
+        .neg()
+        .mul(&Fe::from_u64(121666).invert());
+    let mut constants = Constants {
+        d,
+        d2: d.add(&d),
+        sqrt_minus_one: Fe::sqrt_minus_one(),
+        base: Point::IDENTITY,
+    };
+
+    // y = 4/5, with the positive x
+    let mut base = [0x66; BYTES];
+    base[0] = 0x58;
+    constants.base = Point::decompress_with(&constants, &base).unwrap();
+
+    constants
+});
+
+// a point in extended coordinates: x = X/Z, y = Y/Z and xy = T/Z
+#[derive(Clone, Copy)]
+struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+impl Point {
+    const IDENTITY: Self = Self {
+        x: Fe::ZERO,
+        y: Fe::ONE,
+        z: Fe::ONE,
+        t: Fe::ZERO,
+    };
+
+    // RFC 8032, section 5.1.3
+    fn decompress_with(
+        constants: &Constants,
+        bytes: &[u8; BYTES],
+    ) -> Result<Self, InvalidEncoding> {
+        let sign = bytes[31] >> 7 == 1;
+        let mut y_bytes = *bytes;
+        y_bytes[31] &= 0x7f;
+        let y = Fe::from_bytes(&y_bytes);
+        if y.to_bytes() != y_bytes {
+            return Err(InvalidEncoding::NonCanonical);
+        }
+
+        // x^2 = u / v, and the candidate root is u v^3 (u v^7)^((p - 5) / 8)
+        let yy = y.square();
+        let u = yy.sub(&Fe::ONE);
+        let v = constants.d.mul(&yy).add(&Fe::ONE);
+        let v3 = v.square().mul(&v);
+        let v7 = v3.square().mul(&v);
+        let mut x = u.mul(&v3).mul(&u.mul(&v7).pow_p58());
+
+        let vxx = v.mul(&x.square());
+        if vxx == u.neg() {
+            x = x.mul(&constants.sqrt_minus_one);
+        } else if vxx != u {
+            return Err(InvalidEncoding::NotOnCurve);
+        }
+
+        if x == Fe::ZERO && sign {
+            return Err(InvalidEncoding::NonCanonical);
+        }
+        if x.is_negative() != sign {
+            x = x.neg();
+        }
+
+        Ok(Self {
+            x,
+            y,
+            z: Fe::ONE,
+            t: x.mul(&y),
+        })
+    }
+
+    fn decompress(bytes: &[u8; BYTES]) -> Result<Self, InvalidEncoding> {
+        Self::decompress_with(&CONSTANTS, bytes)
+    }
+
+    fn compress(&self) -> [u8; BYTES] {
+        let z_inv = self.z.invert();
+        let x = self.x.mul(&z_inv);
+        let mut bytes = self.y.mul(&z_inv).to_bytes();
+        bytes[31] |= (x.is_negative() as u8) << 7;
+        bytes
+    }
+
+    // the unified addition formula for a = -1 ("add-2008-hwcd-3"), which also doubles
+    fn add(&self, other: &Self) -> Self {
+        let a = self.y.sub(&self.x).mul(&other.y.sub(&other.x));
+        let b = self.y.add(&self.x).mul(&other.y.add(&other.x));
+        let c = self.t.mul(&CONSTANTS.d2).mul(&other.t);
+        let d = self.z.add(&self.z).mul(&other.z);
+        let (e, f, g, h) = (b.sub(&a), d.sub(&c), d.add(&c), b.add(&a));
+
+        Self {
+            x: e.mul(&f),
+            y: g.mul(&h),
+            z: f.mul(&g),
+            t: e.mul(&h),
+        }
+    }
+
+    fn neg(&self) -> Self {
+        Self {
+            x: self.x.neg(),
+            t: self.t.neg(),
+            ..*self
+        }
+    }
+
+    // double-and-always-add, so that the scalar doesn't leak through the sequence of operations
+    fn mul(&self, scalar: &[u8; BYTES]) -> Self {
+        let mut acc = Self::IDENTITY;
+        for i in (0..256).rev() {
+            acc = acc.add(&acc);
+            let mut sum = acc.add(self);
+            let bit = u64::from((scalar[i / 8] >> (i % 8)) & 1);
+            Fe::conditional_swap(&mut acc.x, &mut sum.x, bit);
+            Fe::conditional_swap(&mut acc.y, &mut sum.y, bit);
+            Fe::conditional_swap(&mut acc.z, &mut sum.z, bit);
+            Fe::conditional_swap(&mut acc.t, &mut sum.t, bit);
+        }
+
+        acc
+    }
+
+    fn is_identity(&self) -> bool {
+        self.x == Fe::ZERO && self.y == self.z
+    }
+
+    // whether the point is in the torsion subgroup, i.e. killed by the cofactor 8
+    fn is_small_order(&self) -> bool {
+        let mut p = *self;
+        for _ in 0..3 {
+            p = p.add(&p);
+        }
+        p.is_identity()
+    }
+}
+
+fn base_mul(scalar: &[u8; BYTES]) -> Point {
+    CONSTANTS.base.mul(scalar)
+}
+
+// a 512-bit little-endian hash, reduced modulo L
+fn reduce(hash: &sha512::Digest) -> U256 {
+    let lo = U256::from_le_slice(&hash[..BYTES]);
+    let hi = U256::from_le_slice(&hash[BYTES..]);
+    U256::const_rem_wide((lo, hi), &L).0
+}
+
+// SHA-512(dom || ...), reduced modulo L. plain Ed25519 has an empty dom.
+fn hash_to_scalar(parts: &[&[u8]]) -> U256 {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    reduce(&hasher.finalize())
+}
+
+// A, the encoding of a point of large order
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "[u8; PUBLIC_KEY_BYTES]")]
+pub struct VerifyingKey([u8; PUBLIC_KEY_BYTES]);
+
+impl TryFrom<[u8; PUBLIC_KEY_BYTES]> for VerifyingKey {
+    type Error = InvalidEncoding;
+
+    fn try_from(bytes: [u8; PUBLIC_KEY_BYTES]) -> Result<Self, Self::Error> {
+        if Point::decompress(&bytes)?.is_small_order() {
+            return Err(InvalidEncoding::SmallOrder);
+        }
+        Ok(Self(bytes))
+    }
+}
+
+// (R, S), with S < L
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "([u8; BYTES], [u8; BYTES])")]
+pub struct Signature([u8; BYTES], [u8; BYTES]);
+
+impl TryFrom<([u8; BYTES], [u8; BYTES])> for Signature {
+    type Error = InvalidEncoding;
+
+    fn try_from((r, s): ([u8; BYTES], [u8; BYTES])) -> Result<Self, Self::Error> {
+        if U256::from_le_slice(&s) >= L {
+            return Err(InvalidEncoding::NonCanonical);
+        }
+        Ok(Self(r, s))
+    }
+}
+
+impl Signature {
+    pub fn from_bytes(bytes: &[u8; SIGNATURE_BYTES]) -> Result<Self, InvalidEncoding> {
+        let (r, s) = bytes.split_at(BYTES);
+        Self::try_from((r.try_into().unwrap(), s.try_into().unwrap()))
+    }
+
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_BYTES] {
+        bytemuck::must_cast([self.0, self.1])
+    }
+}
+
+impl VerifyingKey {
+    pub fn as_bytes(&self) -> &[u8; PUBLIC_KEY_BYTES] {
+        &self.0
+    }
+
+    // checks [S]B = R + [k]A, with k = H(R || A || m)
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+        let Signature(r, s) = signature;
+        let Ok(r_point) = Point::decompress(r) else {
+            return false;
+        };
+        if r_point.is_small_order() {
+            return false;
+        }
+
+        // the key was validated when it was created
+        let a = Point::decompress(&self.0).unwrap();
+        let k = hash_to_scalar(&[r, &self.0, message]);
+
+        // R is canonical, so comparing encodings compares the points
+        let expected = base_mul(s).add(&a.neg().mul(&k.to_le_bytes()));
+        expected.compress() == *r
+    }
+}
+
+// the 32-byte seed from which everything else is derived
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(from = "[u8; BYTES]", into = "[u8; BYTES]")]
+pub struct SigningKey {
+    seed: [u8; BYTES],
+    // the clamped scalar a, and the prefix that nonces are derived from
+    scalar: U256,
+    prefix: [u8; BYTES],
+    public: VerifyingKey,
+}
+
+impl From<[u8; BYTES]> for SigningKey {
+    fn from(seed: [u8; BYTES]) -> Self {
+        let hash = sha512::hash(&seed);
+        let mut scalar: [u8; BYTES] = hash[..BYTES].try_into().unwrap();
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+
+        // a multiple of 8 below L times 8 gives a point of large order
+        let public = VerifyingKey(base_mul(&scalar).compress());
+        let scalar = U256::from_le_slice(&scalar).rem(&NonZero::new(L).unwrap());
+
+        Self {
+            seed,
+            scalar,
+            prefix: hash[BYTES..].try_into().unwrap(),
+            public,
+        }
+    }
+}
+
+impl From<SigningKey> for [u8; BYTES] {
+    fn from(key: SigningKey) -> Self {
+        key.seed
+    }
+}
+
+impl SigningKey {
+    pub fn generate() -> Self {
+        let mut seed = [0u8; BYTES];
+        ThreadDrbg.fill_bytes(&mut seed);
+        Self::from(seed)
+    }
+
+    pub fn to_bytes(&self) -> [u8; BYTES] {
+        self.seed
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.public
+    }
+
+    // the nonce r = H(prefix || m) is deterministic, so the same message always gets the same
+    // signature
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let r = hash_to_scalar(&[&self.prefix, message]);
+        let r_bytes = base_mul(&r.to_le_bytes()).compress();
+        let k = hash_to_scalar(&[&r_bytes, &self.public.0, message]);
+
+        // S = r + k * a (mod L)
+        let ka = U256::const_rem_wide(k.mul_wide(&self.scalar), &L).0;
+        let s = ka.add_mod(&r, &L);
+
+        Signature(r_bytes, s.to_le_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // RFC 8032, section 7.1: tests 1 to 3, TEST 1024 and TEST SHA(abc). the longer messages take
+    // SHA-512 through several blocks.
+    #[test]
+    fn rfc8032_vectors() {
+        let cases: [(_, _, &[u8], _); 5] = [
+            (
+                hex!("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"),
+                hex!("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"),
+                &[],
+                hex!(
+                    "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+                ),
+            ),
+            (
+                hex!("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb"),
+                hex!("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c"),
+                &hex!("72"),
+                hex!(
+                    "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+                ),
+            ),
+            (
+                hex!("c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7"),
+                hex!("fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025"),
+                &hex!("af82"),
+                hex!(
+                    "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a"
+                ),
+            ),
+            (
+                hex!("f5e5767cf153319517630f226876b86c8160cc583bc013744c6bf255f5cc0ee5"),
+                hex!("278117fc144c72340f67d0f2316e8386ceffbf2b2428c9c51fef7c597f1d426e"),
+                &hex!(
+                    "08b8b2b733424243760fe426a4b54908632110a66c2f6591eabd3345e3e4eb98"
+                    "fa6e264bf09efe12ee50f8f54e9f77b1e355f6c50544e23fb1433ddf73be84d8"
+                    "79de7c0046dc4996d9e773f4bc9efe5738829adb26c81b37c93a1b270b20329d"
+                    "658675fc6ea534e0810a4432826bf58c941efb65d57a338bbd2e26640f89ffbc"
+                    "1a858efcb8550ee3a5e1998bd177e93a7363c344fe6b199ee5d02e82d522c4fe"
+                    "ba15452f80288a821a579116ec6dad2b3b310da903401aa62100ab5d1a36553e"
+                    "06203b33890cc9b832f79ef80560ccb9a39ce767967ed628c6ad573cb116dbef"
+                    "efd75499da96bd68a8a97b928a8bbc103b6621fcde2beca1231d206be6cd9ec7"
+                    "aff6f6c94fcd7204ed3455c68c83f4a41da4af2b74ef5c53f1d8ac70bdcb7ed1"
+                    "85ce81bd84359d44254d95629e9855a94a7c1958d1f8ada5d0532ed8a5aa3fb2"
+                    "d17ba70eb6248e594e1a2297acbbb39d502f1a8c6eb6f1ce22b3de1a1f40cc24"
+                    "554119a831a9aad6079cad88425de6bde1a9187ebb6092cf67bf2b13fd65f270"
+                    "88d78b7e883c8759d2c4f5c65adb7553878ad575f9fad878e80a0c9ba63bcbcc"
+                    "2732e69485bbc9c90bfbd62481d9089beccf80cfe2df16a2cf65bd92dd597b07"
+                    "07e0917af48bbb75fed413d238f5555a7a569d80c3414a8d0859dc65a46128ba"
+                    "b27af87a71314f318c782b23ebfe808b82b0ce26401d2e22f04d83d1255dc51a"
+                    "ddd3b75a2b1ae0784504df543af8969be3ea7082ff7fc9888c144da2af58429e"
+                    "c96031dbcad3dad9af0dcbaaaf268cb8fcffead94f3c7ca495e056a9b47acdb7"
+                    "51fb73e666c6c655ade8297297d07ad1ba5e43f1bca32301651339e22904cc8c"
+                    "42f58c30c04aafdb038dda0847dd988dcda6f3bfd15c4b4c4525004aa06eeff8"
+                    "ca61783aacec57fb3d1f92b0fe2fd1a85f6724517b65e614ad6808d6f6ee34df"
+                    "f7310fdc82aebfd904b01e1dc54b2927094b2db68d6f903b68401adebf5a7e08"
+                    "d78ff4ef5d63653a65040cf9bfd4aca7984a74d37145986780fc0b16ac451649"
+                    "de6188a7dbdf191f64b5fc5e2ab47b57f7f7276cd419c17a3ca8e1b939ae49e4"
+                    "88acba6b965610b5480109c8b17b80e1b7b750dfc7598d5d5011fd2dcc5600a3"
+                    "2ef5b52a1ecc820e308aa342721aac0943bf6686b64b2579376504ccc493d97e"
+                    "6aed3fb0f9cd71a43dd497f01f17c0e2cb3797aa2a2f256656168e6c496afc5f"
+                    "b93246f6b1116398a346f1a641f3b041e989f7914f90cc2c7fff357876e506b5"
+                    "0d334ba77c225bc307ba537152f3f1610e4eafe595f6d9d90d11faa933a15ef1"
+                    "369546868a7f3a45a96768d40fd9d03412c091c6315cf4fde7cb68606937380d"
+                    "b2eaaa707b4c4185c32eddcdd306705e4dc1ffc872eeee475a64dfac86aba41c"
+                    "0618983f8741c5ef68d3a101e8a3b8cac60c905c15fc910840b94c00a0b9d0"
+                ),
+                hex!(
+                    "0aab4c900501b3e24d7cdf4663326a3a87df5e4843b2cbdb67cbf6e460fec350aa5371b1508f9f4528ecea23c436d94b5e8fcd4f681e30a6ac00a9704a188a03"
+                ),
+            ),
+            (
+                hex!("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42"),
+                hex!("ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf"),
+                &hex!(
+                    "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a"
+                    "2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+                ),
+                hex!(
+                    "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b58909351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704"
+                ),
+            ),
+        ];
+
+        for (seed, public, message, signature) in cases {
+            let key = SigningKey::from(seed);
+            assert_eq!(key.verifying_key().as_bytes(), &public);
+            assert_eq!(key.sign(message).to_bytes(), signature);
+
+            let public = VerifyingKey::try_from(public).unwrap();
+            let signature = Signature::from_bytes(&signature).unwrap();
+            assert!(public.verify(message, &signature));
+            assert!(!public.verify(b"something else", &signature));
+        }
+    }
+
+    // the key and valid signatures of Wycheproof's eddsa_test.json (tcId 1 to 4), and invalid
+    // signatures built from tcId 3 the way its special-value, modified-bit and malleability cases
+    // are
+    #[test]
+    fn wycheproof_vectors() {
+        let seed = hex!("add4bb8103785baf9ac534258e8aaf65f5f1adb5ef5f3df19bb80ab989c4d64b");
+        let public = hex!("7d4d0e7f6153a69b6242b522abbee685fda4420f8834b108c3bdae369ef549fa");
+        assert_eq!(SigningKey::from(seed).verifying_key().as_bytes(), &public);
+        let public = VerifyingKey::try_from(public).unwrap();
+
+        let valid: [(&[u8], _); 4] = [
+            (
+                &[],
+                hex!(
+                    "d4fbdb52bfa726b44d1786a8c0d171c3e62ca83c9e5bbe63de0bb2483f8fd6cc1429ab72cafc41ab56af02ff8fcc43b99bfe4c7ae940f60f38ebaa9d311c4007"
+                ),
+            ),
+            (
+                &hex!("78"),
+                hex!(
+                    "d80737358ede548acb173ef7e0399f83392fe8125b2ce877de7975d8b726ef5b1e76632280ee38afad12125ea44b961bf92f1178c9fa819d020869975bcbe109"
+                ),
+            ),
+            (
+                &hex!("54657374"),
+                hex!(
+                    "7c38e026f29e14aabd059a0f2db8b0cd783040609a8be684db12f82a27774ab07a9155711ecfaf7f99f277bad0c6ae7e39d4eef676573336a5c51eb6f946b30d"
+                ),
+            ),
+            (
+                &hex!("48656c6c6f"),
+                hex!(
+                    "1c1ad976cbaae3b31dee07971cf92c928ce2091a85f5899f5e11ecec90fc9f8e93df18c5037ec9b29c07195ad284e63d548cd0a6fe358cc775bd6c1608d2c905"
+                ),
+            ),
+        ];
+        for (message, signature) in valid {
+            assert!(public.verify(message, &Signature::from_bytes(&signature).unwrap()));
+        }
+
+        let message = &hex!("54657374");
+        let signature = Signature::from_bytes(&valid[2].1).unwrap();
+        let rejected = |r: [u8; BYTES], s: [u8; BYTES]| match Signature::try_from((r, s)) {
+            Ok(signature) => !public.verify(message, &signature),
+            Err(_) => true,
+        };
+
+        // special values for R and S
+        let (zero, mut one) = ([0u8; BYTES], [0u8; BYTES]);
+        one[0] = 1;
+        for (r, s) in [(zero, zero), (zero, one), (one, zero), (one, one)] {
+            assert!(rejected(r, s));
+        }
+        assert!(rejected(signature.0, zero));
+        assert!(rejected(zero, signature.1));
+
+        // modified bits, including the sign of R and the top bit of S
+        for (byte, bit) in [(0, 0), (0, 1), (15, 7), (31, 6), (31, 7)] {
+            let (mut r, mut s) = (signature.0, signature.1);
+            r[byte] ^= 1 << bit;
+            s[byte] ^= 1 << bit;
+            assert!(rejected(r, signature.1));
+            assert!(rejected(signature.0, s));
+        }
+
+        // S + L and S + 2L are the same scalar, and R with y + p the same point
+        let s = U256::from_le_slice(&signature.1);
+        for s in [s.wrapping_add(&L), s.wrapping_add(&L).wrapping_add(&L)] {
+            assert_eq!(
+                Signature::try_from((signature.0, s.to_le_bytes())),
+                Err(InvalidEncoding::NonCanonical)
+            );
+        }
+        let non_canonical_r =
+            hex!("f0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+        assert!(rejected(non_canonical_r, signature.1));
+    }
+
+    #[test]
+    fn rejects_forgeries() {
+        let key = SigningKey::generate();
+        let public = key.verifying_key();
+        let signature = key.sign(b"Hello, World!");
+        assert!(public.verify(b"Hello, World!", &signature));
+
+        let other = SigningKey::generate();
+        assert!(!other.verifying_key().verify(b"Hello, World!", &signature));
+
+        let mut bytes = signature.to_bytes();
+        bytes[0] ^= 1;
+        let tampered = Signature::from_bytes(&bytes).unwrap();
+        assert!(!public.verify(b"Hello, World!", &tampered));
+
+        // S + L is the same scalar, but the signature is malleable unless it's rejected
+        let mut s = [0u8; BYTES];
+        s.copy_from_slice(&signature.1);
+        let (s_plus_l, carry) = U256::from_le_slice(&s).adc(&L, Default::default());
+        assert_eq!(carry.0, 0);
+        assert_eq!(
+            Signature::try_from((signature.0, s_plus_l.to_le_bytes())),
+            Err(InvalidEncoding::NonCanonical)
+        );
+    }
+
+    #[test]
+    fn strict_decoding() {
+        // the whole torsion subgroup: the identity, and points of order 8, 4, 8, 2, 8, 4 and 8
+        let small_order = [
+            hex!("0100000000000000000000000000000000000000000000000000000000000000"),
+            hex!("c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a"),
+            hex!("0000000000000000000000000000000000000000000000000000000000000080"),
+            hex!("26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc05"),
+            hex!("ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f"),
+            hex!("26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc85"),
+            hex!("0000000000000000000000000000000000000000000000000000000000000000"),
+            hex!("c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa"),
+        ];
+        for point in small_order {
+            assert_eq!(
+                VerifyingKey::try_from(point),
+                Err(InvalidEncoding::SmallOrder)
+            );
+        }
+
+        // y = p + 1, which would otherwise decode as the identity
+        let non_canonical =
+            hex!("eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+        assert_eq!(
+            VerifyingKey::try_from(non_canonical),
+            Err(InvalidEncoding::NonCanonical)
+        );
+        // y = p + 3 and y = 3 would be the same point of large order, and x = 0 has no negative
+        let mut three = [0u8; BYTES];
+        three[0] = 3;
+        assert!(VerifyingKey::try_from(three).is_ok());
+        let non_canonical =
+            hex!("f0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+        assert_eq!(
+            VerifyingKey::try_from(non_canonical),
+            Err(InvalidEncoding::NonCanonical)
+        );
+        let negative_zero =
+            hex!("0100000000000000000000000000000000000000000000000000000000000080");
+        assert_eq!(
+            VerifyingKey::try_from(negative_zero),
+            Err(InvalidEncoding::NonCanonical)
+        );
+        // y = 2 has no x
+        let mut two = [0u8; BYTES];
+        two[0] = 2;
+        assert_eq!(
+            VerifyingKey::try_from(two),
+            Err(InvalidEncoding::NotOnCurve)
+        );
+
+        // nor is a signature whose R has small order accepted
+        let key = SigningKey::generate();
+        let signature = Signature(small_order[0], [0; BYTES]);
+        assert!(!key.verifying_key().verify(b"", &signature));
+
+        let bytes = bincode::serialize(&small_order[1]).unwrap();
+        assert!(bincode::deserialize::<VerifyingKey>(&bytes).is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        let key = SigningKey::generate();
+        let signature = key.sign(b"abc");
+
+        let bytes = bincode::serialize(&signature).unwrap();
+        assert_eq!(bytes.len(), SIGNATURE_BYTES);
+        assert_eq!(
+            bincode::deserialize::<Signature>(&bytes).unwrap(),
+            signature
+        );
+
+        let bytes = bincode::serialize(&key).unwrap();
+        let restored: SigningKey = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(restored.verifying_key(), key.verifying_key());
+        assert_eq!(restored.sign(b"abc"), signature);
+    }
+}
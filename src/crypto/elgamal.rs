@@ -1,119 +1,202 @@
-use crypto_bigint::subtle::{Choice, ConditionallySelectable};
-use crypto_bigint::{CheckedSub, ConcatMixed, NonZero, RandomMod, U2048};
-use rand_core::OsRng;
+use crypto_bigint::Uint;
+use rand_core::RngCore;
 use serde::{Deserialize, Serialize};
 
+use super::drbg::ThreadDrbg;
+use super::group::{uint_from_be_bytes, uint_to_be_bytes, with_group, GroupId, InvalidElement};
 use super::SymmetricKey;
 
-pub(crate) type Int = U2048;
+// size of the secret that Bob transports to Alice, from which both derive the session keys.
+// it is much smaller than p, so every possible secret is a valid plaintext.
+const SECRET_BYTES: usize = 64;
+type Secret = [u8; SECRET_BYTES];
 
-const Q: Int = Int::from_be_hex("7f10d590db04c98b5b88dc544cf27fc758780dcd652763eafdffd0962153671e773bc4bed8214591be5fe640896f211a1a54457bdced391bfde8942f2a36904b8853bf65ecf849e75936bb9f37a972ff882bac35f632a24c31d2aba339e50b9aeb89bdd8db873fa3365907ca09042d3945f15c9f29c8df54cecbb2b98c0989ef315a32c2945c86fdec4f06374d337fbf4f81711c78233bcde92802a3064203c53094feeb8fc31cef66af51ef1d50ff2ad6c89afd7d7781e549eedeebd3afa8cba28eeee98cbb156ddbb9e30eb5227f5e7ac86c8a26301bca3ec15d9ba1e71f638227f00bcc7aa4323dafbb1b64ded53c20a9127be8cc5dfa80c10483dbde0ead").resize();
-const NONZERO_Q: NonZero<Int> = NonZero::from_uint(Q);
-const P: Int = Q.shl(1).saturating_add(&Int::ONE); // there is no overflow
-const BIG_P: <Int as ConcatMixed<Int>>::MixedOutput = P.resize();
-
-const G: Int = Int::from_be_hex("5A98F52C5D61C4047A68A9A2CA5D3CA087640121DE5FB00D1E92D660C3DB2F0B76FEB1D37679EF9215541986D9248AA2A2F876F2E66A48FB8C1C4948B0A259D8F3D75AC7AB352FE54A30E5889C56FAD6005B037F2B96437154A44DA609CCF975385350355E91F0D9223718376E0AC7FF858AA50608A21344CCEBCCE5707F2E32ECD6FAEEB54CB45C8A7EFD7C7DF16A76C947AC44371F33A54B03636AB30915A43F3697E100D6329BFF860B4EA7F5C54EAE0C79A1AF573085070ED243335DD523BB21653039F0A98DB73E7B17F98936FBE8DBDA998267C03586E669B83308E4183B877CE314B57A4E590795BECBFA94E69CC5D75981E66DF5D2F65ECB4A528D9C").resize();
-
-// uniformly generates some x such that 1 <= x <= q - 1
-fn generate_exponent() -> Int {
-    loop {
-        let x = Int::random_mod(&mut OsRng, &NONZERO_Q);
-        if x != Int::ZERO {
-            return x;
-        }
-    }
+fn secret_to_int<const LIMBS: usize>(secret: &Secret) -> Uint<LIMBS> {
+    uint_from_be_bytes(secret)
 }
 
-// calculates lhs * rhs (mod p)
-fn mul(lhs: &Int, rhs: &Int) -> Int {
-    let mul = lhs.mul(rhs);
-    let rem = mul.wrapping_rem(&BIG_P);
-    rem.resize()
+pub(crate) fn int_to_secret<const LIMBS: usize>(int: &Uint<LIMBS>) -> Secret {
+    let bytes = uint_to_be_bytes(int);
+    bytes[bytes.len() - SECRET_BYTES..].try_into().unwrap()
 }
 
-// calculates base^exp (mod p)
-fn pow(base: &Int, exp: &Int) -> Int {
-    // this implementation uses iterated squaring.
-    let mut result = Int::ONE;
-    let mut a = *base;
+// group elements are sent encoded, together with the group they belong to
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(GroupId, Vec<u8>)")]
+pub struct AlicePub(GroupId, Vec<u8>);
 
-    // iterating like this to remain constant-time
-    // using SmallInt becase all of our numbers fit there, but we use Int for convenience
-    for i in 0..Int::BITS {
-        let multiplied = mul(&result, &a);
-        let bit: Choice = exp.bit(i).into();
-        result.conditional_assign(&multiplied, bit);
+impl TryFrom<(GroupId, Vec<u8>)> for AlicePub {
+    type Error = InvalidElement;
 
-        a = mul(&a, &a);
+    fn try_from((group_id, public): (GroupId, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| group
+            .check_subgroup(&group.decode(&public)?))?;
+        Ok(Self(group_id, public))
     }
-
-    result
 }
 
-// calculates base^(-exp) (mod p)
-fn inv_pow(base: &Int, exp: &Int) -> Int {
-    pow(base, &Q.checked_sub(exp).unwrap())
-}
+impl AlicePub {
+    pub fn group(&self) -> GroupId {
+        self.0
+    }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct AlicePub(Int);
+    pub(crate) fn encoded(&self) -> &[u8] {
+        &self.1
+    }
+}
 
 #[derive(Clone)]
 pub struct Alice {
-    secret: Int,
+    secret: Vec<u8>,
     public: AlicePub,
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct BobEphemeral(Int, Int);
+#[serde(try_from = "(GroupId, Vec<u8>, Vec<u8>)")]
+pub struct BobEphemeral(GroupId, Vec<u8>, Vec<u8>);
+
+impl TryFrom<(GroupId, Vec<u8>, Vec<u8>)> for BobEphemeral {
+    type Error = InvalidElement;
+
+    // the second element hides the transported secret, which isn't encoded into the subgroup, so
+    // it can only be checked to be a nonzero residue
+    fn try_from(
+        (group_id, public, ciphertext): (GroupId, Vec<u8>, Vec<u8>),
+    ) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            group.check_subgroup(&group.decode(&public)?)?;
+            group.check_range(&group.decode(&ciphertext)?)
+        })?;
+        Ok(Self(group_id, public, ciphertext))
+    }
+}
+
+impl BobEphemeral {
+    pub fn group(&self) -> GroupId {
+        self.0
+    }
+
+    // g^b and A^b * m
+    pub(crate) fn encoded(&self) -> (&[u8], &[u8]) {
+        (&self.1, &self.2)
+    }
+}
 
 #[derive(Clone)]
 pub struct Bob {
-    secret: SymmetricKey,
+    secret: Secret,
 }
 
 impl Alice {
-    pub fn generate() -> Self {
-        let secret = generate_exponent();
-        let public = AlicePub(pow(&G, &secret));
+    pub fn generate(group_id: GroupId) -> Self {
+        with_group!(group_id, |group| {
+            let secret = group.generate_exponent();
+            let public = AlicePub(group_id, group.encode(&group.pow_g(&secret)));
 
-        Self { secret, public }
+            Self {
+                secret: group.encode(&secret),
+                public,
+            }
+        })
     }
 
     pub fn get_public(&self) -> &AlicePub {
         &self.public
     }
 
-    pub fn extract_shared_secret(&self, eph: BobEphemeral) -> SymmetricKey {
-        let BobEphemeral(public, enc) = eph; // g^b, A^b * m
-        let inv_key = inv_pow(&public, &self.secret); // (g^b)^-a
-        let plaintext = mul(&enc, &inv_key);
+    // the encoded exponent, for the other schemes that use the same keys
+    pub(crate) fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    pub fn extract_shared_secret(&self, eph: BobEphemeral) -> Result<SymmetricKey, InvalidElement> {
+        let BobEphemeral(group_id, public, enc) = eph; // g^b, A^b * m
+        if group_id != self.public.0 {
+            return Err(InvalidElement::GroupMismatch);
+        }
+
+        let secret = with_group!(group_id, |group| {
+            let public = group.decode(&public)?;
+            let enc = group.decode(&enc)?;
+            let inv_key = group.inv_pow(&public, &group.decode(&self.secret)?); // (g^b)^-a
+            int_to_secret(&group.mul(&enc, &inv_key))
+        });
+
+        Ok(SymmetricKey::from_shared_secret(&secret))
+    }
+
+    // opens the output of seal. tampering with any part of it changes the key or the data, which
+    // the mac catches.
+    pub fn open(&self, sealed: &[u8]) -> Option<Vec<u8>> {
+        let SealedBox(eph, ciphertext) = bincode::deserialize(sealed).ok()?;
+        if eph.0 != self.public.0 {
+            return None;
+        }
 
-        SymmetricKey::from_elgamal_int(&plaintext)
+        let shared = with_group!(eph.0, |group| {
+            let exponent = group.decode(&self.secret).ok()?;
+            // eph was validated when it was deserialized
+            group.encode(&group.pow(&group.decode(&eph.1).unwrap(), &exponent))
+        });
+        sealed_key(&shared, &eph, &self.public).decrypt(&ciphertext)
     }
 }
 
+// a message to a published AlicePub, which only the matching Alice can open, without any
+// interaction. it's hashed ElGamal: the sender makes a one-off key pair in the same group, and
+// the data is encrypted under a key hashed from the Diffie-Hellman value and both public keys.
+#[derive(Serialize, Deserialize)]
+struct SealedBox(AlicePub, Vec<u8>);
+
+pub fn seal(pk: &AlicePub, data: &[u8]) -> Vec<u8> {
+    let eph = Alice::generate(pk.0);
+
+    let shared = with_group!(pk.0, |group| {
+        let exponent = group.decode(&eph.secret).unwrap();
+        // pk was validated when it was deserialized or generated
+        group.encode(&group.pow(&group.decode(&pk.1).unwrap(), &exponent))
+    });
+    let ciphertext = sealed_key(&shared, &eph.public, pk).encrypt(data);
+
+    bincode::serialize(&SealedBox(eph.public, ciphertext)).unwrap()
+}
+
+// g^ab alone would do, but hashing in both public keys ties the key to this exchange
+fn sealed_key(shared: &[u8], eph: &AlicePub, pk: &AlicePub) -> SymmetricKey {
+    let mut secret = Vec::with_capacity(shared.len() + eph.1.len() + pk.1.len());
+    secret.extend_from_slice(shared);
+    secret.extend_from_slice(&eph.1);
+    secret.extend_from_slice(&pk.1);
+
+    SymmetricKey::from_shared_secret(&secret)
+}
+
 impl Bob {
     pub fn generate() -> Self {
-        Self {
-            secret: SymmetricKey::generate(),
-        }
+        let mut secret = [0u8; SECRET_BYTES];
+        ThreadDrbg.fill_bytes(&mut secret);
+
+        Self { secret }
     }
 
     pub fn encrypt_for_alice(&self, pk: &AlicePub) -> BobEphemeral {
-        let exponent = generate_exponent();
+        let AlicePub(group_id, alice) = pk;
+        with_group!(*group_id, |group| {
+            let exponent = group.generate_exponent();
 
-        let public = pow(&G, &exponent);
+            let public = group.pow_g(&exponent);
 
-        let AlicePub(alice) = &pk;
-        let key = pow(alice, &exponent);
-        let secret = self.secret.to_elgamal_int();
-        let ciphertext = mul(&key, &secret);
-        BobEphemeral(public, ciphertext)
+            // pk was validated when it was deserialized or generated
+            let alice = group.decode(alice).unwrap();
+            let key = group.pow(&alice, &exponent);
+            let secret = secret_to_int(&self.secret);
+            let ciphertext = group.mul(&key, &secret);
+            BobEphemeral(*group_id, group.encode(&public), group.encode(&ciphertext))
+        })
     }
 
     pub fn extract_shared_secret(&self) -> SymmetricKey {
-        self.secret.clone()
+        SymmetricKey::from_shared_secret(&self.secret)
     }
 }
 
@@ -123,13 +206,90 @@ mod tests {
 
     #[test]
     fn it_works() {
-        let alice = Alice::generate();
-        let bob = Bob::generate();
+        for group in [GroupId::Ffdhe3072, GroupId::Modp4096] {
+            let alice = Alice::generate(group);
+            let bob = Bob::generate();
+
+            let eph = bob.encrypt_for_alice(&alice.public);
+            let shared_alice = alice.extract_shared_secret(eph).unwrap();
+            let shared_bob = bob.extract_shared_secret();
+
+            assert_eq!(shared_alice, shared_bob)
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_elements() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let valid = alice.public.1.clone();
 
-        let eph = bob.encrypt_for_alice(&alice.public);
-        let shared_alice = alice.extract_shared_secret(eph);
-        let shared_bob = bob.extract_shared_secret();
+        let mut one = vec![0u8; valid.len()];
+        *one.last_mut().unwrap() = 1;
+        let cases = [
+            (vec![0u8; valid.len()], InvalidElement::OutOfRange),
+            (vec![0xff; valid.len()], InvalidElement::OutOfRange),
+            (valid[1..].to_vec(), InvalidElement::WrongLength),
+            (one, InvalidElement::Trivial),
+        ];
+        for (x, error) in cases {
+            let public = (GroupId::Ffdhe3072, x.clone());
+            assert_eq!(AlicePub::try_from(public).err(), Some(error));
+            let eph = (GroupId::Ffdhe3072, x, valid.clone());
+            assert_eq!(BobEphemeral::try_from(eph).err(), Some(error));
+        }
+
+        // the right size for ffdhe3072, but not for ffdhe4096
+        assert_eq!(
+            AlicePub::try_from((GroupId::Ffdhe4096, valid.clone())).err(),
+            Some(InvalidElement::WrongLength)
+        );
+
+        let eph = Bob::generate().encrypt_for_alice(&alice.public);
+        let other = Alice::generate(GroupId::Modp3072);
+        assert_eq!(
+            other.extract_shared_secret(eph).err(),
+            Some(InvalidElement::GroupMismatch)
+        );
+    }
+
+    #[test]
+    fn sealed_box() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let first = seal(alice.get_public(), b"Hello, World!");
+        let second = seal(alice.get_public(), b"");
+        assert_ne!(first, seal(alice.get_public(), b"Hello, World!"));
+
+        assert_eq!(alice.open(&first).as_deref(), Some(&b"Hello, World!"[..]));
+        assert_eq!(alice.open(&second).as_deref(), Some(&b""[..]));
+
+        let other = Alice::generate(GroupId::Ffdhe3072);
+        assert_eq!(other.open(&first), None);
+
+        // the one-off public key, and the end of the data
+        for i in [first.len() / 4, first.len() - 1] {
+            let mut tampered = first.clone();
+            tampered[i] ^= 1;
+            assert_eq!(alice.open(&tampered), None);
+        }
+        assert_eq!(alice.open(&first[..first.len() / 2]), None);
+
+        // a box sealed to a key in another group
+        let other = Alice::generate(GroupId::Modp3072);
+        assert_eq!(other.open(&first), None);
+    }
+
+    #[test]
+    fn deserialization_validates() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let bytes = bincode::serialize(alice.get_public()).unwrap();
+        let public: AlicePub = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(public.group(), GroupId::Ffdhe3072);
+        assert_eq!(public.1, alice.public.1);
 
-        assert_eq!(shared_alice, shared_bob)
+        let mut one = vec![0u8; alice.public.1.len()];
+        *one.last_mut().unwrap() = 1;
+        let bytes = bincode::serialize(&AlicePub(GroupId::Ffdhe3072, one)).unwrap();
+        let error = bincode::deserialize::<AlicePub>(&bytes).err().unwrap();
+        assert_eq!(error.to_string(), InvalidElement::Trivial.to_string());
     }
 }
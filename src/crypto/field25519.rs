@@ -0,0 +1,183 @@
+// arithmetic modulo p = 2^255 - 19, which both X25519 and Ed25519 are built on
+
+pub(crate) const BYTES: usize = 32;
+
+// an element of GF(2^255 - 19), as 5 limbs of 51 bits. the limbs may grow a little past 51 bits
+// between reductions, and the value may be anywhere below 2^256; only to_bytes reduces fully.
+#[derive(Clone, Copy)]
+pub(crate) struct Fe([u64; 5]);
+
+const MASK: u64 = (1 << 51) - 1;
+
+impl Fe {
+    pub(crate) const ZERO: Self = Self([0; 5]);
+    pub(crate) const ONE: Self = Self([1, 0, 0, 0, 0]);
+
+    // the top bit is ignored, as RFC 7748 requires. values of p and above are accepted and
+    // reduced.
+    pub(crate) fn from_bytes(bytes: &[u8; BYTES]) -> Self {
+        let load = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Self([
+            load(0) & MASK,
+            (load(6) >> 3) & MASK,
+            (load(12) >> 6) & MASK,
+            (load(19) >> 1) & MASK,
+            (load(24) >> 12) & MASK,
+        ])
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; BYTES] {
+        let mut limbs = Self::carry(self.0.map(u128::from)).0;
+
+        // the value is below 2p now. it's at least p exactly when adding 19 carries into 2^255.
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..] {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        // dropping the carry out of the top limb subtracts 2^255
+        limbs[4] &= MASK;
+
+        let mut bytes = [0u8; BYTES];
+        let (mut acc, mut bits, mut j) = (0u128, 0, 0);
+        for limb in limbs {
+            acc |= u128::from(limb) << bits;
+            bits += 51;
+            while bits >= 8 {
+                bytes[j] = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+                j += 1;
+            }
+        }
+        bytes[j] = acc as u8;
+
+        bytes
+    }
+
+    // brings every limb down to 51 bits, plus a small carry into the second one. 2^255 = 19.
+    fn carry(mut limbs: [u128; 5]) -> Self {
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK as u128;
+        }
+        limbs[0] += (limbs[4] >> 51) * 19;
+        limbs[4] &= MASK as u128;
+        limbs[1] += limbs[0] >> 51;
+        limbs[0] &= MASK as u128;
+
+        Self(limbs.map(|limb| limb as u64))
+    }
+
+    pub(crate) fn add(&self, other: &Self) -> Self {
+        let mut limbs = [0u128; 5];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u128::from(self.0[i]) + u128::from(other.0[i]);
+        }
+        Self::carry(limbs)
+    }
+
+    pub(crate) fn sub(&self, other: &Self) -> Self {
+        // adds 16p first, which is more than any operand, so that no limb goes negative
+        const SIXTEEN_P: [u64; 5] = [16 * (MASK - 18), 16 * MASK, 16 * MASK, 16 * MASK, 16 * MASK];
+
+        let mut limbs = [0u128; 5];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u128::from(self.0[i] + SIXTEEN_P[i] - other.0[i]);
+        }
+        Self::carry(limbs)
+    }
+
+    pub(crate) fn mul(&self, other: &Self) -> Self {
+        let (a, b) = (self.0.map(u128::from), other.0.map(u128::from));
+        // a_i * b_j lands at limb i + j, and limbs past the fourth wrap around times 19
+        let b19 = b.map(|x| x * 19);
+
+        Self::carry([
+            a[0] * b[0] + a[1] * b19[4] + a[2] * b19[3] + a[3] * b19[2] + a[4] * b19[1],
+            a[0] * b[1] + a[1] * b[0] + a[2] * b19[4] + a[3] * b19[3] + a[4] * b19[2],
+            a[0] * b[2] + a[1] * b[1] + a[2] * b[0] + a[3] * b19[4] + a[4] * b19[3],
+            a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0] + a[4] * b19[4],
+            a[0] * b[4] + a[1] * b[3] + a[2] * b[2] + a[3] * b[1] + a[4] * b[0],
+        ])
+    }
+
+    pub(crate) fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    pub(crate) const fn from_u64(x: u64) -> Self {
+        Self([x & MASK, x >> 51, 0, 0, 0])
+    }
+
+    pub(crate) fn neg(&self) -> Self {
+        Self::ZERO.sub(self)
+    }
+
+    // whether the fully reduced value is odd, which is how Ed25519 encodes the sign of x
+    pub(crate) fn is_negative(&self) -> bool {
+        self.to_bytes()[0] & 1 == 1
+    }
+
+    // self^exponent, with the exponent given little-endian. it's always a public constant, so
+    // plain square-and-multiply is fine.
+    fn pow(&self, exponent: &[u8; BYTES]) -> Self {
+        (0..255).rev().fold(Self::ONE, |acc, bit| {
+            let acc = acc.square();
+            match (exponent[bit / 8] >> (bit % 8)) & 1 {
+                1 => acc.mul(self),
+                _ => acc,
+            }
+        })
+    }
+
+    // self^(p - 2) = 1 / self
+    pub(crate) fn invert(&self) -> Self {
+        // 2^255 - 21
+        let mut exponent = [0xff; BYTES];
+        exponent[0] = 0xeb;
+        exponent[31] = 0x7f;
+        self.pow(&exponent)
+    }
+
+    // self^((p - 5) / 8), the main step of a square root
+    pub(crate) fn pow_p58(&self) -> Self {
+        // 2^252 - 3
+        let mut exponent = [0xff; BYTES];
+        exponent[0] = 0xfd;
+        exponent[31] = 0x0f;
+        self.pow(&exponent)
+    }
+
+    // 2^((p - 1) / 4), a square root of -1
+    pub(crate) fn sqrt_minus_one() -> Self {
+        // 2^253 - 5
+        let mut exponent = [0xff; BYTES];
+        exponent[0] = 0xfb;
+        exponent[31] = 0x1f;
+        Self::from_u64(2).pow(&exponent)
+    }
+
+    // swaps a and b iff swap is 1, without branching on it
+    pub(crate) fn conditional_swap(a: &mut Self, b: &mut Self, swap: u64) {
+        let mask = 0u64.wrapping_sub(swap);
+        for i in 0..5 {
+            let t = mask & (a.0[i] ^ b.0[i]);
+            a.0[i] ^= t;
+            b.0[i] ^= t;
+        }
+    }
+}
+
+// compares the fully reduced values
+impl PartialEq for Fe {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl Eq for Fe {}
@@ -0,0 +1,1010 @@
+use std::fmt;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::thread;
+
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::subtle::{ConditionallySelectable, ConstantTimeEq};
+use crypto_bigint::{Limb, NonZero, RandomMod, Uint, Word, U2048, U3072, U4096, U6144, U8192};
+use serde::{Deserialize, Serialize};
+
+use super::drbg::ThreadDrbg;
+use super::hash::HashFunction;
+use super::primality::is_probable_prime;
+use super::sha256::{self, Sha256};
+
+// the order-q subgroup of the integers modulo a safe prime p = 2q + 1, generated by g. integers
+// are Uint<LIMBS>, so the same code serves every modulus size that crypto-bigint has a width for.
+// DSA uses the same arithmetic with a smaller q that merely divides p - 1.
+pub struct Group<const LIMBS: usize> {
+    name: &'static str,
+    p: Uint<LIMBS>,
+    q: Uint<LIMBS>,
+    g: Uint<LIMBS>,
+    nonzero_q: NonZero<Uint<LIMBS>>,
+    p_minus_one: Uint<LIMBS>,
+    // precomputed constants for Montgomery multiplication modulo p
+    montgomery: DynResidueParams<LIMBS>,
+    // g_table[i] holds the powers of g^(WINDOW_SIZE^(i * g_spacing())). it's built on first use.
+    g_table: OnceLock<Vec<Table<LIMBS>>>,
+}
+
+// group elements are kept in Montgomery form while doing arithmetic, which replaces the division
+// by p after every multiplication with a much cheaper Montgomery reduction
+type Residue<const LIMBS: usize> = DynResidue<LIMBS>;
+
+// exponents are processed WINDOW_BITS at a time, from precomputed tables of WINDOW_SIZE powers
+const WINDOW_BITS: usize = 4;
+const WINDOW_SIZE: usize = 1 << WINDOW_BITS;
+
+// WINDOW_SIZE powers of some element, in Montgomery form
+type Table<const LIMBS: usize> = [Uint<LIMBS>; WINDOW_SIZE];
+
+// the most tables kept for g, which bounds them at 4 MB for 8192-bit groups. exponents with more
+// windows than that share each table between several of them, at the cost of a few squarings.
+const MAX_G_TABLES: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvalidElement {
+    WrongLength,
+    OutOfRange,
+    Trivial,
+    NotInSubgroup,
+    GroupMismatch,
+}
+
+impl fmt::Display for InvalidElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongLength => write!(f, "group element has the wrong length"),
+            Self::OutOfRange => write!(f, "group element is zero or not smaller than p"),
+            Self::Trivial => write!(f, "group element is 1 or p - 1"),
+            Self::NotInSubgroup => write!(f, "group element is not in the order-q subgroup"),
+            Self::GroupMismatch => write!(f, "group element belongs to a different group"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidElement {}
+
+// the parameters of a group that isn't built in, e.g. one from paramgen. both are big-endian.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct GroupParams {
+    pub p: Vec<u8>,
+    pub g: Vec<u8>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvalidParams {
+    TooSmall,
+    TooLarge,
+    NotSafePrime,
+    BadGenerator,
+}
+
+impl fmt::Display for InvalidParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall => write!(f, "p has fewer than {MIN_PARAMS_BITS} bits"),
+            Self::TooLarge => write!(f, "group parameters are too large"),
+            Self::NotSafePrime => write!(f, "p is not a safe prime"),
+            Self::BadGenerator => write!(f, "g does not generate the order-q subgroup"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidParams {}
+
+// the smallest p that from_params loads, the same minimum that the built-in groups meet
+pub const MIN_PARAMS_BITS: usize = 3072;
+
+impl<const LIMBS: usize> Group<LIMBS> {
+    // p has to be a safe prime and g a generator of its order-q subgroup. neither is checked here.
+    pub fn new(name: &'static str, p: Uint<LIMBS>, g: Uint<LIMBS>) -> Self {
+        Self::with_order(name, p, p.shr_vartime(1), g)
+    }
+
+    // for an odd prime p, a prime q dividing p - 1 and g of order q. none of it is checked here.
+    pub fn with_order(name: &'static str, p: Uint<LIMBS>, q: Uint<LIMBS>, g: Uint<LIMBS>) -> Self {
+        Self {
+            name,
+            p,
+            q,
+            g,
+            nonzero_q: NonZero::from_uint(q),
+            p_minus_one: p.wrapping_sub(&Uint::ONE),
+            montgomery: DynResidueParams::new(&p),
+            g_table: OnceLock::new(),
+        }
+    }
+
+    // loads parameters that may come from anywhere, so they're checked in full first
+    pub fn from_params(params: &GroupParams) -> Result<Self, InvalidParams> {
+        let max_bytes = LIMBS * Limb::BYTES;
+        if params.p.len() > max_bytes || params.g.len() > max_bytes {
+            return Err(InvalidParams::TooLarge);
+        }
+
+        let p: Uint<LIMBS> = uint_from_be_bytes(&params.p);
+        if p.bits_vartime() < MIN_PARAMS_BITS {
+            return Err(InvalidParams::TooSmall);
+        }
+        // Montgomery arithmetic needs an odd modulus, and self_check tests primality
+        if p.as_words()[0] & 1 == 0 {
+            return Err(InvalidParams::NotSafePrime);
+        }
+
+        let group = Self::new("generated", p, uint_from_be_bytes(&params.g));
+        group.self_check()?;
+
+        Ok(group)
+    }
+
+    // checks that p and q are prime and that g generates the order-q subgroup
+    pub fn self_check(&self) -> Result<(), InvalidParams> {
+        if !is_probable_prime(&self.p) || !is_probable_prime(&self.q) {
+            return Err(InvalidParams::NotSafePrime);
+        }
+
+        self.check_subgroup(&self.g)
+            .map_err(|_| InvalidParams::BadGenerator)
+    }
+
+    // a generator of the order-q subgroup that is verifiably derived from a public seed, so that
+    // nobody knows its discrete logarithm to g. this is the verifiable canonical generation of
+    // FIPS 186-4, appendix A.2.3, with SHA-256: the hash is raised to (p - 1) / q = 2. the hash is
+    // taken modulo p, which only changes anything for groups smaller than it.
+    pub fn derive_generator(&self, seed: &[u8], index: u8) -> Uint<LIMBS> {
+        let byte = Uint::from_u16(256).wrapping_rem(&self.p);
+        (1..=u16::MAX)
+            .find_map(|count| {
+                let mut hasher = Sha256::new();
+                hasher.update(seed);
+                hasher.update(b"ggen");
+                hasher.update(&[index]);
+                hasher.update(&count.to_be_bytes());
+
+                let w = hasher.finalize().iter().fold(Uint::ZERO, |w, &b| {
+                    let b = Uint::from_u8(b).wrapping_rem(&self.p);
+                    self.mul(&w, &byte).add_mod(&b, &self.p)
+                });
+                let g = self.mul(&w, &w);
+                (g > Uint::ONE).then_some(g)
+            })
+            .expect("every hash squared to 0 or 1")
+    }
+
+    pub fn to_params(&self) -> GroupParams {
+        GroupParams {
+            p: self.encode(&self.p),
+            g: self.encode(&self.g),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn p(&self) -> &Uint<LIMBS> {
+        &self.p
+    }
+
+    pub fn q(&self) -> &Uint<LIMBS> {
+        &self.q
+    }
+
+    pub fn g(&self) -> &Uint<LIMBS> {
+        &self.g
+    }
+
+    // the size of p in bits
+    pub fn bits(&self) -> usize {
+        self.p.bits_vartime()
+    }
+
+    // the size of an encoded element
+    pub fn element_bytes(&self) -> usize {
+        self.bits().div_ceil(8)
+    }
+
+    // big-endian and exactly element_bytes long, for anything smaller than p
+    pub(crate) fn encode(&self, x: &Uint<LIMBS>) -> Vec<u8> {
+        let mut bytes = uint_to_be_bytes(x);
+        bytes.split_off(bytes.len() - self.element_bytes())
+    }
+
+    // the inverse of encode. the result isn't necessarily smaller than p.
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Result<Uint<LIMBS>, InvalidElement> {
+        if bytes.len() != self.element_bytes() {
+            return Err(InvalidElement::WrongLength);
+        }
+
+        Ok(uint_from_be_bytes(bytes))
+    }
+
+    // uniformly generates some x such that 1 <= x <= q - 1
+    pub(crate) fn generate_exponent(&self) -> Uint<LIMBS> {
+        loop {
+            let x = Uint::random_mod(&mut ThreadDrbg, &self.nonzero_q);
+            if x != Uint::ZERO {
+                return x;
+            }
+        }
+    }
+
+    fn to_residue(&self, x: &Uint<LIMBS>) -> Residue<LIMBS> {
+        Residue::new(x, self.montgomery)
+    }
+
+    // calculates lhs * rhs (mod p)
+    pub(crate) fn mul(&self, lhs: &Uint<LIMBS>, rhs: &Uint<LIMBS>) -> Uint<LIMBS> {
+        self.to_residue(lhs).mul(&self.to_residue(rhs)).retrieve()
+    }
+
+    // returns table[index], reading every entry so that the memory access pattern doesn't depend
+    // on the index
+    fn lookup(&self, table: &Table<LIMBS>, index: u8) -> Residue<LIMBS> {
+        let mut output = Uint::ZERO;
+        for (i, entry) in table.iter().enumerate() {
+            output.conditional_assign(entry, (i as u8).ct_eq(&index));
+        }
+
+        Residue::from_montgomery(output, self.montgomery)
+    }
+
+    // [1, x, x^2, ..., x^(WINDOW_SIZE - 1)]
+    fn powers_table(&self, x: &Residue<LIMBS>) -> Table<LIMBS> {
+        let mut table = [Uint::ZERO; WINDOW_SIZE];
+        let mut power = Residue::one(self.montgomery);
+        for entry in table.iter_mut() {
+            *entry = power.to_montgomery();
+            power = power.mul(x);
+        }
+
+        table
+    }
+
+    // calculates base^exp (mod p)
+    pub(crate) fn pow(&self, base: &Uint<LIMBS>, exp: &Uint<LIMBS>) -> Uint<LIMBS> {
+        // this implementation uses a fixed window: every window costs WINDOW_BITS squarings and a
+        // single multiplication, even when the window is zero, to remain constant-time
+        let table = self.powers_table(&self.to_residue(base));
+        let mut result = Residue::one(self.montgomery);
+
+        for j in (0..windows::<LIMBS>()).rev() {
+            for _ in 0..WINDOW_BITS {
+                result = result.square();
+            }
+            result = result.mul(&self.lookup(&table, window(exp, j)));
+        }
+
+        result.retrieve()
+    }
+
+    // how many consecutive windows share a table of g. only exponents below q need to be covered.
+    fn g_spacing(&self) -> usize {
+        self.q
+            .bits_vartime()
+            .div_ceil(WINDOW_BITS)
+            .div_ceil(MAX_G_TABLES)
+    }
+
+    fn g_table(&self) -> &[Table<LIMBS>] {
+        self.g_table.get_or_init(|| {
+            let spacing = self.g_spacing();
+            let count = self.q.bits_vartime().div_ceil(WINDOW_BITS * spacing);
+            let mut tables = Vec::with_capacity(count);
+            let mut base = self.to_residue(&self.g);
+            for _ in 0..count {
+                tables.push(self.powers_table(&base));
+                for _ in 0..WINDOW_BITS * spacing {
+                    base = base.square();
+                }
+            }
+
+            tables
+        })
+    }
+
+    // calculates g^exp (mod p)
+    pub(crate) fn pow_g(&self, exp: &Uint<LIMBS>) -> Uint<LIMBS> {
+        // the tables only reach as far as q, which exponents that are kept secret never do
+        let (tables, spacing) = (self.g_table(), self.g_spacing());
+        if exp.bits_vartime() > tables.len() * spacing * WINDOW_BITS {
+            return self.pow(&self.g, exp);
+        }
+
+        // with g fixed, window i * spacing + s is looked up in table i, so only the windows within
+        // a row need squarings between them: a comb with `spacing` teeth
+        let mut result = Residue::one(self.montgomery);
+        for s in (0..spacing).rev() {
+            for _ in 0..WINDOW_BITS {
+                result = result.square();
+            }
+            for (i, table) in tables.iter().enumerate() {
+                result = result.mul(&self.lookup(table, window(exp, i * spacing + s)));
+            }
+        }
+
+        result.retrieve()
+    }
+
+    // calculates base^(-exp) (mod p)
+    pub(crate) fn inv_pow(&self, base: &Uint<LIMBS>, exp: &Uint<LIMBS>) -> Uint<LIMBS> {
+        self.pow(base, &self.q.wrapping_sub(exp))
+    }
+
+    // x (mod q), for turning hashes into exponents
+    pub(crate) fn reduce_exponent(&self, x: &Uint<LIMBS>) -> Uint<LIMBS> {
+        x.rem(&self.nonzero_q)
+    }
+
+    // calculates lhs + rhs (mod q), for exponents smaller than q
+    pub(crate) fn add_exponents(&self, lhs: &Uint<LIMBS>, rhs: &Uint<LIMBS>) -> Uint<LIMBS> {
+        lhs.add_mod(rhs, &self.q)
+    }
+
+    // calculates lhs - rhs (mod q), for exponents smaller than q
+    pub(crate) fn sub_exponents(&self, lhs: &Uint<LIMBS>, rhs: &Uint<LIMBS>) -> Uint<LIMBS> {
+        lhs.sub_mod(rhs, &self.q)
+    }
+
+    // calculates lhs * rhs (mod q)
+    pub(crate) fn mul_exponents(&self, lhs: &Uint<LIMBS>, rhs: &Uint<LIMBS>) -> Uint<LIMBS> {
+        Uint::const_rem_wide(lhs.mul_wide(rhs), &self.q).0
+    }
+
+    // x^(-1) (mod q), for a nonzero x smaller than q
+    pub(crate) fn invert_exponent(&self, x: &Uint<LIMBS>) -> Uint<LIMBS> {
+        x.inv_odd_mod(&self.q).0
+    }
+
+    // 0 < x < p
+    pub(crate) fn check_range(&self, x: &Uint<LIMBS>) -> Result<(), InvalidElement> {
+        if x == &Uint::ZERO || x >= &self.p {
+            return Err(InvalidElement::OutOfRange);
+        }
+
+        Ok(())
+    }
+
+    // 1 < x < p - 1 and x^q = 1 (mod p). the trivial elements are rejected explicitly, since they
+    // would force the shared secret to a known value.
+    pub(crate) fn check_subgroup(&self, x: &Uint<LIMBS>) -> Result<(), InvalidElement> {
+        self.check_range(x)?;
+        if x == &Uint::ONE || x == &self.p_minus_one {
+            return Err(InvalidElement::Trivial);
+        }
+        if self.pow(x, &self.q) != Uint::ONE {
+            return Err(InvalidElement::NotInSubgroup);
+        }
+
+        Ok(())
+    }
+}
+
+fn windows<const LIMBS: usize>() -> usize {
+    Uint::<LIMBS>::BITS / WINDOW_BITS
+}
+
+// the j-th window of the exponent, counting from the least significant bits, and 0 past its end.
+// the position is public, so only the value has to be handled carefully.
+fn window<const LIMBS: usize>(exp: &Uint<LIMBS>, j: usize) -> u8 {
+    let bit = j * WINDOW_BITS;
+    if bit >= Uint::<LIMBS>::BITS {
+        return 0;
+    }
+    let word = exp.as_words()[bit / Word::BITS as usize];
+    ((word >> (bit % Word::BITS as usize)) as u8) & (WINDOW_SIZE as u8 - 1)
+}
+
+// the seed that derive_generator is used with for the generators that every group needs besides
+// g, which is 2 in the built-in ones: index 1 is Pedersen's h, and 2 and 3 are SPAKE2's M and N
+pub const GENERATOR_SEED: &[u8] = b"titanium-rose generator";
+
+// the outcome of Group::self_check for one of the built-in groups
+#[derive(Clone, Debug)]
+pub struct SelfCheck {
+    pub name: &'static str,
+    pub bits: usize,
+    pub result: Result<(), InvalidParams>,
+}
+
+impl SelfCheck {
+    fn run<const LIMBS: usize>(group: &Group<LIMBS>) -> Self {
+        Self {
+            name: group.name(),
+            bits: group.bits(),
+            result: group.self_check(),
+        }
+    }
+}
+
+// checks all the built-in groups, including the 2048-bit ones, in parallel
+pub fn self_check_all() -> Vec<SelfCheck> {
+    thread::scope(|scope| {
+        let checks = [
+            scope.spawn(|| SelfCheck::run(&*MODP_2048)),
+            scope.spawn(|| SelfCheck::run(&*MODP_3072)),
+            scope.spawn(|| SelfCheck::run(&*MODP_4096)),
+            scope.spawn(|| SelfCheck::run(&*FFDHE_2048)),
+            scope.spawn(|| SelfCheck::run(&*FFDHE_3072)),
+            scope.spawn(|| SelfCheck::run(&*FFDHE_4096)),
+            scope.spawn(|| SelfCheck::run(&*FFDHE_6144)),
+            scope.spawn(|| SelfCheck::run(&*FFDHE_8192)),
+        ];
+
+        checks.map(|check| check.join().unwrap()).into()
+    })
+}
+
+// crypto-bigint only implements Encoding for its named sizes, so generic code goes through these
+pub(crate) fn uint_to_be_bytes<const LIMBS: usize>(x: &Uint<LIMBS>) -> Vec<u8> {
+    x.as_words()
+        .iter()
+        .rev()
+        .flat_map(|word| word.to_be_bytes())
+        .collect()
+}
+
+// bytes may be shorter than the integer, in which case it's zero-extended
+pub(crate) fn uint_from_be_bytes<const LIMBS: usize>(bytes: &[u8]) -> Uint<LIMBS> {
+    let mut padded = vec![0u8; LIMBS * Limb::BYTES];
+    let start = padded.len() - bytes.len();
+    padded[start..].copy_from_slice(bytes);
+
+    let mut words = [0 as Word; LIMBS];
+    for (word, chunk) in words.iter_mut().rev().zip(padded.chunks_exact(Limb::BYTES)) {
+        *word = Word::from_be_bytes(chunk.try_into().unwrap());
+    }
+
+    Uint::from_words(words)
+}
+
+// evaluates the body with `$group` bound to the group named by a GroupId. the body is compiled
+// once for every group, since each of them has its own integer width.
+macro_rules! with_group {
+    ($id:expr, |$group:ident| $body:expr) => {{
+        match $id {
+            $crate::crypto::group::GroupId::Ffdhe3072 => {
+                let $group = &*$crate::crypto::group::FFDHE_3072;
+                $body
+            }
+            $crate::crypto::group::GroupId::Ffdhe4096 => {
+                let $group = &*$crate::crypto::group::FFDHE_4096;
+                $body
+            }
+            $crate::crypto::group::GroupId::Ffdhe6144 => {
+                let $group = &*$crate::crypto::group::FFDHE_6144;
+                $body
+            }
+            $crate::crypto::group::GroupId::Ffdhe8192 => {
+                let $group = &*$crate::crypto::group::FFDHE_8192;
+                $body
+            }
+            $crate::crypto::group::GroupId::Modp3072 => {
+                let $group = &*$crate::crypto::group::MODP_3072;
+                $body
+            }
+            $crate::crypto::group::GroupId::Modp4096 => {
+                let $group = &*$crate::crypto::group::MODP_4096;
+                $body
+            }
+            $crate::crypto::group::GroupId::Custom(id) => match id.group() {
+                $crate::crypto::group::CustomGroup::Bits3072($group) => $body,
+                $crate::crypto::group::CustomGroup::Bits4096($group) => $body,
+                $crate::crypto::group::CustomGroup::Bits6144($group) => $body,
+                $crate::crypto::group::CustomGroup::Bits8192($group) => $body,
+            },
+        }
+    }};
+}
+
+pub(crate) use with_group;
+
+// the groups a session can be run in, which is sent along with the public values so that both
+// sides use the same one. compliance requires at least 3072 bits, so the 2048-bit groups below
+// are deliberately missing here.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GroupId {
+    #[default]
+    Ffdhe3072,
+    Ffdhe4096,
+    Ffdhe6144,
+    Ffdhe8192,
+    Modp3072,
+    Modp4096,
+    // one loaded with load_group, which both sides have to do before a session can use it
+    Custom(CustomGroupId),
+}
+
+impl GroupId {
+    pub const ALL: [GroupId; 6] = [
+        GroupId::Ffdhe3072,
+        GroupId::Ffdhe4096,
+        GroupId::Ffdhe6144,
+        GroupId::Ffdhe8192,
+        GroupId::Modp3072,
+        GroupId::Modp4096,
+    ];
+
+    pub fn name(self) -> &'static str {
+        with_group!(self, |group| group.name())
+    }
+
+    pub fn bits(self) -> usize {
+        with_group!(self, |group| group.bits())
+    }
+}
+
+// a group loaded with load_group, in the integer width that fits it. loaded groups stay around
+// until the program exits.
+#[derive(Clone, Copy)]
+pub enum CustomGroup {
+    Bits3072(&'static Group<{ U3072::LIMBS }>),
+    Bits4096(&'static Group<{ U4096::LIMBS }>),
+    Bits6144(&'static Group<{ U6144::LIMBS }>),
+    Bits8192(&'static Group<{ U8192::LIMBS }>),
+}
+
+static CUSTOM_GROUPS: Mutex<Vec<(CustomGroupId, CustomGroup)>> = Mutex::new(Vec::new());
+
+// the SHA-256 of a loaded group's parameters. one only exists for a group that has been loaded,
+// and one received from the other side fails to deserialize unless the group is loaded here too.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(try_from = "sha256::Digest")]
+pub struct CustomGroupId(sha256::Digest);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UnknownGroup;
+
+impl fmt::Display for UnknownGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the group hasn't been loaded, load its parameters first")
+    }
+}
+
+impl std::error::Error for UnknownGroup {}
+
+impl TryFrom<sha256::Digest> for CustomGroupId {
+    type Error = UnknownGroup;
+
+    fn try_from(digest: sha256::Digest) -> Result<Self, Self::Error> {
+        let id = Self(digest);
+        find_custom(id).map(|_| id).ok_or(UnknownGroup)
+    }
+}
+
+impl CustomGroupId {
+    pub fn group(self) -> CustomGroup {
+        find_custom(self).expect("custom group ids are only made for loaded groups")
+    }
+}
+
+fn find_custom(id: CustomGroupId) -> Option<CustomGroup> {
+    let groups = CUSTOM_GROUPS.lock().unwrap();
+    groups
+        .iter()
+        .find(|(other, _)| *other == id)
+        .map(|&(_, group)| group)
+}
+
+fn register<const LIMBS: usize>(
+    params: &GroupParams,
+    wrap: fn(&'static Group<LIMBS>) -> CustomGroup,
+) -> Result<GroupId, InvalidParams> {
+    let mut group = Group::from_params(params)?;
+    let digest = sha256::hash(&bincode::serialize(&group.to_params()).unwrap());
+    let id = CustomGroupId(digest);
+
+    let mut groups = CUSTOM_GROUPS.lock().unwrap();
+    if !groups.iter().any(|(other, _)| *other == id) {
+        let fingerprint: String = digest[..4].iter().map(|b| format!("{b:02x}")).collect();
+        group.name = format!("{}-bit custom ({fingerprint})", group.bits()).leak();
+        groups.push((id, wrap(Box::leak(Box::new(group)))));
+    }
+
+    Ok(GroupId::Custom(id))
+}
+
+// checks parameters that may come from anywhere and makes them usable in sessions. loading the
+// same parameters again gives the same id.
+pub fn load_group(params: &GroupParams) -> Result<GroupId, InvalidParams> {
+    if params.p.len() > U8192::BYTES {
+        return Err(InvalidParams::TooLarge);
+    }
+
+    match uint_from_be_bytes::<{ U8192::LIMBS }>(&params.p).bits_vartime() {
+        ..=3072 => register(params, CustomGroup::Bits3072),
+        3073..=4096 => register(params, CustomGroup::Bits4096),
+        4097..=6144 => register(params, CustomGroup::Bits6144),
+        _ => register(params, CustomGroup::Bits8192),
+    }
+}
+
+// the groups loaded so far, in the order they were loaded
+pub fn custom_groups() -> Vec<GroupId> {
+    let groups = CUSTOM_GROUPS.lock().unwrap();
+    groups.iter().map(|&(id, _)| GroupId::Custom(id)).collect()
+}
+
+impl fmt::Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// RFC 3526 and RFC 7919 groups, set up on first use. all of them are safe primes with 2
+// generating the order-q subgroup (2 is a quadratic residue since p = 7 mod 8).
+pub static MODP_2048: LazyLock<Group<{ U2048::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "2048-bit MODP (RFC 3526)",
+        U2048::from_be_hex(MODP_2048_P),
+        U2048::from_u8(2),
+    )
+});
+pub static MODP_3072: LazyLock<Group<{ U3072::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "3072-bit MODP (RFC 3526)",
+        U3072::from_be_hex(MODP_3072_P),
+        U3072::from_u8(2),
+    )
+});
+pub static MODP_4096: LazyLock<Group<{ U4096::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "4096-bit MODP (RFC 3526)",
+        U4096::from_be_hex(MODP_4096_P),
+        U4096::from_u8(2),
+    )
+});
+pub static FFDHE_2048: LazyLock<Group<{ U2048::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "ffdhe2048 (RFC 7919)",
+        U2048::from_be_hex(FFDHE_2048_P),
+        U2048::from_u8(2),
+    )
+});
+pub static FFDHE_3072: LazyLock<Group<{ U3072::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "ffdhe3072 (RFC 7919)",
+        U3072::from_be_hex(FFDHE_3072_P),
+        U3072::from_u8(2),
+    )
+});
+pub static FFDHE_4096: LazyLock<Group<{ U4096::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "ffdhe4096 (RFC 7919)",
+        U4096::from_be_hex(FFDHE_4096_P),
+        U4096::from_u8(2),
+    )
+});
+pub static FFDHE_6144: LazyLock<Group<{ U6144::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "ffdhe6144 (RFC 7919)",
+        U6144::from_be_hex(FFDHE_6144_P),
+        U6144::from_u8(2),
+    )
+});
+pub static FFDHE_8192: LazyLock<Group<{ U8192::LIMBS }>> = LazyLock::new(|| {
+    Group::new(
+        "ffdhe8192 (RFC 7919)",
+        U8192::from_be_hex(FFDHE_8192_P),
+        U8192::from_u8(2),
+    )
+});
+
+const MODP_2048_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF",
+);
+
+const MODP_3072_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF",
+);
+
+const MODP_4096_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+    "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8",
+    "DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2",
+    "233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+    "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF",
+);
+
+const FFDHE_2048_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B423861285C97FFFFFFFFFFFFFFFF",
+);
+
+const FFDHE_3072_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+    "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+    "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+    "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+    "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B66C62E37FFFFFFFFFFFFFFFF",
+);
+
+const FFDHE_4096_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+    "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+    "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+    "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+    "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB",
+    "7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A",
+    "7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038",
+    "092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF",
+    "8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E655F6AFFFFFFFFFFFFFFFF",
+);
+
+const FFDHE_6144_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+    "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+    "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+    "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+    "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB",
+    "7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A",
+    "7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038",
+    "092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF",
+    "8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E0DD9020BFD64B645036C7A",
+    "4E677D2C38532A3A23BA4442CAF53EA63BB454329B7624C8917BDD64B1C0FD4C",
+    "B38E8C334C701C3ACDAD0657FCCFEC719B1F5C3E4E46041F388147FB4CFDB477",
+    "A52471F7A9A96910B855322EDB6340D8A00EF092350511E30ABEC1FFF9E3A26E",
+    "7FB29F8C183023C3587E38DA0077D9B4763E4E4B94B2BBC194C6651E77CAF992",
+    "EEAAC0232A281BF6B3A739C1226116820AE8DB5847A67CBEF9C9091B462D538C",
+    "D72B03746AE77F5E62292C311562A846505DC82DB854338AE49F5235C95B9117",
+    "8CCF2DD5CACEF403EC9D1810C6272B045B3B71F9DC6B80D63FDD4A8E9ADB1E69",
+    "62A69526D43161C1A41D570D7938DAD4A40E329CD0E40E65FFFFFFFFFFFFFFFF",
+);
+
+const FFDHE_8192_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+    "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+    "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+    "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+    "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB",
+    "7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A",
+    "7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038",
+    "092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF",
+    "8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E0DD9020BFD64B645036C7A",
+    "4E677D2C38532A3A23BA4442CAF53EA63BB454329B7624C8917BDD64B1C0FD4C",
+    "B38E8C334C701C3ACDAD0657FCCFEC719B1F5C3E4E46041F388147FB4CFDB477",
+    "A52471F7A9A96910B855322EDB6340D8A00EF092350511E30ABEC1FFF9E3A26E",
+    "7FB29F8C183023C3587E38DA0077D9B4763E4E4B94B2BBC194C6651E77CAF992",
+    "EEAAC0232A281BF6B3A739C1226116820AE8DB5847A67CBEF9C9091B462D538C",
+    "D72B03746AE77F5E62292C311562A846505DC82DB854338AE49F5235C95B9117",
+    "8CCF2DD5CACEF403EC9D1810C6272B045B3B71F9DC6B80D63FDD4A8E9ADB1E69",
+    "62A69526D43161C1A41D570D7938DAD4A40E329CCFF46AAA36AD004CF600C838",
+    "1E425A31D951AE64FDB23FCEC9509D43687FEB69EDD1CC5E0B8CC3BDF64B10EF",
+    "86B63142A3AB8829555B2F747C932665CB2C0F1CC01BD70229388839D2AF05E4",
+    "54504AC78B7582822846C0BA35C35F5C59160CC046FD8251541FC68C9C86B022",
+    "BB7099876A460E7451A8A93109703FEE1C217E6C3826E52C51AA691E0E423CFC",
+    "99E9E31650C1217B624816CDAD9A95F9D5B8019488D9C0A0A1FE3075A577E231",
+    "83F81D4A3F2FA4571EFC8CE0BA8A4FE8B6855DFE72B0A66EDED2FBABFBE58A30",
+    "FAFABE1C5D71A87E2F741EF8C1FE86FEA6BBFDE530677F0D97D11D49F7A8443D",
+    "0822E506A9F4614E011E2A94838FF88CD68C8BB7C5C6424CFFFFFFFFFFFFFFFF",
+);
+
+#[cfg(test)]
+mod tests {
+    use crypto_bigint::U64;
+
+    use super::*;
+
+    #[test]
+    fn montgomery_matches_division() {
+        let group = &*MODP_2048;
+        let a = group.pow_g(&group.generate_exponent());
+        let b = group.pow_g(&group.generate_exponent());
+
+        let expected = a.mul(&b).wrapping_rem(&group.p.resize());
+        assert_eq!(group.mul(&a, &b), expected.resize());
+
+        let mut expected = Uint::ONE;
+        for _ in 0..5 {
+            expected = group.mul(&expected, &a);
+        }
+        assert_eq!(group.pow(&a, &Uint::from_u8(5)), expected);
+    }
+
+    fn check_fixed_base<const LIMBS: usize>(group: &Group<LIMBS>) {
+        // p - 1 is past the end of the tables
+        for exp in [
+            Uint::ZERO,
+            Uint::ONE,
+            group.q.wrapping_sub(&Uint::ONE),
+            group.generate_exponent(),
+            group.p_minus_one,
+        ] {
+            assert_eq!(group.pow_g(&exp), group.pow(&group.g, &exp));
+        }
+        assert!(group.g_table().len() <= MAX_G_TABLES);
+    }
+
+    #[test]
+    fn fixed_base_matches_variable_base() {
+        check_fixed_base(&*FFDHE_3072);
+        // several windows to a table
+        check_fixed_base(&*FFDHE_8192);
+        assert_eq!(FFDHE_8192.g_spacing(), 8);
+
+        // a q much smaller than p, which needs only a few tables
+        let group = Group::with_order(
+            "test",
+            U64::from_u64(18446744073709550147),
+            U64::from_u64(9223372036854775073),
+            U64::from_u8(4),
+        );
+        check_fixed_base(&group);
+        let small = Group::with_order("test", U64::from_u8(23), U64::from_u8(11), U64::from_u8(2));
+        check_fixed_base(&small);
+        assert_eq!(small.g_table().len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_elements() {
+        let group = &*FFDHE_3072;
+        let valid = group.pow_g(&group.generate_exponent());
+        assert_eq!(group.check_subgroup(&valid), Ok(()));
+
+        let negated = group.mul(&valid, &group.p_minus_one); // has order 2q
+        let cases = [
+            (Uint::ZERO, InvalidElement::OutOfRange),
+            (group.p, InvalidElement::OutOfRange),
+            (Uint::MAX, InvalidElement::OutOfRange),
+            (Uint::ONE, InvalidElement::Trivial),
+            (group.p_minus_one, InvalidElement::Trivial),
+            (negated, InvalidElement::NotInSubgroup),
+        ];
+        for (x, error) in cases {
+            assert_eq!(group.check_subgroup(&x), Err(error));
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        let group = &*FFDHE_3072;
+        let x = group.pow_g(&group.generate_exponent());
+        let bytes = group.encode(&x);
+        assert_eq!(bytes.len(), 384);
+        assert_eq!(group.decode(&bytes), Ok(x));
+        assert_eq!(group.decode(&bytes[1..]), Err(InvalidElement::WrongLength));
+
+        assert_eq!(
+            GroupId::ALL.map(GroupId::bits),
+            [3072, 4096, 6144, 8192, 3072, 4096]
+        );
+    }
+
+    #[test]
+    fn builtin_groups_pass_self_check() {
+        for check in self_check_all() {
+            assert_eq!(check.result, Ok(()), "{}", check.name);
+        }
+    }
+
+    #[test]
+    fn self_check_detects_bad_params() {
+        let group = |p: u64, g: u64| Group::new("test", U64::from_u64(p), U64::from_u64(g));
+
+        assert_eq!(group(23, 2).self_check(), Ok(()));
+        // 5 is not a quadratic residue modulo 23, so it has order 22
+        assert_eq!(group(23, 5).self_check(), Err(InvalidParams::BadGenerator));
+        assert_eq!(group(23, 22).self_check(), Err(InvalidParams::BadGenerator));
+        // 29 is prime, but 14 isn't
+        assert_eq!(group(29, 4).self_check(), Err(InvalidParams::NotSafePrime));
+        assert_eq!(group(27, 4).self_check(), Err(InvalidParams::NotSafePrime));
+    }
+
+    #[test]
+    fn loads_custom_groups() {
+        let small = GroupParams {
+            p: vec![23],
+            g: vec![2],
+        };
+        assert_eq!(load_group(&small), Err(InvalidParams::TooSmall));
+
+        // the same prime as a built-in group, but loaded the way a generated one would be
+        let mut even = FFDHE_3072.to_params();
+        *even.p.last_mut().unwrap() ^= 1;
+        assert_eq!(load_group(&even), Err(InvalidParams::NotSafePrime));
+
+        let params = FFDHE_3072.to_params();
+        let id = load_group(&params).unwrap();
+        assert!(matches!(id, GroupId::Custom(_)));
+        assert_eq!(id.bits(), 3072);
+        assert_eq!(load_group(&params), Ok(id));
+        assert!(custom_groups().contains(&id));
+
+        let bytes = bincode::serialize(&id).unwrap();
+        assert_eq!(bincode::deserialize::<GroupId>(&bytes).unwrap(), id);
+        // an id that nobody loaded here can't be received
+        let mut unknown = bytes.clone();
+        *unknown.last_mut().unwrap() ^= 1;
+        assert!(bincode::deserialize::<GroupId>(&unknown).is_err());
+
+        with_group!(id, |group| {
+            let x = group.generate_exponent();
+            assert_eq!(group.check_subgroup(&group.pow_g(&x)), Ok(()));
+        });
+    }
+
+    #[test]
+    fn derived_generator() {
+        let group = &*FFDHE_3072;
+        let h = group.derive_generator(GENERATOR_SEED, 1);
+        assert_eq!(group.check_subgroup(&h), Ok(()));
+        assert_eq!(h, group.derive_generator(GENERATOR_SEED, 1));
+        assert_ne!(h, group.derive_generator(GENERATOR_SEED, 2));
+        assert_ne!(h, group.g);
+
+        // groups smaller than the hash work too
+        let small = Group::new("test", U64::from_u64(18446744073709550147), U64::from_u8(4));
+        let tiny = Group::new("test", U64::from_u8(23), U64::from_u8(2));
+        for index in 0..4 {
+            let h = small.derive_generator(GENERATOR_SEED, index);
+            assert_eq!(small.check_subgroup(&h), Ok(()));
+            let h = tiny.derive_generator(GENERATOR_SEED, index);
+            assert_eq!(tiny.check_subgroup(&h), Ok(()));
+        }
+    }
+}
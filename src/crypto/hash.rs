@@ -0,0 +1,31 @@
+use std::fmt::Debug;
+
+// a Merkle-Damgard style hash function that can be fed its input in pieces. everything built on
+// top of a hash (e.g. hmac) should be generic over this, so that new hashes get it for free.
+pub trait HashFunction: Clone {
+    const BLOCK_BYTES: usize;
+    const DIGEST_BYTES: usize;
+
+    // [u8; BLOCK_BYTES] and [u8; DIGEST_BYTES] respectively
+    type Block: ByteArray;
+    type Digest: ByteArray;
+
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Self::Digest;
+
+    fn digest(data: &[u8]) -> Self::Digest {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+// lets generic code create and index fixed-size byte arrays whose length it can't name
+pub trait ByteArray: AsRef<[u8]> + AsMut<[u8]> + Copy + Eq + Debug {
+    const ZERO: Self;
+}
+
+impl<const N: usize> ByteArray for [u8; N] {
+    const ZERO: Self = [0; N];
+}
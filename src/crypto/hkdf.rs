@@ -0,0 +1,116 @@
+use super::hmac::{self, hmac};
+use super::sha256::{Digest, Sha256, DIGEST_BYTES};
+
+pub const MAX_OUTPUT_BYTES: usize = 255 * DIGEST_BYTES;
+
+// HKDF-Extract (RFC 5869, section 2.2)
+pub fn extract(salt: &[u8], ikm: &[u8]) -> Digest {
+    // an empty salt is the same as a block of zeros, which is what from_bytes produces
+    hmac(&hmac::Key::<Sha256>::from_bytes(salt), ikm)
+}
+
+// HKDF-Expand (RFC 5869, section 2.3), filling the entire output slice
+pub fn expand(prk: &Digest, info: &[u8], output: &mut [u8]) {
+    assert!(output.len() <= MAX_OUTPUT_BYTES);
+
+    let key = hmac::Key::<Sha256>::from_bytes(prk);
+
+    // T(0) is the empty string, every T(i) after it is a full digest
+    let mut t = [0u8; DIGEST_BYTES];
+    let mut t_len = 0;
+
+    let mut input = Vec::with_capacity(DIGEST_BYTES + info.len() + 1);
+    for (i, chunk) in output.chunks_mut(DIGEST_BYTES).enumerate() {
+        input.clear();
+        input.extend_from_slice(&t[..t_len]);
+        input.extend_from_slice(info);
+        input.push(i as u8 + 1);
+
+        t = hmac(&key, &input);
+        t_len = DIGEST_BYTES;
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+pub fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], output: &mut [u8]) {
+    let prk = extract(salt, ikm);
+    expand(&prk, info, output);
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // RFC 5869, appendix A.1
+    #[test]
+    fn rfc5869_basic() {
+        let ikm = [0x0b; 22];
+        let salt = hex!("000102030405060708090a0b0c");
+        let info = hex!("f0f1f2f3f4f5f6f7f8f9");
+
+        let prk = extract(&salt, &ikm);
+        assert_eq!(
+            prk,
+            hex!("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+        );
+
+        let mut okm = [0u8; 42];
+        expand(&prk, &info, &mut okm);
+        assert_eq!(
+            okm,
+            hex!(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf"
+                "34007208d5b887185865"
+            )
+        );
+    }
+
+    // RFC 5869, appendix A.2
+    #[test]
+    fn rfc5869_long_inputs() {
+        let ikm: Vec<u8> = (0x00..=0x4f).collect();
+        let salt: Vec<u8> = (0x60..=0xaf).collect();
+        let info: Vec<u8> = (0xb0..=0xff).collect();
+
+        let prk = extract(&salt, &ikm);
+        assert_eq!(
+            prk,
+            hex!("06a6b88c5853361a06104c9ceb35b45cef760014904671014a193f40c15fc244")
+        );
+
+        let mut okm = [0u8; 82];
+        expand(&prk, &info, &mut okm);
+        assert_eq!(
+            okm,
+            hex!(
+                "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c"
+                "59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71"
+                "cc30c58179ec3e87c14c01d5c1f3434f1d87"
+            )
+        );
+    }
+
+    // RFC 5869, appendix A.3
+    #[test]
+    fn rfc5869_empty_salt_and_info() {
+        let ikm = [0x0b; 22];
+
+        let prk = extract(&[], &ikm);
+        assert_eq!(
+            prk,
+            hex!("19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04")
+        );
+
+        let mut okm = [0u8; 42];
+        hkdf(&[], &ikm, &[], &mut okm);
+        assert_eq!(
+            okm,
+            hex!(
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d"
+                "9d201395faa4b61a96c8"
+            )
+        );
+    }
+}
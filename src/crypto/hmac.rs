@@ -1,42 +1,157 @@
-use rand_core::{OsRng, RngCore};
+use rand_core::RngCore;
 
-use super::sha256::{self, Digest, BLOCK_BYTES, DIGEST_BYTES};
+use super::drbg::ThreadDrbg;
+use super::hash::{ByteArray, HashFunction};
+use super::sha256::{self, Sha256};
 
-#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
-#[derive(Clone)]
-pub struct Key(pub(crate) Block);
+pub struct Key<H: HashFunction = Sha256>(pub(crate) H::Block);
 
 pub const KEY_BYTES: usize = sha256::BLOCK_BYTES;
 
-type Block = [u8; sha256::BLOCK_BYTES];
-const OPAD: Block = [0x5c; sha256::BLOCK_BYTES];
-const IPAD: Block = [0x36; sha256::BLOCK_BYTES];
+const OPAD: u8 = 0x5c;
+const IPAD: u8 = 0x36;
 
-pub fn hmac(key: &Key, message: &[u8]) -> Digest {
-    let mut inner_input = Vec::with_capacity(BLOCK_BYTES + message.len());
-    inner_input.extend_from_slice(&xor_block(&key.0, &IPAD));
-    inner_input.extend_from_slice(message);
-    let inner = sha256::hash(&inner_input);
+pub fn hmac<H: HashFunction>(key: &Key<H>, message: &[u8]) -> H::Digest {
+    let mut mac = Hmac::new(key);
+    mac.update(message);
+    mac.finalize()
+}
 
-    let mut outer_input = Vec::with_capacity(BLOCK_BYTES + DIGEST_BYTES);
-    outer_input.extend_from_slice(&xor_block(&key.0, &OPAD));
-    outer_input.extend_from_slice(&inner);
-    sha256::hash(&outer_input)
+// the streaming form of `hmac`, for when the message isn't available all at once
+#[derive(Clone)]
+pub struct Hmac<H: HashFunction = Sha256> {
+    inner: H,
+    outer: H,
 }
 
-fn xor_block(a: &Block, b: &Block) -> Block {
-    let mut output = [0; sha256::BLOCK_BYTES];
-    for i in 0..output.len() {
-        output[i] = a[i] ^ b[i];
+impl<H: HashFunction> Hmac<H> {
+    pub fn new(key: &Key<H>) -> Self {
+        let mut inner = H::new();
+        inner.update(xor_block::<H>(&key.0, IPAD).as_ref());
+
+        let mut outer = H::new();
+        outer.update(xor_block::<H>(&key.0, OPAD).as_ref());
+
+        Self { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> H::Digest {
+        let Self { inner, mut outer } = self;
+        outer.update(inner.finalize().as_ref());
+        outer.finalize()
+    }
+}
+
+fn xor_block<H: HashFunction>(block: &H::Block, pad: u8) -> H::Block {
+    let mut output = *block;
+    for byte in output.as_mut() {
+        *byte ^= pad;
     }
 
     output
 }
 
-impl Key {
+impl<H: HashFunction> Key<H> {
     pub fn generate() -> Self {
-        let mut bytes = [0u8; BLOCK_BYTES];
-        OsRng.fill_bytes(&mut bytes);
+        let mut bytes = H::Block::ZERO;
+        ThreadDrbg.fill_bytes(bytes.as_mut());
         Self(bytes)
     }
+
+    // keys longer than a block are hashed first, shorter keys are zero-padded (RFC 2104)
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut block = H::Block::ZERO;
+        if bytes.len() > H::BLOCK_BYTES {
+            block.as_mut()[..H::DIGEST_BYTES].copy_from_slice(H::digest(bytes).as_ref());
+        } else {
+            block.as_mut()[..bytes.len()].copy_from_slice(bytes);
+        }
+
+        Self(block)
+    }
+}
+
+impl<H: HashFunction> Clone for Key<H> {
+    fn clone(&self) -> Self {
+        Self(self.0)
+    }
+}
+
+#[cfg(test)]
+impl<H: HashFunction> PartialEq for Key<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+#[cfg(test)]
+impl<H: HashFunction> Eq for Key<H> {}
+
+#[cfg(test)]
+impl<H: HashFunction> std::fmt::Debug for Key<H> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Key").field(&self.0).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // RFC 4231, test case 1
+    #[test]
+    fn rfc4231_basic() {
+        let key = Key::<Sha256>::from_bytes(&[0x0b; 20]);
+        assert_eq!(
+            hmac(&key, b"Hi There"),
+            hex!("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7")
+        );
+    }
+
+    // RFC 4231, test case 2
+    #[test]
+    fn rfc4231_short_key() {
+        let key = Key::<Sha256>::from_bytes(b"Jefe");
+        assert_eq!(
+            hmac(&key, b"what do ya want for nothing?"),
+            hex!("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+
+    // RFC 4231, test case 6
+    #[test]
+    fn rfc4231_long_key() {
+        let key = Key::<Sha256>::from_bytes(&[0xaa; 131]);
+        assert_eq!(
+            hmac(
+                &key,
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            hex!("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")
+        );
+    }
+
+    // RFC 4231, test case 7
+    #[test]
+    fn rfc4231_long_key_and_data() {
+        let key = Key::<Sha256>::from_bytes(&[0xaa; 131]);
+        let data = b"This is a test using a larger than block-size key and a larger than \
+            block-size data. The key needs to be hashed before being used by the HMAC algorithm.";
+
+        let mut mac = Hmac::new(&key);
+        for chunk in data.chunks(10) {
+            mac.update(chunk);
+        }
+
+        assert_eq!(
+            mac.finalize(),
+            hex!("9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2")
+        );
+    }
 }
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crypto_bigint::Uint;
+use serde::{Deserialize, Serialize};
+
+use super::elgamal::{Alice, AlicePub};
+use super::group::{uint_from_be_bytes, with_group, Group, GroupId, InvalidElement};
+use super::hash::HashFunction;
+use super::sha256::Sha256;
+
+// exponential ElGamal, with the same keys as elgamal: m is encrypted as (g^r, g^m * y^r), so
+// multiplying ciphertexts adds their plaintexts. decryption only recovers g^m, and the discrete
+// logarithm is then found with baby-step giant-step, which is only feasible for small m. this is
+// enough to tally polls, where every ballot is 0 or 1.
+
+const PROOF_TAG: &[u8] = b"titanium-rose ballot";
+
+// the largest plaintext that decrypt looks for: 2^20 baby steps take some 400 MB with 3072-bit
+// groups, and as many giant steps a few seconds
+pub const MAX_PLAINTEXT: u64 = 1 << 40;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecryptError {
+    InvalidElement(InvalidElement),
+    // the bound is above MAX_PLAINTEXT
+    BoundTooLarge,
+}
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidElement(error) => error.fmt(f),
+            Self::BoundTooLarge => write!(f, "can't search for plaintexts above 2^40"),
+        }
+    }
+}
+
+impl std::error::Error for DecryptError {}
+
+impl From<InvalidElement> for DecryptError {
+    fn from(error: InvalidElement) -> Self {
+        Self::InvalidElement(error)
+    }
+}
+
+// (g^r, g^m * y^r)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(GroupId, Vec<u8>, Vec<u8>)")]
+pub struct Ciphertext(GroupId, Vec<u8>, Vec<u8>);
+
+impl TryFrom<(GroupId, Vec<u8>, Vec<u8>)> for Ciphertext {
+    type Error = InvalidElement;
+
+    fn try_from((group_id, c1, c2): (GroupId, Vec<u8>, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            group.check_subgroup(&group.decode(&c1)?)?;
+            group.check_subgroup(&group.decode(&c2)?)
+        })?;
+        Ok(Self(group_id, c1, c2))
+    }
+}
+
+impl Ciphertext {
+    pub fn group(&self) -> GroupId {
+        self.0
+    }
+
+    // an encryption of 0 without any randomness, to start sums from
+    fn zero(group_id: GroupId) -> Self {
+        with_group!(group_id, |group| {
+            let one = group.encode(&Uint::ONE);
+            Self(group_id, one.clone(), one)
+        })
+    }
+
+    pub(crate) fn from_elements<const LIMBS: usize>(
+        group_id: GroupId,
+        group: &Group<LIMBS>,
+        (c1, c2): &(Uint<LIMBS>, Uint<LIMBS>),
+    ) -> Self {
+        Self(group_id, group.encode(c1), group.encode(c2))
+    }
+
+    // ciphertexts are validated when they're created
+    pub(crate) fn elements<const LIMBS: usize>(
+        &self,
+        group: &Group<LIMBS>,
+    ) -> (Uint<LIMBS>, Uint<LIMBS>) {
+        (
+            group.decode(&self.1).unwrap(),
+            group.decode(&self.2).unwrap(),
+        )
+    }
+
+    // a fresh ciphertext of the same plaintext, which can't be linked to this one without the key
+    pub fn rerandomize(&self, public: &AlicePub) -> Result<Self, InvalidElement> {
+        if self.0 != public.group() {
+            return Err(InvalidElement::GroupMismatch);
+        }
+
+        with_group!(self.0, |group| {
+            let y = group.decode(public.encoded()).unwrap();
+            let r = group.generate_exponent();
+            let c = rerandomize_with(group, &y, &self.elements(group), &r);
+            Ok(Self::from_elements(self.0, group, &c))
+        })
+    }
+
+    // a ciphertext of the sum of both plaintexts
+    pub fn add(&self, other: &Self) -> Result<Self, InvalidElement> {
+        if self.0 != other.0 {
+            return Err(InvalidElement::GroupMismatch);
+        }
+
+        with_group!(self.0, |group| {
+            // both were validated when they were created
+            let [a1, a2, b1, b2] =
+                [&self.1, &self.2, &other.1, &other.2].map(|x| group.decode(x).unwrap());
+            Ok(Self(
+                self.0,
+                group.encode(&group.mul(&a1, &b1)),
+                group.encode(&group.mul(&a2, &b2)),
+            ))
+        })
+    }
+}
+
+fn encrypt_with<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    y: &Uint<LIMBS>,
+    m: &Uint<LIMBS>,
+    r: &Uint<LIMBS>,
+) -> (Uint<LIMBS>, Uint<LIMBS>) {
+    (group.pow_g(r), group.mul(&group.pow_g(m), &group.pow(y, r)))
+}
+
+// multiplies in the encryption (g^r, y^r) of 0
+pub(crate) fn rerandomize_with<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    y: &Uint<LIMBS>,
+    (c1, c2): &(Uint<LIMBS>, Uint<LIMBS>),
+    r: &Uint<LIMBS>,
+) -> (Uint<LIMBS>, Uint<LIMBS>) {
+    (
+        group.mul(c1, &group.pow_g(r)),
+        group.mul(c2, &group.pow(y, r)),
+    )
+}
+
+pub fn encrypt(public: &AlicePub, m: u64) -> Ciphertext {
+    let group_id = public.group();
+    with_group!(group_id, |group| {
+        // public keys are validated when they're created
+        let y = group.decode(public.encoded()).unwrap();
+        let r = group.generate_exponent();
+        let (c1, c2) = encrypt_with(group, &y, &Uint::from_u64(m), &r);
+        Ciphertext(group_id, group.encode(&c1), group.encode(&c2))
+    })
+}
+
+// recovers m, or None if it's larger than max, which can be at most MAX_PLAINTEXT. takes about
+// 2 * sqrt(max) multiplications, and as much memory for the baby steps.
+pub fn decrypt(
+    alice: &Alice,
+    ciphertext: &Ciphertext,
+    max: u64,
+) -> Result<Option<u64>, DecryptError> {
+    if ciphertext.0 != alice.get_public().group() {
+        return Err(InvalidElement::GroupMismatch.into());
+    }
+    if max > MAX_PLAINTEXT {
+        return Err(DecryptError::BoundTooLarge);
+    }
+
+    with_group!(ciphertext.0, |group| {
+        let x = group.decode(alice.secret())?;
+        let c1 = group.decode(&ciphertext.1)?;
+        let c2 = group.decode(&ciphertext.2)?;
+
+        // g^m = c2 * c1^(-x)
+        let target = group.mul(&c2, &group.inv_pow(&c1, &x));
+        Ok(discrete_log(group, &target, max))
+    })
+}
+
+// baby-step giant-step: m = i * steps + j, with g^j looked up in a table for every i
+fn discrete_log<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    target: &Uint<LIMBS>,
+    max: u64,
+) -> Option<u64> {
+    let steps = max.isqrt() + 1;
+
+    let mut baby_steps = HashMap::with_capacity(steps as usize);
+    let mut power = Uint::ONE;
+    for j in 0..steps {
+        baby_steps.entry(power.to_words()).or_insert(j);
+        power = group.mul(&power, group.g());
+    }
+
+    // power is g^steps now
+    let giant_step = group.inv_pow(&power, &Uint::ONE);
+    let mut gamma = *target;
+    for i in 0..steps {
+        if let Some(j) = baby_steps.get(&gamma.to_words()) {
+            let m = i * steps + j;
+            return (m <= max).then_some(m);
+        }
+        gamma = group.mul(&gamma, &giant_step);
+    }
+
+    None
+}
+
+// an encrypted vote of 0 or 1, with a proof that it is one of them. the proof is a disjunction
+// of two Chaum-Pedersen proofs (Cramer, Damgard and Schoenmakers), one that log_g c1 = log_y c2
+// and one that log_g c1 = log_y (c2 / g). only the branch of the actual vote is proven for real,
+// the other one is simulated, and Fiat-Shamir challenges make it non-interactive.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Ballot {
+    ciphertext: Ciphertext,
+    challenges: [Vec<u8>; 2],
+    responses: [Vec<u8>; 2],
+}
+
+type Commitment<const LIMBS: usize> = (Uint<LIMBS>, Uint<LIMBS>);
+
+// the commitments (g^z * c1^(-e), y^z * (c2 / g^vote)^(-e)) that a valid proof of the branch for
+// `vote` reproduces
+fn commitment<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    y: &Uint<LIMBS>,
+    (c1, c2): &(Uint<LIMBS>, Uint<LIMBS>),
+    vote: usize,
+    e: &Uint<LIMBS>,
+    z: &Uint<LIMBS>,
+) -> Commitment<LIMBS> {
+    let shifted = match vote {
+        0 => *c2,
+        _ => group.mul(c2, &group.inv_pow(group.g(), &Uint::ONE)),
+    };
+
+    (
+        group.mul(&group.pow_g(z), &group.inv_pow(c1, e)),
+        group.mul(&group.pow(y, z), &group.inv_pow(&shifted, e)),
+    )
+}
+
+fn challenge<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    public: &[u8],
+    (c1, c2): &(Uint<LIMBS>, Uint<LIMBS>),
+    commitments: &[Commitment<LIMBS>; 2],
+) -> Uint<LIMBS> {
+    let mut hasher = Sha256::new();
+    hasher.update(PROOF_TAG);
+    hasher.update(group.name().as_bytes());
+    hasher.update(public);
+    for x in [c1, c2] {
+        hasher.update(&group.encode(x));
+    }
+    for (a, b) in commitments {
+        hasher.update(&group.encode(a));
+        hasher.update(&group.encode(b));
+    }
+
+    group.reduce_exponent(&uint_from_be_bytes(&hasher.finalize()))
+}
+
+impl Ballot {
+    pub fn cast(public: &AlicePub, vote: bool) -> Self {
+        let group_id = public.group();
+        with_group!(group_id, |group| {
+            let y = group.decode(public.encoded()).unwrap();
+            let (real, fake) = (vote as usize, !vote as usize);
+
+            let r = group.generate_exponent();
+            let c = encrypt_with(group, &y, &Uint::from_u8(real as u8), &r);
+
+            // the simulated branch picks its challenge and response first
+            let mut e = [Uint::ZERO; 2];
+            let mut z = [Uint::ZERO; 2];
+            e[fake] = group.generate_exponent();
+            z[fake] = group.generate_exponent();
+
+            let w = group.generate_exponent();
+            let mut commitments = [(Uint::ZERO, Uint::ZERO); 2];
+            commitments[fake] = commitment(group, &y, &c, fake, &e[fake], &z[fake]);
+            commitments[real] = (group.pow_g(&w), group.pow(&y, &w));
+
+            // the challenges have to add up to the hash, which leaves only one choice for the
+            // real branch
+            let total = challenge(group, public.encoded(), &c, &commitments);
+            e[real] = group.sub_exponents(&total, &e[fake]);
+            z[real] = group.add_exponents(&w, &group.mul_exponents(&e[real], &r));
+
+            Self {
+                ciphertext: Ciphertext(group_id, group.encode(&c.0), group.encode(&c.1)),
+                challenges: e.map(|e| group.encode(&e)),
+                responses: z.map(|z| group.encode(&z)),
+            }
+        })
+    }
+
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ciphertext
+    }
+
+    pub fn verify(&self, public: &AlicePub) -> bool {
+        if self.ciphertext.0 != public.group() {
+            return false;
+        }
+
+        with_group!(public.group(), |group| {
+            // the challenges and responses, which all have to be smaller than q
+            let exponents: Option<Vec<_>> = self
+                .challenges
+                .iter()
+                .chain(&self.responses)
+                .map(|x| group.decode(x).ok().filter(|x| x < group.q()))
+                .collect();
+            let Some(&[e0, e1, z0, z1]) = exponents.as_deref() else {
+                return false;
+            };
+            let (e, z) = ([e0, e1], [z0, z1]);
+
+            let y = group.decode(public.encoded()).unwrap();
+            let c = (
+                group.decode(&self.ciphertext.1).unwrap(),
+                group.decode(&self.ciphertext.2).unwrap(),
+            );
+            let commitments =
+                [0, 1].map(|vote| commitment(group, &y, &c, vote, &e[vote], &z[vote]));
+
+            group.add_exponents(&e[0], &e[1])
+                == challenge(group, public.encoded(), &c, &commitments)
+        })
+    }
+}
+
+// the encrypted number of yes votes. None if any of the ballots is invalid.
+pub fn tally<'a>(
+    public: &AlicePub,
+    ballots: impl IntoIterator<Item = &'a Ballot>,
+) -> Option<Ciphertext> {
+    let mut sum = Ciphertext::zero(public.group());
+    for ballot in ballots {
+        if !ballot.verify(public) {
+            return None;
+        }
+        sum = sum.add(&ballot.ciphertext).unwrap();
+    }
+
+    // without any ballots, the sum is still the trivial ciphertext that nobody would accept
+    Some(sum.rerandomize(public).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addition() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let a = encrypt(alice.get_public(), 1234);
+        let b = encrypt(alice.get_public(), 4321);
+        let sum = a.add(&b).unwrap();
+
+        assert_eq!(decrypt(&alice, &a, 10000), Ok(Some(1234)));
+        assert_eq!(decrypt(&alice, &sum, 10000), Ok(Some(5555)));
+        assert_eq!(decrypt(&alice, &sum, 5555), Ok(Some(5555)));
+        assert_eq!(decrypt(&alice, &sum, 5554), Ok(None));
+
+        let rerandomized = a.rerandomize(alice.get_public()).unwrap();
+        assert_ne!(rerandomized, a);
+        assert_eq!(decrypt(&alice, &rerandomized, 10000), Ok(Some(1234)));
+        assert_eq!(
+            decrypt(&alice, &encrypt(alice.get_public(), 0), 0),
+            Ok(Some(0))
+        );
+        assert_eq!(
+            decrypt(&alice, &a, MAX_PLAINTEXT + 1),
+            Err(DecryptError::BoundTooLarge)
+        );
+
+        let other = Alice::generate(GroupId::Modp3072);
+        assert_eq!(
+            a.add(&encrypt(other.get_public(), 1)),
+            Err(InvalidElement::GroupMismatch)
+        );
+    }
+
+    #[test]
+    fn poll() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let votes = [true, false, true, true, false];
+        let ballots: Vec<Ballot> = votes
+            .iter()
+            .map(|&vote| Ballot::cast(alice.get_public(), vote))
+            .collect();
+        assert!(ballots
+            .iter()
+            .all(|ballot| ballot.verify(alice.get_public())));
+
+        let sum = tally(alice.get_public(), &ballots).unwrap();
+        assert_eq!(decrypt(&alice, &sum, votes.len() as u64), Ok(Some(3)));
+
+        // an empty poll still gives a ciphertext that can be sent
+        let sum = tally(alice.get_public(), []).unwrap();
+        let sum: Ciphertext = bincode::deserialize(&bincode::serialize(&sum).unwrap()).unwrap();
+        assert_eq!(decrypt(&alice, &sum, 0), Ok(Some(0)));
+    }
+
+    #[test]
+    fn rejects_invalid_ballots() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let ballot = Ballot::cast(alice.get_public(), true);
+
+        // a proof for one ciphertext doesn't carry over to another, e.g. one of 2 votes
+        let stuffed = Ballot {
+            ciphertext: encrypt(alice.get_public(), 2),
+            ..ballot.clone()
+        };
+        assert!(!stuffed.verify(alice.get_public()));
+        assert_eq!(tally(alice.get_public(), [&ballot, &stuffed]), None);
+
+        let mut tampered = ballot.clone();
+        tampered.challenges.swap(0, 1);
+        assert!(!tampered.verify(alice.get_public()));
+
+        let other = Alice::generate(GroupId::Ffdhe3072);
+        assert!(!ballot.verify(other.get_public()));
+    }
+
+    #[test]
+    fn deserialization_validates() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let ballot = Ballot::cast(alice.get_public(), false);
+        let bytes = bincode::serialize(&ballot).unwrap();
+        let ballot: Ballot = bincode::deserialize(&bytes).unwrap();
+        assert!(ballot.verify(alice.get_public()));
+
+        // what sums start from, which is never sent as it is
+        let zero = Ciphertext::zero(GroupId::Ffdhe3072);
+        let bytes = bincode::serialize(&zero).unwrap();
+        let error = bincode::deserialize::<Ciphertext>(&bytes).err().unwrap();
+        assert_eq!(error.to_string(), InvalidElement::Trivial.to_string());
+    }
+}
@@ -1,52 +1,82 @@
-use crypto_bigint::{Encoding, Limb, Uint};
-use rand_core::{OsRng, RngCore};
+use rand_core::RngCore;
 
+use self::drbg::ThreadDrbg;
 use self::hmac::hmac;
 
+pub mod commitment;
+pub mod dh;
+pub mod drbg;
+pub mod dsa;
+pub mod ed25519;
 pub mod elgamal;
+pub mod field25519;
+pub mod group;
+pub mod hash;
+pub mod hkdf;
 pub mod hmac;
+pub mod homomorphic;
+pub mod otp;
+pub mod paramgen;
+pub mod pbkdf2;
+pub mod primality;
+pub mod rfc6979;
+pub mod rsa;
+pub mod sas;
+pub mod schnorr;
+pub mod sha1;
 pub mod sha256;
+pub mod sha512;
+pub mod shuffle;
+pub mod sigma;
+pub mod spake2;
+pub mod threshold;
 pub mod twofish;
+pub mod x25519;
+
+const HKDF_SALT: &[u8] = b"titanium-rose v1";
+const TWOFISH_KEY_INFO: &[u8] = b"titanium-rose twofish key";
+const HMAC_KEY_INFO: &[u8] = b"titanium-rose hmac key";
+
+pub const PASSWORD_SALT_BYTES: usize = 16;
+pub const DEFAULT_PASSWORD_ITERATIONS: u32 = 600_000;
+// the iteration count comes with the ciphertext, so it's capped to keep a crafted one from tying
+// up whoever tries to decrypt it
+pub const MAX_PASSWORD_ITERATIONS: u32 = 10 * DEFAULT_PASSWORD_ITERATIONS;
 
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 #[derive(Clone)]
 pub struct SymmetricKey(twofish::Key, hmac::Key);
 
 impl SymmetricKey {
-    #[cfg(debug_assertions)]
+    #[cfg(any(test, debug_assertions))]
     pub fn generate() -> Self {
         Self(twofish::Key::generate(), hmac::Key::generate())
     }
 
-    #[cfg(not(debug_assertions))]
-    fn generate() -> Self {
-        Self(twofish::Key::generate(), hmac::Key::generate())
-    }
+    // derives the session keys from a transported or agreed secret. every key gets its own
+    // `info` label, so more of them can be added without touching the key exchange itself.
+    pub(crate) fn from_shared_secret(secret: &[u8]) -> Self {
+        let prk = hkdf::extract(HKDF_SALT, secret);
+
+        let mut twofish_bytes = [0u8; twofish::KEY_BYTES];
+        hkdf::expand(&prk, TWOFISH_KEY_INFO, &mut twofish_bytes);
+        let mut hmac_bytes = [0u8; hmac::KEY_BYTES];
+        hkdf::expand(&prk, HMAC_KEY_INFO, &mut hmac_bytes);
 
-    fn from_elgamal_int(int: &elgamal::Int) -> Self {
-        let bytes = int
-            .resize::<{ (twofish::KEY_BYTES + hmac::KEY_BYTES) / Limb::BYTES }>()
-            .to_be_bytes();
-        let twofish_bytes: [u8; twofish::KEY_BYTES] =
-            bytes[..twofish::KEY_BYTES].try_into().unwrap();
-        let hmac_bytes: [u8; hmac::KEY_BYTES] = bytes[twofish::KEY_BYTES..].try_into().unwrap();
         Self(twofish::Key(twofish_bytes), hmac::Key(hmac_bytes))
     }
 
-    fn to_elgamal_int(&self) -> elgamal::Int {
-        let mut bytes = Vec::with_capacity(twofish::KEY_BYTES + hmac::KEY_BYTES);
-        bytes.extend_from_slice(&self.0 .0);
-        bytes.extend_from_slice(&self.1 .0);
-        let int = Uint::<{ (twofish::KEY_BYTES + hmac::KEY_BYTES) / Limb::BYTES }>::from_be_bytes(
-            bytes.try_into().unwrap(),
-        );
-        int.resize()
+    pub fn from_password(password: &[u8], salt: &[u8], iterations: u32) -> Self {
+        let mut secret = [0u8; sha256::DIGEST_BYTES];
+        pbkdf2::pbkdf2(password, salt, iterations, &mut secret);
+
+        Self::from_shared_secret(&secret)
     }
 
     pub fn encrypt(&self, data: &[u8]) -> Vec<u8> {
         let iv = {
             let mut iv = [0u8; twofish::BLOCK_BYTES];
-            OsRng.fill_bytes(&mut iv);
+            ThreadDrbg.fill_bytes(&mut iv);
             iv
         };
 
@@ -106,6 +136,35 @@ impl SymmetricKey {
     }
 }
 
+// encrypts with a key derived from the password and a fresh salt. the salt and iteration count
+// are stored next to the ciphertext, so decryption only needs the password.
+pub fn encrypt_with_password(password: &[u8], iterations: u32, data: &[u8]) -> Vec<u8> {
+    assert!((1..=MAX_PASSWORD_ITERATIONS).contains(&iterations));
+
+    let mut salt = [0u8; PASSWORD_SALT_BYTES];
+    ThreadDrbg.fill_bytes(&mut salt);
+
+    let key = SymmetricKey::from_password(password, &salt, iterations);
+    PasswordCiphertext {
+        salt,
+        iterations,
+        ciphertext: key.encrypt(data),
+    }
+    .serialize()
+}
+
+pub fn decrypt_with_password(password: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let PasswordCiphertext {
+        salt,
+        iterations,
+        ciphertext,
+    } = PasswordCiphertext::deserialize(data)?;
+
+    // tampering with the salt or iteration count yields a different key, which the mac catches
+    let key = SymmetricKey::from_password(password, &salt, iterations);
+    key.decrypt(&ciphertext)
+}
+
 fn xor_block(a: &twofish::Block, b: &twofish::Block) -> twofish::Block {
     let mut output = [0u8; twofish::BLOCK_BYTES];
     for i in 0..twofish::BLOCK_BYTES {
@@ -122,7 +181,7 @@ fn pad(data: &[u8]) -> Vec<u8> {
 
     let mut output = Vec::with_capacity(data.len() + to_add);
     output.extend_from_slice(data);
-    output.extend(std::iter::repeat(to_add as u8).take(to_add));
+    output.extend(std::iter::repeat_n(to_add as u8, to_add));
 
     output
 }
@@ -168,10 +227,10 @@ impl CompleteCiphertext {
     }
 
     pub fn deserialize(mut data: &[u8]) -> Option<Self> {
-        let mac: sha256::Digest = data[0..sha256::DIGEST_BYTES].try_into().ok()?;
+        let mac: sha256::Digest = data.get(0..sha256::DIGEST_BYTES)?.try_into().ok()?;
         data = &data[sha256::DIGEST_BYTES..];
 
-        let iv: twofish::Block = data[0..twofish::BLOCK_BYTES].try_into().ok()?;
+        let iv: twofish::Block = data.get(0..twofish::BLOCK_BYTES)?.try_into().ok()?;
         data = &data[twofish::BLOCK_BYTES..];
 
         let ciphertext = data.to_owned();
@@ -184,6 +243,48 @@ impl CompleteCiphertext {
     }
 }
 
+struct PasswordCiphertext {
+    salt: [u8; PASSWORD_SALT_BYTES],
+    iterations: u32,
+    ciphertext: Vec<u8>,
+}
+
+impl PasswordCiphertext {
+    pub fn serialize(self) -> Vec<u8> {
+        let Self {
+            salt,
+            iterations,
+            ciphertext,
+        } = self;
+        let mut output = Vec::with_capacity(salt.len() + 4 + ciphertext.len());
+
+        output.extend_from_slice(&salt);
+        output.extend_from_slice(&iterations.to_be_bytes());
+        output.extend_from_slice(&ciphertext);
+
+        output
+    }
+
+    pub fn deserialize(mut data: &[u8]) -> Option<Self> {
+        let salt: [u8; PASSWORD_SALT_BYTES] = data.get(0..PASSWORD_SALT_BYTES)?.try_into().ok()?;
+        data = &data[PASSWORD_SALT_BYTES..];
+
+        let iterations = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?);
+        if !(1..=MAX_PASSWORD_ITERATIONS).contains(&iterations) {
+            return None;
+        }
+        data = &data[4..];
+
+        let ciphertext = data.to_owned();
+
+        Some(Self {
+            salt,
+            iterations,
+            ciphertext,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,4 +313,40 @@ mod tests {
         let decrypted = skey.decrypt(&encrypted);
         assert!(decrypted.is_none());
     }
+
+    #[test]
+    fn password_encryption_decryption() {
+        let data = b"Hello, World!";
+
+        let encrypted = encrypt_with_password(b"hunter2", 1000, data);
+        let decrypted = decrypt_with_password(b"hunter2", &encrypted).unwrap();
+        assert_eq!(data, &*decrypted);
+
+        assert!(decrypt_with_password(b"hunter3", &encrypted).is_none());
+    }
+
+    #[test]
+    fn password_iterations_tamper() {
+        let data = b"Hello, World!";
+
+        let encrypted = encrypt_with_password(b"hunter2", 1000, data);
+        let mut ciphertext = PasswordCiphertext::deserialize(&encrypted).unwrap();
+        ciphertext.iterations = 1;
+
+        let decrypted = decrypt_with_password(b"hunter2", &ciphertext.serialize());
+        assert!(decrypted.is_none());
+    }
+
+    #[test]
+    fn password_iterations_limit() {
+        let encrypted = encrypt_with_password(b"hunter2", 1000, b"Hello, World!");
+        for iterations in [0, MAX_PASSWORD_ITERATIONS + 1, u32::MAX] {
+            let mut ciphertext = PasswordCiphertext::deserialize(&encrypted).unwrap();
+            ciphertext.iterations = iterations;
+
+            let bytes = ciphertext.serialize();
+            assert!(PasswordCiphertext::deserialize(&bytes).is_none());
+            assert!(decrypt_with_password(b"hunter2", &bytes).is_none());
+        }
+    }
 }
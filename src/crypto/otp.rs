@@ -0,0 +1,358 @@
+use std::fmt;
+
+use super::hash::HashFunction;
+use super::hmac::{self, hmac};
+use super::sha1::Sha1;
+use super::sha256::Sha256;
+use super::sha512::Sha512;
+
+// HOTP (RFC 4226) and TOTP (RFC 6238), over HMAC with SHA-1, SHA-256 or SHA-512
+
+pub const DEFAULT_DIGITS: u32 = 6;
+pub const DEFAULT_PERIOD: u64 = 30;
+const MAX_DIGITS: u32 = 9;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Algorithm {
+    // what the otpauth format and most authenticator apps default to
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Some(Self::Sha1),
+            "SHA256" => Some(Self::Sha256),
+            "SHA512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sha1 => write!(f, "SHA1"),
+            Self::Sha256 => write!(f, "SHA256"),
+            Self::Sha512 => write!(f, "SHA512"),
+        }
+    }
+}
+
+pub fn hotp(algorithm: Algorithm, secret: &[u8], counter: u64, digits: u32) -> u32 {
+    match algorithm {
+        Algorithm::Sha1 => hotp_with::<Sha1>(secret, counter, digits),
+        Algorithm::Sha256 => hotp_with::<Sha256>(secret, counter, digits),
+        Algorithm::Sha512 => hotp_with::<Sha512>(secret, counter, digits),
+    }
+}
+
+fn hotp_with<H: HashFunction>(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    assert!(0 < digits && digits <= MAX_DIGITS);
+
+    let mac = hmac(&hmac::Key::<H>::from_bytes(secret), &counter.to_be_bytes());
+    let mac = mac.as_ref();
+
+    // dynamic truncation
+    let offset = (mac[mac.len() - 1] & 0xf) as usize;
+    let bytes: [u8; 4] = mac[offset..offset + 4].try_into().unwrap();
+    let binary = u32::from_be_bytes(bytes) & 0x7fffffff;
+
+    binary % 10u32.pow(digits)
+}
+
+pub fn totp(algorithm: Algorithm, secret: &[u8], unix_time: u64, period: u64, digits: u32) -> u32 {
+    hotp(algorithm, secret, unix_time / period, digits)
+}
+
+// seconds until the code for `unix_time` is replaced by the next one
+pub fn totp_remaining(unix_time: u64, period: u64) -> u64 {
+    period - unix_time % period
+}
+
+pub fn format_code(code: u32, digits: u32) -> String {
+    format!("{code:0width$}", width = digits as usize)
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// RFC 4648 base32, as used for OTP secrets. case, whitespace and padding are ignored.
+pub fn decode_base32(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.bytes() {
+        if c.is_ascii_whitespace() || c == b'=' {
+            continue;
+        }
+
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(output)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OtpKind {
+    Hotp { counter: u64 },
+    Totp { period: u64 },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OtpAuth {
+    pub kind: OtpKind,
+    pub algorithm: Algorithm,
+    pub label: String,
+    pub issuer: Option<String>,
+    pub secret: Vec<u8>,
+    pub digits: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OtpAuthError {
+    NotOtpAuth,
+    UnknownType(String),
+    MissingSecret,
+    InvalidSecret,
+    MissingCounter,
+    InvalidParameter(&'static str),
+    UnsupportedAlgorithm(String),
+}
+
+impl fmt::Display for OtpAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotOtpAuth => write!(f, "not an otpauth:// uri"),
+            Self::UnknownType(kind) => write!(f, "unknown otp type \"{kind}\""),
+            Self::MissingSecret => write!(f, "missing secret"),
+            Self::InvalidSecret => write!(f, "secret is not valid base32"),
+            Self::MissingCounter => write!(f, "hotp uri is missing its counter"),
+            Self::InvalidParameter(name) => write!(f, "invalid value for \"{name}\""),
+            Self::UnsupportedAlgorithm(algorithm) => {
+                write!(f, "unsupported algorithm \"{algorithm}\"")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OtpAuthError {}
+
+impl OtpAuth {
+    // parses the Key Uri Format used by authenticator apps, e.g.
+    // otpauth://totp/Issuer:account?secret=JBSWY3DPEHPK3PXP&issuer=Issuer&algorithm=SHA256
+    pub fn parse(uri: &str) -> Result<Self, OtpAuthError> {
+        let rest = uri
+            .trim()
+            .strip_prefix("otpauth://")
+            .ok_or(OtpAuthError::NotOtpAuth)?;
+        let (kind, rest) = rest.split_once('/').ok_or(OtpAuthError::NotOtpAuth)?;
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let label = percent_decode(label).ok_or(OtpAuthError::InvalidParameter("label"))?;
+
+        let mut secret = None;
+        let mut issuer = None;
+        let mut algorithm = None;
+        let mut digits = DEFAULT_DIGITS;
+        let mut counter = None;
+        let mut period = DEFAULT_PERIOD;
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value).ok_or(OtpAuthError::InvalidParameter("query"))?;
+
+            match name {
+                "secret" => {
+                    secret = Some(decode_base32(&value).ok_or(OtpAuthError::InvalidSecret)?)
+                }
+                "issuer" => issuer = Some(value),
+                "algorithm" => algorithm = Some(value),
+                "digits" => {
+                    digits = value
+                        .parse()
+                        .ok()
+                        .filter(|digits| (1..=MAX_DIGITS).contains(digits))
+                        .ok_or(OtpAuthError::InvalidParameter("digits"))?
+                }
+                "counter" => {
+                    counter = Some(
+                        value
+                            .parse()
+                            .map_err(|_| OtpAuthError::InvalidParameter("counter"))?,
+                    )
+                }
+                "period" => {
+                    period = value
+                        .parse()
+                        .ok()
+                        .filter(|&period| period > 0)
+                        .ok_or(OtpAuthError::InvalidParameter("period"))?
+                }
+                // unknown parameters (e.g. "image") are ignored, like authenticator apps do
+                _ => {}
+            }
+        }
+
+        let algorithm = match algorithm {
+            Some(name) => {
+                Algorithm::parse(&name).ok_or(OtpAuthError::UnsupportedAlgorithm(name))?
+            }
+            None => Algorithm::default(),
+        };
+
+        let kind = match kind {
+            "hotp" => OtpKind::Hotp {
+                counter: counter.ok_or(OtpAuthError::MissingCounter)?,
+            },
+            "totp" => OtpKind::Totp { period },
+            other => return Err(OtpAuthError::UnknownType(other.to_owned())),
+        };
+
+        let secret = secret.ok_or(OtpAuthError::MissingSecret)?;
+        if secret.is_empty() {
+            return Err(OtpAuthError::MissingSecret);
+        }
+
+        Ok(Self {
+            kind,
+            algorithm,
+            label,
+            issuer,
+            secret,
+            digits,
+        })
+    }
+
+    // the code for the current counter or time step
+    pub fn code(&self, unix_time: u64) -> u32 {
+        match self.kind {
+            OtpKind::Hotp { counter } => hotp(self.algorithm, &self.secret, counter, self.digits),
+            OtpKind::Totp { period } => {
+                totp(self.algorithm, &self.secret, unix_time, period, self.digits)
+            }
+        }
+    }
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut bytes = input.bytes();
+
+    while let Some(b) = bytes.next() {
+        match b {
+            b'%' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                output.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            b => output.push(b),
+        }
+    }
+
+    String::from_utf8(output).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4226, appendix D
+    #[test]
+    fn rfc4226() {
+        let secret = b"12345678901234567890";
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+
+        for (counter, expected) in expected.into_iter().enumerate() {
+            assert_eq!(hotp(Algorithm::Sha1, secret, counter as u64, 6), expected);
+        }
+    }
+
+    // RFC 6238, appendix B. the seed is repeated to the length of each hash's output.
+    #[test]
+    fn rfc6238() {
+        let vectors = [
+            (59, 94287082, 46119246, 90693936),
+            (1111111109, 7081804, 68084774, 25091201),
+            (1111111111, 14050471, 67062674, 99943326),
+            (1234567890, 89005924, 91819424, 93441116),
+            (2000000000, 69279037, 90698825, 38618901),
+            (20000000000, 65353130, 77737706, 47863826),
+        ];
+        let sha1_secret = b"12345678901234567890";
+        let sha256_secret = b"12345678901234567890123456789012";
+        let sha512_secret = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+        for (time, sha1, sha256, sha512) in vectors {
+            assert_eq!(totp(Algorithm::Sha1, sha1_secret, time, 30, 8), sha1);
+            assert_eq!(totp(Algorithm::Sha256, sha256_secret, time, 30, 8), sha256);
+            assert_eq!(totp(Algorithm::Sha512, sha512_secret, time, 30, 8), sha512);
+        }
+    }
+
+    #[test]
+    fn base32() {
+        assert_eq!(
+            decode_base32("JBSWY3DPEHPK3PXP").unwrap(),
+            b"Hello!\xde\xad\xbe\xef"
+        );
+        assert_eq!(
+            decode_base32("jbsw y3dp ehpk 3pxp").unwrap(),
+            b"Hello!\xde\xad\xbe\xef"
+        );
+        assert_eq!(decode_base32("MZXW6===").unwrap(), b"foo");
+        assert!(decode_base32("not base32!").is_none());
+    }
+
+    #[test]
+    fn parse_uri() {
+        let auth = OtpAuth::parse(
+            "otpauth://totp/ACME%20Co:john@example.com?secret=JBSWY3DPEHPK3PXP&issuer=ACME%20Co\
+             &algorithm=SHA256&digits=8&period=60",
+        )
+        .unwrap();
+
+        assert_eq!(auth.kind, OtpKind::Totp { period: 60 });
+        assert_eq!(auth.algorithm, Algorithm::Sha256);
+        assert_eq!(auth.label, "ACME Co:john@example.com");
+        assert_eq!(auth.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(auth.secret, b"Hello!\xde\xad\xbe\xef");
+        assert_eq!(auth.digits, 8);
+    }
+
+    #[test]
+    fn parse_uri_errors() {
+        assert_eq!(
+            OtpAuth::parse("otpauth://totp/x?secret=JBSWY3DPEHPK3PXP")
+                .unwrap()
+                .algorithm,
+            Algorithm::Sha1
+        );
+        assert_eq!(
+            OtpAuth::parse("otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&algorithm=MD5"),
+            Err(OtpAuthError::UnsupportedAlgorithm("MD5".to_owned()))
+        );
+        assert_eq!(
+            OtpAuth::parse("otpauth://hotp/x?secret=JBSWY3DPEHPK3PXP"),
+            Err(OtpAuthError::MissingCounter)
+        );
+        assert_eq!(
+            OtpAuth::parse("https://example.com"),
+            Err(OtpAuthError::NotOtpAuth)
+        );
+    }
+}
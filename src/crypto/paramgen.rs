@@ -0,0 +1,196 @@
+use std::ops::ControlFlow;
+
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::{Random, Uint, U2048, U3072, U4096, U6144, U8192};
+
+use super::drbg::ThreadDrbg;
+use super::group::{Group, GroupParams};
+use super::primality::{is_probable_prime, rem_small, small_primes};
+
+// generation of new safe-prime groups, for those who'd rather not use the built-in ones
+
+// what the generator is currently doing, reported through a callback that can also cancel it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Progress {
+    // looking for a safe prime. `sieved` candidates have been ruled out by small factors, and
+    // `tested` had to go through a primality test.
+    Searching { sieved: u64, tested: u64 },
+    // a candidate passed the quick tests and is being checked in full
+    Verifying,
+    SelectingGenerator,
+    Done,
+}
+
+// the sizes (in bits) that generate_params accepts
+pub const MIN_BITS: usize = 64;
+pub const MAX_BITS: usize = 8192;
+
+// finds a random safe prime p = 2q + 1 of exactly `bits` bits. returns None if cancelled.
+pub fn generate_safe_prime<const LIMBS: usize>(
+    bits: usize,
+    progress: &mut impl FnMut(Progress) -> ControlFlow<()>,
+) -> Option<Uint<LIMBS>> {
+    assert!((MIN_BITS..=Uint::<LIMBS>::BITS).contains(&bits));
+
+    let primes = small_primes();
+    let mut sieved = 0;
+    let mut tested = 0;
+
+    loop {
+        // a random odd q of bits - 1 bits
+        let start = Uint::<LIMBS>::random(&mut ThreadDrbg)
+            .shr_vartime(Uint::<LIMBS>::BITS - (bits - 1))
+            .bitor(&Uint::ONE.shl_vartime(bits - 2))
+            .bitor(&Uint::ONE);
+
+        // q + delta and 2(q + delta) + 1 are divisible by a small prime exactly when these
+        // residues are 0, so stepping through candidates only needs machine arithmetic
+        let mut q_residues: Vec<u32> = primes.iter().map(|&r| rem_small(&start, r)).collect();
+
+        let mut delta = 0;
+        // stop before the top bits get disturbed; a new start is cheaper than the carry checks
+        while delta < 1 << 20 {
+            let has_small_factor = q_residues.iter().zip(primes).any(|(&q, &r)| {
+                let p = (2 * q + 1) % r;
+                q == 0 || p == 0
+            });
+
+            if has_small_factor {
+                sieved += 1;
+            } else {
+                tested += 1;
+                if progress(Progress::Searching { sieved, tested }).is_break() {
+                    return None;
+                }
+
+                let q = start.wrapping_add(&Uint::from_u64(delta));
+                let p = q.shl_vartime(1).wrapping_add(&Uint::ONE);
+                if p.bits_vartime() != bits {
+                    break;
+                }
+
+                // a single Fermat test of p with base 2 discards almost every remaining
+                // candidate, before the more expensive full tests run
+                if fermat_base_2(&p) {
+                    if progress(Progress::Verifying).is_break() {
+                        return None;
+                    }
+                    if is_probable_prime(&q) && is_probable_prime(&p) {
+                        return Some(p);
+                    }
+                }
+            }
+
+            delta += 2;
+            for (q, &r) in q_residues.iter_mut().zip(primes) {
+                *q = (*q + 2) % r;
+            }
+        }
+    }
+}
+
+fn fermat_base_2<const LIMBS: usize>(p: &Uint<LIMBS>) -> bool {
+    let params = DynResidueParams::new(p);
+    let exponent = p.wrapping_sub(&Uint::ONE);
+    let two = DynResidue::new(&Uint::from_u8(2), params);
+    two.pow_bounded_exp(&exponent, exponent.bits_vartime()) == DynResidue::one(params)
+}
+
+// the smallest g > 1 that generates the order-q subgroup of a safe prime p. since q is prime,
+// every element other than 1 and p - 1 is either in the subgroup or has order 2q, and the
+// quadratic residues are the ones in it. for p > 7, one of 2 and 3 always is.
+pub fn select_generator<const LIMBS: usize>(p: &Uint<LIMBS>) -> Uint<LIMBS> {
+    let params = DynResidueParams::new(p);
+    let q = p.shr_vartime(1);
+    let one = DynResidue::one(params);
+
+    (2u8..)
+        .map(Uint::from_u8)
+        .find(|g| DynResidue::new(g, params).pow_bounded_exp(&q, q.bits_vartime()) == one)
+        .unwrap()
+}
+
+// a new group with a `bits`-bit safe prime, in the form Group::from_params loads
+pub fn generate_params<const LIMBS: usize>(
+    bits: usize,
+    mut progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Option<GroupParams> {
+    let p = generate_safe_prime::<LIMBS>(bits, &mut progress)?;
+    if progress(Progress::SelectingGenerator).is_break() {
+        return None;
+    }
+    let g = select_generator(&p);
+    let _ = progress(Progress::Done);
+
+    Some(Group::new("generated", p, g).to_params())
+}
+
+// generate_params with the smallest integer width that fits
+pub fn generate(
+    bits: usize,
+    progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Option<GroupParams> {
+    assert!((MIN_BITS..=MAX_BITS).contains(&bits));
+
+    match bits {
+        ..=2048 => generate_params::<{ U2048::LIMBS }>(bits, progress),
+        2049..=3072 => generate_params::<{ U3072::LIMBS }>(bits, progress),
+        3073..=4096 => generate_params::<{ U4096::LIMBS }>(bits, progress),
+        4097..=6144 => generate_params::<{ U6144::LIMBS }>(bits, progress),
+        _ => generate_params::<{ U8192::LIMBS }>(bits, progress),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto_bigint::{U256, U64};
+
+    use super::*;
+    use crate::crypto::group::{uint_from_be_bytes, InvalidParams};
+
+    #[test]
+    fn generates_safe_primes() {
+        let mut steps = Vec::new();
+        let params = generate_params::<{ U256::LIMBS }>(160, |step| {
+            steps.push(step);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        // too small for from_params, which only loads groups fit for use
+        assert_eq!(
+            Group::<{ U256::LIMBS }>::from_params(&params).err(),
+            Some(InvalidParams::TooSmall)
+        );
+        let p = uint_from_be_bytes(&params.p);
+        let group = Group::<{ U256::LIMBS }>::new("test", p, uint_from_be_bytes(&params.g));
+        assert_eq!(group.self_check(), Ok(()));
+        assert_eq!(group.bits(), 160);
+        assert!(is_probable_prime(group.q()));
+        assert!(matches!(steps[0], Progress::Searching { .. }));
+        assert_eq!(steps.last(), Some(&Progress::Done));
+    }
+
+    #[test]
+    fn cancellation() {
+        let mut calls = 0;
+        let params = generate_params::<{ U256::LIMBS }>(256, |_| {
+            calls += 1;
+            if calls < 3 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+
+        assert_eq!(params, None);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn generators() {
+        // 23 = 7 mod 8, so 2 is a quadratic residue. 11 = 3 mod 8 isn't, but 3 is.
+        assert_eq!(select_generator(&U64::from_u8(23)), U64::from_u8(2));
+        assert_eq!(select_generator(&U64::from_u8(11)), U64::from_u8(3));
+    }
+}
@@ -0,0 +1,64 @@
+use super::hmac::{self, hmac};
+use super::sha256::{Digest, Sha256, DIGEST_BYTES};
+
+// PBKDF2 (RFC 8018, section 5.2) with HMAC-SHA256 as the PRF, filling the entire output slice
+pub fn pbkdf2(password: &[u8], salt: &[u8], iterations: u32, output: &mut [u8]) {
+    assert!(iterations > 0);
+
+    let key = hmac::Key::<Sha256>::from_bytes(password);
+
+    let mut first_input = Vec::with_capacity(salt.len() + 4);
+    for (i, chunk) in output.chunks_mut(DIGEST_BYTES).enumerate() {
+        let block_index = i as u32 + 1;
+
+        first_input.clear();
+        first_input.extend_from_slice(salt);
+        first_input.extend_from_slice(&block_index.to_be_bytes());
+
+        let mut u = hmac(&key, &first_input);
+        let mut t: Digest = u;
+        for _ in 1..iterations {
+            u = hmac(&key, &u);
+            for (t, u) in t.iter_mut().zip(u) {
+                *t ^= u;
+            }
+        }
+
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // RFC 7914, section 11
+    #[test]
+    fn rfc7914_single_iteration() {
+        let mut output = [0u8; 64];
+        pbkdf2(b"passwd", b"salt", 1, &mut output);
+        assert_eq!(
+            output,
+            hex!(
+                "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+                "49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+            )
+        );
+    }
+
+    // RFC 7914, section 11
+    #[test]
+    fn rfc7914_many_iterations() {
+        let mut output = [0u8; 64];
+        pbkdf2(b"Password", b"NaCl", 80000, &mut output);
+        assert_eq!(
+            output,
+            hex!(
+                "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56"
+                "a1d425a1225833549adb841b51c9b3176a272bdebba1d078478f62b397f33c8d"
+            )
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::sync::OnceLock;
+
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::{Limb, NonZero, RandomMod, Uint};
+
+use super::drbg::ThreadDrbg;
+
+// probabilistic primality tests, for public numbers such as group parameters. none of this is
+// constant-time.
+
+// the odd primes below this are used for trial division and sieving
+const SMALL_PRIME_LIMIT: u32 = 1 << 14;
+
+static SMALL_PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+
+// the odd primes below SMALL_PRIME_LIMIT, found with the sieve of Eratosthenes
+pub(crate) fn small_primes() -> &'static [u32] {
+    SMALL_PRIMES.get_or_init(|| {
+        let limit = SMALL_PRIME_LIMIT as usize;
+        let mut composite = vec![false; limit];
+        let mut primes = Vec::new();
+        for i in (3..limit).step_by(2) {
+            if !composite[i] {
+                primes.push(i as u32);
+                for multiple in (i * i..limit).step_by(2 * i) {
+                    composite[multiple] = true;
+                }
+            }
+        }
+
+        primes
+    })
+}
+
+// n mod m, for a small m
+pub(crate) fn rem_small<const LIMBS: usize>(n: &Uint<LIMBS>, m: u32) -> u32 {
+    let m = NonZero::new(Limb::from_u32(m)).unwrap();
+    n.div_rem_limb(m).1 .0 as u32
+}
+
+fn is_odd<const LIMBS: usize>(n: &Uint<LIMBS>) -> bool {
+    n.as_words()[0] & 1 == 1
+}
+
+// n mod 8, which decides a few Jacobi symbols
+fn mod_8<const LIMBS: usize>(n: &Uint<LIMBS>) -> u32 {
+    (n.as_words()[0] & 7) as u32
+}
+
+// decides small n outright, and finds small factors of large ones. None if n is too large to be
+// decided and has no small factors.
+fn trial_division<const LIMBS: usize>(n: &Uint<LIMBS>) -> Option<bool> {
+    if n < &Uint::from_u8(2) {
+        return Some(false);
+    }
+    if !is_odd(n) {
+        return Some(n == &Uint::from_u8(2));
+    }
+
+    for &prime in small_primes() {
+        if n == &Uint::from_u32(prime) {
+            return Some(true);
+        }
+        if rem_small(n, prime) == 0 {
+            return Some(false);
+        }
+    }
+
+    // any composite below the square of the limit has a factor below the limit
+    let limit = SMALL_PRIME_LIMIT as u64;
+    if n < &Uint::from_u64(limit * limit) {
+        return Some(true);
+    }
+
+    None
+}
+
+// a single round of the Miller-Rabin test on an odd n > 3 with the given base
+fn miller_rabin_round<const LIMBS: usize>(n: &Uint<LIMBS>, base: &Uint<LIMBS>) -> bool {
+    let params = DynResidueParams::new(n);
+    let one = DynResidue::one(params);
+    let minus_one = one.neg();
+
+    // n - 1 = d * 2^s with d odd
+    let n_minus_one = n.wrapping_sub(&Uint::ONE);
+    let s = n_minus_one.trailing_zeros_vartime();
+    let d = n_minus_one.shr_vartime(s);
+
+    let mut x = DynResidue::new(base, params).pow_bounded_exp(&d, d.bits_vartime());
+    if x == one || x == minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = x.square();
+        if x == minus_one {
+            return true;
+        }
+    }
+
+    false
+}
+
+// the Miller-Rabin test with uniformly random bases. a composite n passes a round with
+// probability at most 1/4.
+pub fn miller_rabin<const LIMBS: usize>(n: &Uint<LIMBS>, rounds: usize) -> bool {
+    if let Some(result) = trial_division(n) {
+        return result;
+    }
+
+    // bases are drawn from [2, n - 2]
+    let range = NonZero::new(n.wrapping_sub(&Uint::from_u8(3))).unwrap();
+    (0..rounds).all(|_| {
+        let base = Uint::random_mod(&mut ThreadDrbg, &range).wrapping_add(&Uint::from_u8(2));
+        miller_rabin_round(n, &base)
+    })
+}
+
+fn jacobi_small(mut a: u64, mut n: u64) -> i32 {
+    let mut result = 1;
+    a %= n;
+    while a != 0 {
+        while a.is_multiple_of(2) {
+            a /= 2;
+            if n % 8 == 3 || n % 8 == 5 {
+                result = -result;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        a %= n;
+    }
+
+    if n == 1 {
+        result
+    } else {
+        0
+    }
+}
+
+// the Jacobi symbol (a/n) for a small a and an odd n, using quadratic reciprocity to swap the
+// arguments so that the rest can be done with machine integers
+fn jacobi<const LIMBS: usize>(a: i64, n: &Uint<LIMBS>) -> i32 {
+    let mut result = 1;
+    if a < 0 && mod_8(n) % 4 == 3 {
+        result = -result;
+    }
+
+    let mut a = a.unsigned_abs();
+    if a == 0 {
+        return if n == &Uint::ONE { 1 } else { 0 };
+    }
+    while a.is_multiple_of(2) {
+        a /= 2;
+        if mod_8(n) == 3 || mod_8(n) == 5 {
+            result = -result;
+        }
+    }
+    if a == 1 {
+        return result;
+    }
+
+    if a % 4 == 3 && mod_8(n) % 4 == 3 {
+        result = -result;
+    }
+    result * jacobi_small(rem_small(n, a as u32) as u64, a)
+}
+
+fn is_square<const LIMBS: usize>(n: &Uint<LIMBS>) -> bool {
+    let root = n.sqrt_vartime();
+    &root.wrapping_mul(&root) == n
+}
+
+// the strong Lucas probable prime test with Selfridge's parameters (method A), for an odd n that
+// isn't a perfect square
+fn strong_lucas<const LIMBS: usize>(n: &Uint<LIMBS>) -> bool {
+    // the first D in 5, -7, 9, -11, ... with (D/n) = -1
+    let mut d: i64 = 5;
+    loop {
+        match jacobi(d, n) {
+            -1 => break,
+            // a small factor of n, unless it's n itself
+            0 if n != &Uint::from_u64(d.unsigned_abs()) => return false,
+            _ => d = if d > 0 { -(d + 2) } else { -d + 2 },
+        }
+    }
+
+    let params = DynResidueParams::new(n);
+    let small = |x: i64| {
+        let residue = DynResidue::new(&Uint::from_u64(x.unsigned_abs()), params);
+        if x < 0 {
+            residue.neg()
+        } else {
+            residue
+        }
+    };
+    let zero = DynResidue::zero(params);
+
+    // P = 1 and Q = (1 - D) / 4
+    let big_d = small(d);
+    let q = small((1 - d) / 4);
+
+    // n + 1 = k * 2^s with k odd
+    let n_plus_one = n.wrapping_add(&Uint::ONE);
+    let s = n_plus_one.trailing_zeros_vartime();
+    let k = n_plus_one.shr_vartime(s);
+
+    // computes U_k, V_k and Q^k from the most significant bit of k down
+    let mut u = DynResidue::one(params);
+    let mut v = DynResidue::one(params);
+    let mut q_k = q;
+    for i in (0..k.bits_vartime() - 1).rev() {
+        u = u.mul(&v);
+        v = v.square().sub(&q_k.add(&q_k));
+        q_k = q_k.square();
+
+        if k.bit_vartime(i) {
+            let next_u = u.add(&v).div_by_2();
+            v = big_d.mul(&u).add(&v).div_by_2();
+            u = next_u;
+            q_k = q_k.mul(&q);
+        }
+    }
+
+    if u == zero || v == zero {
+        return true;
+    }
+    for _ in 1..s {
+        v = v.square().sub(&q_k.add(&q_k));
+        q_k = q_k.square();
+        if v == zero {
+            return true;
+        }
+    }
+
+    false
+}
+
+// the Baillie-PSW test: a Miller-Rabin round with base 2 followed by a strong Lucas test. no
+// composite is known to pass it.
+pub fn is_probable_prime<const LIMBS: usize>(n: &Uint<LIMBS>) -> bool {
+    if let Some(result) = trial_division(n) {
+        return result;
+    }
+
+    miller_rabin_round(n, &Uint::from_u8(2)) && !is_square(n) && strong_lucas(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto_bigint::{U128, U64};
+
+    use super::*;
+
+    #[test]
+    fn small_numbers() {
+        let primes: Vec<u64> = (0..2000)
+            .filter(|&n| n >= 2 && (2..n).take_while(|d| d * d <= n).all(|d| n % d != 0))
+            .collect();
+
+        for n in 0..2000 {
+            let expected = primes.contains(&n);
+            assert_eq!(is_probable_prime(&U64::from_u64(n)), expected, "{n}");
+            assert_eq!(miller_rabin(&U64::from_u64(n), 8), expected, "{n}");
+        }
+    }
+
+    #[test]
+    fn pseudoprimes() {
+        // strong pseudoprimes to base 2, which the Lucas test has to catch. the last one is one to
+        // every prime base up to 23.
+        for n in [
+            2047u64,
+            3277,
+            4033,
+            4681,
+            8321,
+            3215031751,
+            2152302898747,
+            3825123056546413051,
+        ] {
+            let n = U64::from_u64(n);
+            assert!(miller_rabin_round(&n, &U64::from_u8(2)), "{n}");
+            assert!(!strong_lucas(&n), "{n}");
+            assert!(!is_probable_prime(&n));
+        }
+
+        // strong Lucas pseudoprimes, which the Miller-Rabin round has to catch
+        for n in [
+            5459u64, 5777, 10877, 16109, 18971, 22499, 24569, 25199, 40309,
+        ] {
+            let n = U64::from_u64(n);
+            assert!(strong_lucas(&n), "{n}");
+            assert!(!miller_rabin_round(&n, &U64::from_u8(2)), "{n}");
+            assert!(!is_probable_prime(&n));
+        }
+
+        // Carmichael numbers
+        for n in [561u64, 41041, 825265, 321197185] {
+            assert!(!is_probable_prime(&U64::from_u64(n)));
+        }
+    }
+
+    #[test]
+    fn large_numbers() {
+        // 2^127 - 1 is a Mersenne prime, 2^127 + 1 is divisible by 3
+        let m127 = U128::MAX.shr_vartime(1);
+        assert!(is_probable_prime(&m127));
+        assert!(miller_rabin(&m127, 16));
+        assert!(!is_probable_prime(&m127.wrapping_add(&U128::from_u8(2))));
+
+        // (2^61 - 1) * (2^64 - 59), a product of two primes without small factors
+        let product = U128::from_u64((1 << 61) - 1).wrapping_mul(&U128::from_u64(u64::MAX - 58));
+        assert!(!is_probable_prime(&product));
+        assert!(!miller_rabin(&product, 16));
+    }
+}
@@ -0,0 +1,152 @@
+use crypto_bigint::Uint;
+
+use super::group::{uint_from_be_bytes, uint_to_be_bytes};
+use super::hash::{ByteArray, HashFunction};
+use super::hmac::{hmac, Key};
+
+// deterministic nonces for discrete logarithm signatures (RFC 6979, section 3.2). k only depends
+// on the private key and the message hash, so a broken random number generator can't leak the key
+// through a repeated or biased nonce.
+
+// the leftmost qlen bits of the input as an integer
+pub(crate) fn bits2int<const LIMBS: usize>(bytes: &[u8], qlen: usize) -> Uint<LIMBS> {
+    let bytes = &bytes[..bytes.len().min(qlen.div_ceil(8))];
+    let int: Uint<LIMBS> = uint_from_be_bytes(bytes);
+    int.shr_vartime((bytes.len() * 8).saturating_sub(qlen))
+}
+
+// big-endian and as long as q
+fn int2octets<const LIMBS: usize>(int: &Uint<LIMBS>, qlen: usize) -> Vec<u8> {
+    let mut bytes = uint_to_be_bytes(int);
+    bytes.split_off(bytes.len() - qlen.div_ceil(8))
+}
+
+fn mac<H: HashFunction>(key: &H::Digest, data: &[&[u8]]) -> H::Digest {
+    hmac(&Key::<H>::from_bytes(key.as_ref()), &data.concat())
+}
+
+// the nonce for signing a message with hash `hash` under the private key x, with 0 < x < q
+pub(crate) fn generate_k<H: HashFunction, const LIMBS: usize>(
+    q: &Uint<LIMBS>,
+    x: &Uint<LIMBS>,
+    hash: &[u8],
+) -> Uint<LIMBS> {
+    generate_k_until::<H, _, _>(q, x, hash, |k| Some(*k))
+}
+
+// the same, for signatures that can come out unusable with some k: `sign` returns None for those,
+// and is then tried with the next candidate (section 3.4)
+pub(crate) fn generate_k_until<H: HashFunction, const LIMBS: usize, T>(
+    q: &Uint<LIMBS>,
+    x: &Uint<LIMBS>,
+    hash: &[u8],
+    mut sign: impl FnMut(&Uint<LIMBS>) -> Option<T>,
+) -> T {
+    let qlen = q.bits_vartime();
+
+    // bits2octets: the hash is reduced modulo q, which takes at most one subtraction
+    let h: Uint<LIMBS> = bits2int(hash, qlen);
+    let h = if &h >= q { h.wrapping_sub(q) } else { h };
+    let seed = [int2octets(x, qlen), int2octets(&h, qlen)].concat();
+
+    let mut key = H::Digest::ZERO;
+    let mut v = H::Digest::ZERO;
+    v.as_mut().fill(0x01);
+    for separator in [0x00, 0x01] {
+        key = mac::<H>(&key, &[v.as_ref(), &[separator], &seed]);
+        v = mac::<H>(&key, &[v.as_ref()]);
+    }
+
+    loop {
+        let mut t = Vec::new();
+        while t.len() * 8 < qlen {
+            v = mac::<H>(&key, &[v.as_ref()]);
+            t.extend_from_slice(v.as_ref());
+        }
+
+        let k = bits2int(&t, qlen);
+        if k != Uint::ZERO && &k < q {
+            if let Some(signature) = sign(&k) {
+                return signature;
+            }
+        }
+
+        key = mac::<H>(&key, &[v.as_ref(), &[0x00]]);
+        v = mac::<H>(&key, &[v.as_ref()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto_bigint::U256;
+    use hex_literal::hex;
+
+    use super::*;
+    use crate::crypto::sha256::{hash, Sha256};
+
+    fn k(q: &[u8], x: &[u8], message: &[u8]) -> Vec<u8> {
+        let q: U256 = uint_from_be_bytes(q);
+        let x: U256 = uint_from_be_bytes(x);
+        int2octets(
+            &generate_k::<Sha256, _>(&q, &x, &hash(message)),
+            q.bits_vartime(),
+        )
+    }
+
+    // RFC 6979, appendix A.2.1: DSA with a 160-bit q
+    #[test]
+    fn dsa_1024() {
+        let q = hex!("996F967F6C8E388D9E28D01E205FBA957A5698B1");
+        let x = hex!("411602CB19A6CCC34494D79D98EF1E7ED5AF25F7");
+
+        assert_eq!(
+            k(&q, &x, b"sample"),
+            hex!("519BA0546D0C39202A7D34D7DFA5E760B318BCFB")
+        );
+        assert_eq!(
+            k(&q, &x, b"test"),
+            hex!("5A67592E8128E03A417B0484410FB72C0B630E1A")
+        );
+    }
+
+    // RFC 6979, appendix A.2.2: DSA with a 256-bit q
+    #[test]
+    fn dsa_2048() {
+        let q = hex!("F2C3119374CE76C9356990B465374A17F23F9ED35089BD969F61C6DDE9998C1F");
+        let x = hex!("69C7548C21D0DFEA6B9A51C9EAD4E27C33D3B3F180316E5BCAB92C933F0E4DBC");
+
+        assert_eq!(
+            k(&q, &x, b"sample"),
+            hex!("8926A27C40484216F052F4427CFD5647338B7B3939BC6573AF4333569D597C52")
+        );
+        assert_eq!(
+            k(&q, &x, b"test"),
+            hex!("1D6CE6DDA1C5D37307839CD03AB0A5CBB18E60D800937D67DFB4479AAC8DEAD7")
+        );
+    }
+
+    #[test]
+    fn retries() {
+        let q: U256 = uint_from_be_bytes(&hex!(
+            "F2C3119374CE76C9356990B465374A17F23F9ED35089BD969F61C6DDE9998C1F"
+        ));
+        let x: U256 = uint_from_be_bytes(&hex!(
+            "69C7548C21D0DFEA6B9A51C9EAD4E27C33D3B3F180316E5BCAB92C933F0E4DBC"
+        ));
+        let candidates = |skip: usize| {
+            let mut tried = Vec::new();
+            generate_k_until::<Sha256, _, _>(&q, &x, &hash(b"sample"), |k| {
+                tried.push(*k);
+                (tried.len() > skip).then_some(())
+            });
+            tried
+        };
+
+        // the first candidate is the usual k, and the ones after it are deterministic too
+        let tried = candidates(2);
+        assert_eq!(tried.len(), 3);
+        assert_eq!(tried[0], generate_k::<Sha256, _>(&q, &x, &hash(b"sample")));
+        assert!(tried[1] != tried[0] && tried[2] != tried[1] && tried.iter().all(|k| k < &q));
+        assert_eq!(candidates(2), tried);
+    }
+}
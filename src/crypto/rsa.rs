@@ -0,0 +1,803 @@
+use std::fmt;
+
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::{NonZero, Random, RandomMod, Uint, U1024, U2048, U3072, U4096};
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::drbg::ThreadDrbg;
+use super::group::{uint_from_be_bytes, uint_to_be_bytes};
+use super::primality::{is_probable_prime, rem_small};
+use super::sha256::{self, DIGEST_BYTES};
+
+// RSA (RFC 8017) with OAEP encryption and PSS signatures, both over SHA-256 with MGF1. keys are
+// read and written as PKCS#1 DER, which is what `openssl rsa -traditional -outform DER` gives
+// for a key made by `openssl genrsa` (and `-RSAPublicKey_out` for the public half).
+
+// the moduli that generate makes, in bits
+pub const SIZES: [usize; 3] = [2048, 3072, 4096];
+// the moduli that are accepted from elsewhere. 1024 bits is only there for old keys.
+pub const MIN_BITS: usize = 1024;
+pub const MAX_BITS: usize = 4096;
+
+const PUBLIC_EXPONENT: u32 = 65537;
+// PSS signatures are made with a salt as long as the hash, though any length is accepted
+const SALT_BYTES: usize = DIGEST_BYTES;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvalidKey {
+    Malformed,
+    UnsupportedSize,
+    BadExponent,
+    NotPrime,
+    Inconsistent,
+}
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "not a PKCS#1 key in DER"),
+            Self::UnsupportedSize => write!(f, "the modulus is even or not 1024 to 4096 bits long"),
+            Self::BadExponent => write!(f, "the public exponent is even or out of range"),
+            Self::NotPrime => write!(f, "p or q is not prime"),
+            Self::Inconsistent => write!(f, "the numbers of the private key don't fit together"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidKey {}
+
+fn bit_len(bytes: &[u8]) -> usize {
+    match bytes.first() {
+        Some(&first) => bytes.len() * 8 - first.leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+// big-endian without leading zeros, the form every number is kept in
+fn to_minimal_bytes<const LIMBS: usize>(x: &Uint<LIMBS>) -> Vec<u8> {
+    let bytes = uint_to_be_bytes(x);
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+    bytes[zeros..].to_vec()
+}
+
+// DynResidueParams::new wants an odd modulus, which every one here is
+fn pow_mod<const LIMBS: usize>(
+    base: &Uint<LIMBS>,
+    exp: &Uint<LIMBS>,
+    exp_bits: usize,
+    modulus: &Uint<LIMBS>,
+) -> Uint<LIMBS> {
+    let params = DynResidueParams::new(modulus);
+    DynResidue::new(base, params)
+        .pow_bounded_exp(exp, exp_bits)
+        .retrieve()
+}
+
+fn mul_mod<const LIMBS: usize>(
+    lhs: &Uint<LIMBS>,
+    rhs: &Uint<LIMBS>,
+    modulus: &Uint<LIMBS>,
+) -> Uint<LIMBS> {
+    Uint::const_rem_wide(lhs.mul_wide(rhs), modulus).0
+}
+
+// n and e as integers, with the width that fits n
+struct Modulus<const LIMBS: usize> {
+    n: Uint<LIMBS>,
+    e: Uint<LIMBS>,
+    bytes: usize,
+}
+
+impl<const LIMBS: usize> Modulus<LIMBS> {
+    fn public_op(&self, x: &Uint<LIMBS>) -> Uint<LIMBS> {
+        pow_mod(x, &self.e, self.e.bits_vartime(), &self.n)
+    }
+
+    // I2OSP, to as many bytes as n takes
+    fn encode(&self, x: &Uint<LIMBS>) -> Vec<u8> {
+        let mut bytes = uint_to_be_bytes(x);
+        bytes.split_off(bytes.len() - self.bytes)
+    }
+
+    // OS2IP, for ciphertexts and signatures, which have to be exactly as long as n and below it
+    fn decode(&self, bytes: &[u8]) -> Option<Uint<LIMBS>> {
+        if bytes.len() != self.bytes {
+            return None;
+        }
+        let x = uint_from_be_bytes(bytes);
+        (x < self.n).then_some(x)
+    }
+}
+
+// evaluates the body with `$modulus` bound to the Modulus of a public key
+macro_rules! with_modulus {
+    ($key:expr, |$modulus:ident| $body:expr) => {{
+        let key: &PublicKey = $key;
+        match key.bits() {
+            ..=1024 => {
+                let $modulus = &key.modulus::<{ U1024::LIMBS }>();
+                $body
+            }
+            ..=2048 => {
+                let $modulus = &key.modulus::<{ U2048::LIMBS }>();
+                $body
+            }
+            ..=3072 => {
+                let $modulus = &key.modulus::<{ U3072::LIMBS }>();
+                $body
+            }
+            _ => {
+                let $modulus = &key.modulus::<{ U4096::LIMBS }>();
+                $body
+            }
+        }
+    }};
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(Vec<u8>, Vec<u8>)")]
+pub struct PublicKey {
+    n: Vec<u8>,
+    e: Vec<u8>,
+}
+
+impl TryFrom<(Vec<u8>, Vec<u8>)> for PublicKey {
+    type Error = InvalidKey;
+
+    fn try_from((n, e): (Vec<u8>, Vec<u8>)) -> Result<Self, Self::Error> {
+        let strip = |x: Vec<u8>| x[x.iter().take_while(|&&b| b == 0).count()..].to_vec();
+        let (n, e) = (strip(n), strip(e));
+
+        if !(MIN_BITS..=MAX_BITS).contains(&bit_len(&n)) || n.last().unwrap() & 1 == 0 {
+            return Err(InvalidKey::UnsupportedSize);
+        }
+        // e = 1 would leave messages as they are, and an even e has no inverse mod (p - 1)(q - 1)
+        if bit_len(&e) < 2 || e.last().unwrap() & 1 == 0 || e.len() > n.len() {
+            return Err(InvalidKey::BadExponent);
+        }
+
+        let key = Self { n, e };
+        if with_modulus!(&key, |modulus| modulus.e >= modulus.n) {
+            return Err(InvalidKey::BadExponent);
+        }
+        Ok(key)
+    }
+}
+
+impl PublicKey {
+    pub fn bits(&self) -> usize {
+        bit_len(&self.n)
+    }
+
+    fn modulus<const LIMBS: usize>(&self) -> Modulus<LIMBS> {
+        Modulus {
+            n: uint_from_be_bytes(&self.n),
+            e: uint_from_be_bytes(&self.e),
+            bytes: self.n.len(),
+        }
+    }
+
+    // RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<Self, InvalidKey> {
+        let [n, e] = parse_der_integers(der)
+            .and_then(|integers| <[Vec<u8>; 2]>::try_from(integers).ok())
+            .ok_or(InvalidKey::Malformed)?;
+        Self::try_from((n, e))
+    }
+
+    pub fn to_pkcs1_der(&self) -> Vec<u8> {
+        der_sequence(&[&self.n, &self.e])
+    }
+
+    // the longest message that encrypt takes
+    pub fn max_message_len(&self) -> usize {
+        self.n.len() - 2 * DIGEST_BYTES - 2
+    }
+
+    // RSAES-OAEP with an empty label. None if the message is longer than max_message_len.
+    pub fn encrypt(&self, message: &[u8]) -> Option<Vec<u8>> {
+        if message.len() > self.max_message_len() {
+            return None;
+        }
+
+        // DB = lHash || PS || 0x01 || M, masked by the seed and then masking the seed in turn
+        let k = self.n.len();
+        let mut db = vec![0u8; k - DIGEST_BYTES - 1];
+        db[..DIGEST_BYTES].copy_from_slice(&sha256::hash(b""));
+        db[k - DIGEST_BYTES - 2 - message.len()] = 1;
+        db[k - DIGEST_BYTES - 1 - message.len()..].copy_from_slice(message);
+
+        let mut seed = [0u8; DIGEST_BYTES];
+        ThreadDrbg.fill_bytes(&mut seed);
+        xor_in_place(&mut db, &mgf1(&seed, k - DIGEST_BYTES - 1));
+        xor_in_place(&mut seed, &mgf1(&db, DIGEST_BYTES));
+
+        let em = [&[0][..], &seed, &db].concat();
+        Some(with_modulus!(self, |modulus| {
+            modulus.encode(&modulus.public_op(&uint_from_be_bytes(&em)))
+        }))
+    }
+
+    // RSASSA-PSS, with the salt length recovered from the signature
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        let em_bits = self.bits() - 1;
+        let em_len = em_bits.div_ceil(8);
+
+        let Some(encoded) = with_modulus!(self, |modulus| {
+            modulus
+                .decode(signature)
+                .map(|s| modulus.encode(&modulus.public_op(&s)))
+        }) else {
+            return false;
+        };
+        // when the modulus is 8k + 1 bits long, EM is a byte shorter than it
+        let (zeros, em) = encoded.split_at(encoded.len() - em_len);
+        if zeros.iter().any(|&b| b != 0) || em_len < DIGEST_BYTES + 2 {
+            return false;
+        }
+
+        let (masked_db, rest) = em.split_at(em_len - DIGEST_BYTES - 1);
+        let (h, trailer) = rest.split_at(DIGEST_BYTES);
+        // the bits of EM above em_bits have to be zero
+        let used_bits = 0xffu8 >> (8 * em_len - em_bits);
+        if trailer != [0xbc] || masked_db[0] & !used_bits != 0 {
+            return false;
+        }
+
+        let mut db = masked_db.to_vec();
+        let mask = mgf1(h, db.len());
+        xor_in_place(&mut db, &mask);
+        db[0] &= used_bits;
+
+        // DB = PS || 0x01 || salt, where PS is all zeros
+        let Some(separator) = db.iter().position(|&b| b != 0) else {
+            return false;
+        };
+        if db[separator] != 1 {
+            return false;
+        }
+        let salt = &db[separator + 1..];
+
+        pss_hash(message, salt) == h
+    }
+}
+
+#[derive(Clone)]
+pub struct PrivateKey {
+    public: PublicKey,
+    d: Vec<u8>,
+    p: Vec<u8>,
+    q: Vec<u8>,
+    dp: Vec<u8>,
+    dq: Vec<u8>,
+    qinv: Vec<u8>,
+}
+
+// the primes and CRT exponents as integers, as wide as n
+struct Crt<const LIMBS: usize> {
+    p: Uint<LIMBS>,
+    q: Uint<LIMBS>,
+    dp: Uint<LIMBS>,
+    dq: Uint<LIMBS>,
+    qinv: Uint<LIMBS>,
+}
+
+impl PrivateKey {
+    // a new key with e = 65537 and a modulus of one of SIZES
+    pub fn generate(bits: usize) -> Self {
+        assert!(SIZES.contains(&bits));
+
+        match bits {
+            2048 => generate_key::<{ U2048::LIMBS }>(bits),
+            3072 => generate_key::<{ U3072::LIMBS }>(bits),
+            _ => generate_key::<{ U4096::LIMBS }>(bits),
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    // RSAPrivateKey ::= SEQUENCE { version INTEGER, modulus INTEGER, publicExponent INTEGER,
+    //     privateExponent INTEGER, prime1 INTEGER, prime2 INTEGER, exponent1 INTEGER,
+    //     exponent2 INTEGER, coefficient INTEGER }
+    // with version 0, as keys of more than two primes aren't supported
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<Self, InvalidKey> {
+        let [version, n, e, d, p, q, dp, dq, qinv] = parse_der_integers(der)
+            .and_then(|integers| <[Vec<u8>; 9]>::try_from(integers).ok())
+            .ok_or(InvalidKey::Malformed)?;
+        if !version.is_empty() {
+            return Err(InvalidKey::Malformed);
+        }
+
+        let public = PublicKey::try_from((n, e))?;
+        if [&d, &p, &q, &dp, &dq, &qinv]
+            .iter()
+            .any(|x| x.len() > public.n.len())
+        {
+            return Err(InvalidKey::Inconsistent);
+        }
+
+        let key = Self {
+            public,
+            d,
+            p,
+            q,
+            dp,
+            dq,
+            qinv,
+        };
+        with_modulus!(&key.public, |modulus| key.check(modulus))?;
+        Ok(key)
+    }
+
+    pub fn to_pkcs1_der(&self) -> Vec<u8> {
+        der_sequence(&[
+            &[],
+            &self.public.n,
+            &self.public.e,
+            &self.d,
+            &self.p,
+            &self.q,
+            &self.dp,
+            &self.dq,
+            &self.qinv,
+        ])
+    }
+
+    fn crt<const LIMBS: usize>(&self) -> Crt<LIMBS> {
+        Crt {
+            p: uint_from_be_bytes(&self.p),
+            q: uint_from_be_bytes(&self.q),
+            dp: uint_from_be_bytes(&self.dp),
+            dq: uint_from_be_bytes(&self.dq),
+            qinv: uint_from_be_bytes(&self.qinv),
+        }
+    }
+
+    fn check<const LIMBS: usize>(&self, modulus: &Modulus<LIMBS>) -> Result<(), InvalidKey> {
+        let Crt { p, q, dp, dq, qinv } = self.crt::<LIMBS>();
+        let d: Uint<LIMBS> = uint_from_be_bytes(&self.d);
+
+        if p.mul_wide(&q) != (modulus.n, Uint::ZERO) {
+            return Err(InvalidKey::Inconsistent);
+        }
+        if !is_probable_prime(&p) || !is_probable_prime(&q) {
+            return Err(InvalidKey::NotPrime);
+        }
+
+        // d, dP and dQ all have to undo e, modulo p - 1 and q - 1
+        for (prime, crt_exponent) in [(&p, &dp), (&q, &dq)] {
+            let order = prime.wrapping_sub(&Uint::ONE);
+            if d.wrapping_rem(&order) != *crt_exponent
+                || mul_mod(&modulus.e, crt_exponent, &order) != Uint::ONE
+            {
+                return Err(InvalidKey::Inconsistent);
+            }
+        }
+        if d >= modulus.n || qinv >= p || mul_mod(&qinv, &q, &p) != Uint::ONE {
+            return Err(InvalidKey::Inconsistent);
+        }
+
+        Ok(())
+    }
+
+    // RSAES-OAEP decryption. every way of failing gives the same None, and the checks all run
+    // before it's returned, so that decryption can't be used as an oracle (Manger's attack).
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        let k = self.public.n.len();
+        let em = with_modulus!(&self.public, |modulus| {
+            let c = modulus.decode(ciphertext)?;
+            modulus.encode(&private_op(modulus, &self.crt(), &c))
+        });
+
+        let (y, rest) = em.split_first().unwrap();
+        let (masked_seed, masked_db) = rest.split_at(DIGEST_BYTES);
+        let mut seed = masked_seed.to_vec();
+        xor_in_place(&mut seed, &mgf1(masked_db, DIGEST_BYTES));
+        let mut db = masked_db.to_vec();
+        xor_in_place(&mut db, &mgf1(&seed, k - DIGEST_BYTES - 1));
+
+        let (label_hash, rest) = db.split_at(DIGEST_BYTES);
+        let mut bad = *y;
+        for (a, b) in label_hash.iter().zip(sha256::hash(b"")) {
+            bad |= a ^ b;
+        }
+
+        // PS || 0x01 || M: find the first 1, and fail if anything other than 0 comes before it
+        let mut found = 0u8;
+        let mut start = 0;
+        for (i, &b) in rest.iter().enumerate() {
+            let is_separator = !found & u8::from(b == 1);
+            start |= usize::from(is_separator) * (i + 1);
+            bad |= !found & u8::from(b != 0 && b != 1);
+            found |= is_separator;
+        }
+        bad |= !found & 1;
+
+        (bad == 0).then(|| rest[start..].to_vec())
+    }
+
+    // RSASSA-PSS with a salt as long as the hash
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let em_bits = self.public.bits() - 1;
+        let em_len = em_bits.div_ceil(8);
+
+        let mut salt = [0u8; SALT_BYTES];
+        ThreadDrbg.fill_bytes(&mut salt);
+        let h = pss_hash(message, &salt);
+
+        // DB = PS || 0x01 || salt, masked with H
+        let mut db = vec![0u8; em_len - DIGEST_BYTES - 1];
+        let separator = db.len() - SALT_BYTES - 1;
+        db[separator] = 1;
+        db[separator + 1..].copy_from_slice(&salt);
+        let mask = mgf1(&h, db.len());
+        xor_in_place(&mut db, &mask);
+        db[0] &= 0xff >> (8 * em_len - em_bits);
+
+        let em = [&db[..], &h, &[0xbc]].concat();
+        with_modulus!(&self.public, |modulus| {
+            let m = uint_from_be_bytes(&em);
+            modulus.encode(&private_op(modulus, &self.crt(), &m))
+        })
+    }
+}
+
+fn generate_prime<const LIMBS: usize>(bits: usize) -> Uint<LIMBS> {
+    loop {
+        // with the top two bits set, the product of two of these has exactly twice the bits
+        let candidate = Uint::<LIMBS>::random(&mut ThreadDrbg)
+            .shr_vartime(Uint::<LIMBS>::BITS - bits)
+            .bitor(&Uint::from_u8(3).shl_vartime(bits - 2))
+            .bitor(&Uint::ONE);
+
+        // p - 1 has to be coprime to e, which for a prime e means p mod e isn't 1. the primality
+        // tests aren't constant-time, but they only ever see the one candidate that passes.
+        if rem_small(&candidate, PUBLIC_EXPONENT) != 1 && is_probable_prime(&candidate) {
+            return candidate;
+        }
+    }
+}
+
+fn generate_key<const LIMBS: usize>(bits: usize) -> PrivateKey {
+    let e = Uint::<LIMBS>::from_u32(PUBLIC_EXPONENT);
+
+    loop {
+        let p = generate_prime::<LIMBS>(bits / 2);
+        let q = generate_prime::<LIMBS>(bits / 2);
+
+        // primes this close would let Fermat's method factor n (FIPS 186-4, appendix B.3.1)
+        let distance = if p > q {
+            p.wrapping_sub(&q)
+        } else {
+            q.wrapping_sub(&p)
+        };
+        if distance.bits_vartime() <= bits / 2 - 100 {
+            continue;
+        }
+
+        let n = p.wrapping_mul(&q);
+        let p_minus_one = p.wrapping_sub(&Uint::ONE);
+        let q_minus_one = q.wrapping_sub(&Uint::ONE);
+        // e is coprime to both, so this always exists
+        let d = e.inv_mod(&p_minus_one.wrapping_mul(&q_minus_one)).0;
+
+        return PrivateKey {
+            public: PublicKey {
+                n: to_minimal_bytes(&n),
+                e: to_minimal_bytes(&e),
+            },
+            d: to_minimal_bytes(&d),
+            p: to_minimal_bytes(&p),
+            q: to_minimal_bytes(&q),
+            dp: to_minimal_bytes(&d.wrapping_rem(&p_minus_one)),
+            dq: to_minimal_bytes(&d.wrapping_rem(&q_minus_one)),
+            qinv: to_minimal_bytes(&q.inv_odd_mod(&p).0),
+        };
+    }
+}
+
+// x^d mod n, by way of the CRT (RFC 8017, section 5.1.2). x is blinded with a random r^e first,
+// so the time the exponentiations take has nothing to do with x.
+fn private_op<const LIMBS: usize>(
+    modulus: &Modulus<LIMBS>,
+    crt: &Crt<LIMBS>,
+    x: &Uint<LIMBS>,
+) -> Uint<LIMBS> {
+    let n = &modulus.n;
+    let (r, r_inv) = loop {
+        let r = Uint::random_mod(&mut ThreadDrbg, &NonZero::new(*n).unwrap());
+        let (r_inv, exists) = r.inv_odd_mod(n);
+        if bool::from(exists) {
+            break (r, r_inv);
+        }
+    };
+    let blinded = mul_mod(x, &modulus.public_op(&r), n);
+
+    let Crt { p, q, dp, dq, qinv } = crt;
+    let m1 = pow_mod(
+        &blinded.rem(&NonZero::new(*p).unwrap()),
+        dp,
+        p.bits_vartime(),
+        p,
+    );
+    let m2 = pow_mod(
+        &blinded.rem(&NonZero::new(*q).unwrap()),
+        dq,
+        q.bits_vartime(),
+        q,
+    );
+    let m2_mod_p = m2.rem(&NonZero::new(*p).unwrap());
+    let h = mul_mod(&m1.sub_mod(&m2_mod_p, p), qinv, p);
+    let m = m2.wrapping_add(&h.wrapping_mul(q));
+
+    // a fault in either half would give a result that's only right modulo the other prime, and
+    // the gcd of its difference from the right one with n would be a factor (the Bellcore attack)
+    assert!(modulus.public_op(&m) == blinded, "RSA-CRT fault");
+
+    mul_mod(&m, &r_inv, n)
+}
+
+// MGF1 with SHA-256 (RFC 8017, appendix B.2.1)
+fn mgf1(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(len.next_multiple_of(DIGEST_BYTES));
+    for counter in 0u32.. {
+        if output.len() >= len {
+            break;
+        }
+        output.extend_from_slice(&sha256::hash(&[seed, &counter.to_be_bytes()].concat()));
+    }
+
+    output.truncate(len);
+    output
+}
+
+fn xor_in_place(data: &mut [u8], mask: &[u8]) {
+    for (x, m) in data.iter_mut().zip(mask) {
+        *x ^= m;
+    }
+}
+
+// H = Hash(0x00 * 8 || Hash(M) || salt)
+fn pss_hash(message: &[u8], salt: &[u8]) -> sha256::Digest {
+    sha256::hash(&[&[0u8; 8][..], &sha256::hash(message), salt].concat())
+}
+
+// just enough DER for PKCS#1: a SEQUENCE of non-negative INTEGERs
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+
+fn der_tlv(tag: u8, contents: &[u8], output: &mut Vec<u8>) {
+    output.push(tag);
+    if contents.len() < 0x80 {
+        output.push(contents.len() as u8);
+    } else {
+        let len = contents.len().to_be_bytes();
+        let zeros = len.iter().take_while(|&&b| b == 0).count();
+        output.push(0x80 | (len.len() - zeros) as u8);
+        output.extend_from_slice(&len[zeros..]);
+    }
+    output.extend_from_slice(contents);
+}
+
+// the integers are big-endian without leading zeros
+fn der_sequence(integers: &[&[u8]]) -> Vec<u8> {
+    let mut contents = Vec::new();
+    for integer in integers {
+        // a set top bit would make it negative
+        if integer.first().is_none_or(|&b| b & 0x80 != 0) {
+            der_tlv(INTEGER, &[&[0], *integer].concat(), &mut contents);
+        } else {
+            der_tlv(INTEGER, integer, &mut contents);
+        }
+    }
+
+    let mut output = Vec::new();
+    der_tlv(SEQUENCE, &contents, &mut output);
+    output
+}
+
+// (tag, contents, what follows)
+fn read_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, data) = data.split_first()?;
+    let (&first, mut data) = data.split_first()?;
+
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        // DER only uses the long form when it has to, and without leading zeros
+        let count = (first & 0x7f) as usize;
+        let bytes = data
+            .get(..count)
+            .filter(|bytes| (1..=4).contains(&bytes.len()))?;
+        if bytes[0] == 0 {
+            return None;
+        }
+        data = &data[count..];
+        let len = bytes.iter().fold(0, |len, &b| len << 8 | b as usize);
+        if len < 0x80 {
+            return None;
+        }
+        len
+    };
+
+    let contents = data.get(..len)?;
+    Some((tag, contents, &data[len..]))
+}
+
+// the integers of a SEQUENCE, big-endian without leading zeros (so 0 is empty). None for anything
+// that isn't strict DER, negative numbers, and trailing data.
+fn parse_der_integers(der: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let (tag, mut contents, rest) = read_tlv(der)?;
+    if tag != SEQUENCE || !rest.is_empty() {
+        return None;
+    }
+
+    let mut integers = Vec::new();
+    while !contents.is_empty() {
+        let (tag, integer, rest) = read_tlv(contents)?;
+        contents = rest;
+
+        let (&first, tail) = integer.split_first()?;
+        let minimal = first != 0 || tail.first().is_none_or(|&b| b & 0x80 != 0);
+        if tag != INTEGER || first & 0x80 != 0 || !minimal {
+            return None;
+        }
+        integers.push(if first == 0 { tail } else { integer }.to_vec());
+    }
+
+    Some(integers)
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // a 1024-bit key from `openssl genrsa`, in PKCS#1
+    const OPENSSL_KEY: [u8; 609] = hex!(
+        "3082025d02010002818100d3f98ba4482f184894c069dba10915db3206e37506"
+        "979493bb925fedd390aed6dae29be8b5ed5e26e08ca996f508ca733de6f8fea8"
+        "8d6953a3ef397c1b94b6db2e5147dd5b1b210cdd7730a920671f971634e78c77"
+        "9fc90ad0a9ac0707022927c40c63c2b6a1bb6801ccc5af788ef87a53b3250aa9"
+        "634fed5bcc2bbc9e6e41a5020301000102818100a4c3dadc36538cff95c83e40"
+        "3e5aee8733ca90f0bcd003e8152b80af3efcbe3a39675d22b48eadd9703f45ef"
+        "15c5e07f55457c938d7665c162b2a72f25cd2197b06c041f59ef8b7245c8fbfb"
+        "1b2de62a52dd838f8db5aa226fd4b873b4567432db5def8a7658f596bcd533af"
+        "7efce45fc48a54801deefcc3e20cea464d38ca01024100f246792604f6778c3b"
+        "95f8a46c46f9da4ce3edcaeca2b147692c79fee1addc27e6fb117305a7eb6503"
+        "78af555f211bb1841778f185bdadd717e59ca104020f25024100dffba5e86b64"
+        "26c08118ff4556fc00baa7cc2d3c717a7f7b82c04c687aab9317ad19dec7a610"
+        "42ecac678353202d24ab7539b392e350e93ccfa751462180208102402bc14687"
+        "f3457ff9b799323a9fc381c44736b2ef7f2532d72ecb758ceebd33919149aeb1"
+        "13486c5ee5ed99dcd53d697ef9d833638b3fe271824359b24ac30471024072ae"
+        "d2e0123c916af7aee8e584083c07566b40ae361c56b1d2dccb42a3acbdffdd6d"
+        "e597abfb9f88b72db91c12d98a710ff09f7592569755bfad17fc24a30a010241"
+        "00cdbbbd16c6fe3ade82a2fc98dece5eaa2ea20e3c9198d28492dca8688f3dcf"
+        "3441f0d82dc9f16b83b84dc2a37c92239ae647e9bded9daef487c014cbb6fdba"
+        "cd"
+    );
+
+    // its public half, from `openssl rsa -RSAPublicKey_out`
+    const OPENSSL_PUBLIC_KEY: [u8; 140] = hex!(
+        "30818902818100d3f98ba4482f184894c069dba10915db3206e37506979493bb"
+        "925fedd390aed6dae29be8b5ed5e26e08ca996f508ca733de6f8fea88d6953a3"
+        "ef397c1b94b6db2e5147dd5b1b210cdd7730a920671f971634e78c779fc90ad0"
+        "a9ac0707022927c40c63c2b6a1bb6801ccc5af788ef87a53b3250aa9634fed5b"
+        "cc2bbc9e6e41a50203010001"
+    );
+
+    // OAEP and PSS (with the longest salt that fits) from pyca/cryptography, for "Hello, World!"
+    #[test]
+    fn interoperates_with_openssl() {
+        let key = PrivateKey::from_pkcs1_der(&OPENSSL_KEY).unwrap();
+        let public = PublicKey::from_pkcs1_der(&OPENSSL_PUBLIC_KEY).unwrap();
+        assert_eq!(key.public_key(), &public);
+        assert_eq!(public.bits(), 1024);
+        assert_eq!(key.to_pkcs1_der(), OPENSSL_KEY);
+        assert_eq!(public.to_pkcs1_der(), OPENSSL_PUBLIC_KEY);
+
+        let ciphertext = hex!(
+            "bbb1879cc772fdc98a23eda0bef0b0df7e355fdd934438ce4e457bb39caad4e8"
+            "e3689050204dfc9231ce484f0fc4e4b3f986bedc969a4779ed8eb6767c509062"
+            "12affe522cd5d520091038e05543356ddf6359b56a46d432fadc7be303ed3d6d"
+            "cbd0ca293eced63723a4ed2bffcffbea4e55ed5f9ddec9360ba6a9148d682376"
+        );
+        assert_eq!(key.decrypt(&ciphertext).unwrap(), b"Hello, World!");
+
+        let signature = hex!(
+            "b4948651a7cad61bac275948f8cdbf76b472abb242d4cc5f95b59377cd0c038a"
+            "05d2e96ce1a3b10e54b65579669ef903d42e6c4cfd8416a0d025fd09425bdabd"
+            "3298254ae63167bb46063ad85bb66e6e40011c5b3c17431076433bfc03586abd"
+            "ba45a4685170d9719c734a543dc9517c801ab38c0471de15013f0b99b724431f"
+        );
+        assert!(public.verify(b"Hello, World!", &signature));
+        assert!(!public.verify(b"Hello, World?", &signature));
+    }
+
+    #[test]
+    fn it_works() {
+        let key = PrivateKey::generate(2048);
+        let public = key.public_key();
+        assert_eq!(public.bits(), 2048);
+        assert_eq!(
+            PrivateKey::from_pkcs1_der(&key.to_pkcs1_der()).unwrap().d,
+            key.d
+        );
+
+        let message = vec![42; public.max_message_len()];
+        let ciphertext = public.encrypt(&message).unwrap();
+        assert_eq!(key.decrypt(&ciphertext).unwrap(), message);
+        assert_eq!(key.decrypt(&public.encrypt(b"").unwrap()).unwrap(), b"");
+        assert!(public.encrypt(&[message, vec![42]].concat()).is_none());
+
+        let mut tampered = ciphertext.clone();
+        tampered[100] ^= 1;
+        assert!(key.decrypt(&tampered).is_none());
+        assert!(key.decrypt(&ciphertext[1..]).is_none());
+
+        let signature = key.sign(b"Hello, World!");
+        assert!(public.verify(b"Hello, World!", &signature));
+        let mut tampered = signature.clone();
+        tampered[0] ^= 1;
+        assert!(!public.verify(b"Hello, World!", &tampered));
+        assert!(!public.verify(b"Hello, World!", &public.n));
+
+        let bytes = bincode::serialize(public).unwrap();
+        assert_eq!(&bincode::deserialize::<PublicKey>(&bytes).unwrap(), public);
+    }
+
+    #[test]
+    fn rejects_bad_keys() {
+        let key = PrivateKey::from_pkcs1_der(&OPENSSL_KEY).unwrap();
+        let with = |change: &dyn Fn(&mut PrivateKey)| {
+            let mut key = key.clone();
+            change(&mut key);
+            PrivateKey::from_pkcs1_der(&key.to_pkcs1_der()).err()
+        };
+
+        assert_eq!(
+            with(&|key| key.p.swap(0, 1)),
+            Some(InvalidKey::Inconsistent)
+        );
+        assert_eq!(with(&|key| key.dq[5] ^= 1), Some(InvalidKey::Inconsistent));
+        assert_eq!(
+            with(&|key| key.qinv[5] ^= 1),
+            Some(InvalidKey::Inconsistent)
+        );
+        assert_eq!(
+            with(&|key| key.public.e = vec![3]),
+            Some(InvalidKey::Inconsistent)
+        );
+        assert_eq!(
+            with(&|key| key.public.e = vec![1, 0]),
+            Some(InvalidKey::BadExponent)
+        );
+        assert_eq!(
+            with(&|key| key.public.n.truncate(64)),
+            Some(InvalidKey::UnsupportedSize)
+        );
+        // an even modulus
+        assert_eq!(
+            PublicKey::try_from((vec![0x80; 128], vec![1, 0, 1])),
+            Err(InvalidKey::UnsupportedSize)
+        );
+
+        // DER that isn't minimal, has trailing data or is cut short
+        let mut long_form = OPENSSL_PUBLIC_KEY.to_vec();
+        long_form.splice(1..3, [0x82, 0x00, 0x89]);
+        assert_eq!(
+            PublicKey::from_pkcs1_der(&long_form),
+            Err(InvalidKey::Malformed)
+        );
+        for bad in [
+            [&OPENSSL_PUBLIC_KEY[..], &[0]].concat(),
+            OPENSSL_PUBLIC_KEY[..139].to_vec(),
+        ] {
+            assert_eq!(PublicKey::from_pkcs1_der(&bad), Err(InvalidKey::Malformed));
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use super::hash::HashFunction;
+use super::sha256::{self, Digest, Sha256};
+
+// short authentication strings: a digest of the handshake that both sides compute on their own and
+// then compare over a channel they trust, by reading it out or looking at it side by side. anyone
+// who swapped a public key on the way has a different transcript on each side, and would have to
+// find one that matches in the shown bits. every style shows at least 64 of them, which is more
+// than an attacker can try while the exchange is going on.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Style {
+    #[default]
+    Words,
+    Emoji,
+    Randomart,
+}
+
+impl Style {
+    pub const ALL: [Style; 3] = [Self::Words, Self::Emoji, Self::Randomart];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Words => "Words",
+            Self::Emoji => "Emoji",
+            Self::Randomart => "Randomart",
+        }
+    }
+}
+
+// the digest of both messages of a handshake, in the order they were sent. each is prefixed with
+// its length, so that moving bytes from one to the other changes the result.
+pub fn transcript_hash(alice: &[u8], bob: &[u8]) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(b"titanium-rose sas");
+    for message in [alice, bob] {
+        hasher.update(&(message.len() as u64).to_be_bytes());
+        hasher.update(message);
+    }
+
+    hasher.finalize()
+}
+
+// the first 128 bits of a hash of some data in hex, in the same form as identity fingerprints
+pub fn fingerprint(data: &[u8]) -> String {
+    format_fingerprint(&sha256::hash(data))
+}
+
+// the first 128 bits of a digest in hex, in groups of four digits
+pub(crate) fn format_fingerprint(digest: &Digest) -> String {
+    digest[..16]
+        .chunks(2)
+        .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn render(digest: &Digest, style: Style) -> String {
+    match style {
+        Style::Words => words(digest),
+        Style::Emoji => emoji(digest),
+        Style::Randomart => randomart(digest),
+    }
+}
+
+// eight words, one for each of the first eight bytes
+fn words(digest: &Digest) -> String {
+    digest[..8]
+        .iter()
+        .map(|&b| WORDS[b as usize])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// eleven emoji with their names, from the first 66 bits taken six at a time. the table is the one
+// Matrix uses for its SAS, so it might already be familiar.
+fn emoji(digest: &Digest) -> String {
+    let bits = u128::from_be_bytes(digest[..16].try_into().unwrap());
+    (0..11)
+        .map(|i| {
+            let (symbol, name) = EMOJI[(bits >> (122 - 6 * i)) as usize & 63];
+            format!("{symbol} {name}")
+        })
+        .collect::<Vec<_>>()
+        .join("   ")
+}
+
+const RANDOMART_WIDTH: usize = 17;
+const RANDOMART_HEIGHT: usize = 9;
+const RANDOMART_SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+// the "drunken bishop" of OpenSSH's VisualHostKey: a bishop starts in the middle of the board and
+// takes a diagonal step for every two bits of the digest, and each square shows how often it was
+// visited. S and E are where it started and ended.
+fn randomart(digest: &Digest) -> String {
+    let mut board = [[0usize; RANDOMART_WIDTH]; RANDOMART_HEIGHT];
+    let (mut x, mut y) = (RANDOMART_WIDTH / 2, RANDOMART_HEIGHT / 2);
+    let most = RANDOMART_SYMBOLS.len() - 3;
+
+    for &byte in digest {
+        for step in 0..4 {
+            let bits = byte >> (2 * step);
+            x = if bits & 1 == 1 {
+                (x + 1).min(RANDOMART_WIDTH - 1)
+            } else {
+                x.saturating_sub(1)
+            };
+            y = if bits & 2 == 2 {
+                (y + 1).min(RANDOMART_HEIGHT - 1)
+            } else {
+                y.saturating_sub(1)
+            };
+            board[y][x] = (board[y][x] + 1).min(most);
+        }
+    }
+    board[RANDOMART_HEIGHT / 2][RANDOMART_WIDTH / 2] = most + 1;
+    board[y][x] = most + 2;
+
+    let border = format!("+{}+", "-".repeat(RANDOMART_WIDTH));
+    let mut lines = vec![border.clone()];
+    for row in board {
+        let row: String = row.iter().map(|&n| RANDOMART_SYMBOLS[n] as char).collect();
+        lines.push(format!("|{row}|"));
+    }
+    lines.push(border);
+
+    lines.join("\n")
+}
+
+#[rustfmt::skip]
+const WORDS: [&str; 256] = [
+    "acid", "acorn", "actor", "adobe", "agent", "alarm", "album", "alley", "amber", "anchor",
+    "angle", "ankle", "apple", "apron", "arena", "arrow", "aspen", "atlas", "attic", "audio",
+    "award", "bacon", "badge", "bagel", "baker", "bamboo", "banjo", "barn", "basil", "beach",
+    "beard", "beetle", "bell", "bench", "berry", "bison", "blade", "blanket", "bloom", "board",
+    "boat", "bones", "bonus", "boot", "bottle", "bread", "brick", "bridge", "broom", "brush",
+    "bucket", "bugle", "cabin", "cactus", "camel", "candle", "canoe", "canvas", "carpet", "carrot",
+    "castle", "cedar", "chalk", "cherry", "chess", "chimney", "cider", "cinema", "circus", "clock",
+    "cloud", "clover", "coast", "cobra", "cocoa", "comet", "cookie", "copper", "coral", "cotton",
+    "cradle", "crane", "crater", "crown", "cube", "dagger", "daisy", "dance", "delta", "denim",
+    "desert", "diamond", "dinner", "dolphin", "donkey", "dragon", "drum", "eagle", "easel", "echo",
+    "elbow", "ember", "engine", "falcon", "feather", "fence", "ferry", "fiddle", "flame", "flute",
+    "forest", "fossil", "fountain", "frog", "galaxy", "garden", "garlic", "ginger", "giraffe",
+    "glacier", "glove", "goat", "grape", "gravel", "guitar", "hammer", "harbor", "harp", "hawk",
+    "hazel", "helmet", "hermit", "honey", "hotel", "iceberg", "igloo", "island", "ivory", "jacket",
+    "jaguar", "jelly", "jewel", "jigsaw", "jungle", "kayak", "kettle", "kiwi", "koala", "ladder",
+    "lagoon", "lantern", "lemon", "lizard", "lobster", "locket", "lotus", "magnet", "mango",
+    "maple", "marble", "meadow", "melon", "mirror", "monkey", "mosaic", "muffin", "napkin",
+    "nectar", "needle", "nickel", "noodle", "oasis", "ocean", "olive", "onion", "orbit", "otter",
+    "owl", "paddle", "palace", "panda", "parrot", "peach", "pebble", "pepper", "piano", "pickle",
+    "pillow", "pirate", "planet", "plum", "pocket", "pony", "potato", "pumpkin", "puzzle", "quartz",
+    "quill", "rabbit", "radio", "raft", "rainbow", "raven", "ribbon", "river", "robin", "rocket",
+    "saddle", "salmon", "sandal", "saturn", "scarf", "shadow", "shell", "silver", "sketch", "sled",
+    "snail", "sphinx", "spider", "sponge", "squid", "statue", "stone", "sugar", "summit", "sunset",
+    "swan", "table", "tango", "teapot", "tiger", "timber", "toast", "tomato", "torch", "tractor",
+    "tulip", "tunnel", "turtle", "umbrella", "valley", "velvet", "violin", "volcano", "wagon",
+    "walnut", "walrus", "whale", "window", "winter", "wizard", "yacht", "yogurt", "zebra", "zipper",
+];
+
+#[rustfmt::skip]
+const EMOJI: [(&str, &str); 64] = [
+    ("\u{1f436}", "Dog"), ("\u{1f431}", "Cat"), ("\u{1f981}", "Lion"),
+    ("\u{1f40e}", "Horse"), ("\u{1f984}", "Unicorn"), ("\u{1f437}", "Pig"),
+    ("\u{1f418}", "Elephant"), ("\u{1f430}", "Rabbit"), ("\u{1f43c}", "Panda"),
+    ("\u{1f413}", "Rooster"), ("\u{1f427}", "Penguin"), ("\u{1f422}", "Turtle"),
+    ("\u{1f41f}", "Fish"), ("\u{1f419}", "Octopus"), ("\u{1f98b}", "Butterfly"),
+    ("\u{1f337}", "Flower"), ("\u{1f333}", "Tree"), ("\u{1f335}", "Cactus"),
+    ("\u{1f344}", "Mushroom"), ("\u{1f30f}", "Globe"), ("\u{1f319}", "Moon"),
+    ("\u{2601}", "Cloud"), ("\u{1f525}", "Fire"), ("\u{1f34c}", "Banana"),
+    ("\u{1f34e}", "Apple"), ("\u{1f353}", "Strawberry"), ("\u{1f33d}", "Corn"),
+    ("\u{1f355}", "Pizza"), ("\u{1f382}", "Cake"), ("\u{2764}", "Heart"),
+    ("\u{1f600}", "Smiley"), ("\u{1f916}", "Robot"), ("\u{1f3a9}", "Hat"),
+    ("\u{1f453}", "Glasses"), ("\u{1f527}", "Spanner"), ("\u{1f385}", "Santa"),
+    ("\u{1f44d}", "Thumbs Up"), ("\u{2602}", "Umbrella"), ("\u{231b}", "Hourglass"),
+    ("\u{23f0}", "Clock"), ("\u{1f381}", "Gift"), ("\u{1f4a1}", "Light Bulb"),
+    ("\u{1f4d5}", "Book"), ("\u{270f}", "Pencil"), ("\u{1f4ce}", "Paperclip"),
+    ("\u{2702}", "Scissors"), ("\u{1f512}", "Lock"), ("\u{1f511}", "Key"),
+    ("\u{1f528}", "Hammer"), ("\u{260e}", "Telephone"), ("\u{1f3c1}", "Flag"),
+    ("\u{1f682}", "Train"), ("\u{1f6b2}", "Bicycle"), ("\u{2708}", "Aeroplane"),
+    ("\u{1f680}", "Rocket"), ("\u{1f3c6}", "Trophy"), ("\u{26bd}", "Ball"),
+    ("\u{1f3b8}", "Guitar"), ("\u{1f3ba}", "Trumpet"), ("\u{1f514}", "Bell"),
+    ("\u{2693}", "Anchor"), ("\u{1f3a7}", "Headphones"), ("\u{1f4c1}", "Folder"),
+    ("\u{1f4cc}", "Pin"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_is_unambiguous() {
+        let digest = transcript_hash(b"alice", b"bob");
+        assert_eq!(digest, transcript_hash(b"alice", b"bob"));
+        assert_ne!(digest, transcript_hash(b"alic", b"ebob"));
+        assert_ne!(digest, transcript_hash(b"bob", b"alice"));
+
+        for style in Style::ALL {
+            assert_ne!(
+                render(&digest, style),
+                render(&transcript_hash(b"alice", b"bab"), style)
+            );
+        }
+    }
+
+    #[test]
+    fn words_and_emoji() {
+        let mut digest = [0u8; sha256::DIGEST_BYTES];
+        assert_eq!(words(&digest), "acid acid acid acid acid acid acid acid");
+        digest[..8].copy_from_slice(&[0, 1, 2, 3, 4, 5, 254, 255]);
+        assert_eq!(
+            words(&digest),
+            "acid acorn actor adobe agent alarm zebra zipper"
+        );
+
+        // 1, 2, 3, 4, 5, 6, 63, then zeros and 63 again in bits 60 to 65, six bits each
+        digest[..9].copy_from_slice(&[0x04, 0x20, 0xc4, 0x14, 0x6f, 0xc0, 0, 0x0f, 0xc0]);
+        assert!(emoji(&digest).starts_with("\u{1f431} Cat   \u{1f981} Lion   \u{1f40e} Horse"));
+        assert!(emoji(&digest).ends_with("\u{1f436} Dog   \u{1f4cc} Pin"));
+        assert_eq!(emoji(&digest).matches("   ").count(), 10);
+    }
+
+    #[test]
+    fn randomart_shape() {
+        let art = randomart(&transcript_hash(b"alice", b"bob"));
+        let lines: Vec<&str> = art.lines().collect();
+        assert_eq!(lines.len(), RANDOMART_HEIGHT + 2);
+        assert!(lines.iter().all(|line| line.len() == RANDOMART_WIDTH + 2));
+        assert_eq!(art.matches('E').count(), 1);
+        assert!(art.matches('S').count() <= 1);
+
+        // all zeros walks straight up and to the left, into the corner
+        let corner = randomart(&[0; sha256::DIGEST_BYTES]);
+        assert_eq!(&corner.lines().nth(1).unwrap()[..7], "|E.... ");
+    }
+
+    #[test]
+    fn fingerprints() {
+        let digest: Digest = std::array::from_fn(|i| i as u8);
+        assert_eq!(
+            format_fingerprint(&digest),
+            "0001 0203 0405 0607 0809 0a0b 0c0d 0e0f"
+        );
+        assert_eq!(
+            fingerprint(b"abc"),
+            format_fingerprint(&sha256::hash(b"abc"))
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use crypto_bigint::Uint;
+use serde::{Deserialize, Serialize};
+
+use super::group::{uint_from_be_bytes, with_group, Group, GroupId, InvalidElement};
+use super::hash::HashFunction;
+use super::rfc6979::generate_k;
+use super::sas::format_fingerprint;
+use super::sha256::{self, Sha256};
+
+// Schnorr signatures over the order-q subgroups that the key exchanges use. a signature is a pair
+// (e, s) with e = H(g^k || y || m) and s = k + e * x (mod q), where x is the private key and
+// y = g^x the public one.
+
+const CHALLENGE_TAG: &[u8] = b"titanium-rose schnorr";
+
+// g^x, together with its group
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(GroupId, Vec<u8>)")]
+pub struct VerifyingKey(GroupId, Vec<u8>);
+
+impl TryFrom<(GroupId, Vec<u8>)> for VerifyingKey {
+    type Error = InvalidElement;
+
+    fn try_from((group_id, public): (GroupId, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            group.check_subgroup(&group.decode(&public)?)
+        })?;
+        Ok(Self(group_id, public))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "(GroupId, sha256::Digest, Vec<u8>)")]
+pub struct Signature(GroupId, sha256::Digest, Vec<u8>);
+
+impl TryFrom<(GroupId, sha256::Digest, Vec<u8>)> for Signature {
+    type Error = InvalidElement;
+
+    fn try_from(
+        (group_id, challenge, response): (GroupId, sha256::Digest, Vec<u8>),
+    ) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            if &group.decode(&response)? >= group.q() {
+                return Err(InvalidElement::OutOfRange);
+            }
+            Ok(())
+        })?;
+        Ok(Self(group_id, challenge, response))
+    }
+}
+
+// H(tag || r || y || m)
+fn challenge<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    r: &Uint<LIMBS>,
+    public: &[u8],
+    message: &[u8],
+) -> sha256::Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(CHALLENGE_TAG);
+    hasher.update(&group.encode(r));
+    hasher.update(public);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+impl VerifyingKey {
+    pub fn group(&self) -> GroupId {
+        self.0
+    }
+
+    pub(crate) fn encoded(&self) -> &[u8] {
+        &self.1
+    }
+
+    // the first 128 bits of a hash of the key in hex, short enough for people to compare
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.0.name().as_bytes());
+        hasher.update(&self.1);
+
+        format_fingerprint(&hasher.finalize())
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+        if signature.0 != self.0 {
+            return false;
+        }
+
+        with_group!(self.0, |group| {
+            // both were validated when they were created
+            let y = group.decode(&self.1).unwrap();
+            let s = group.decode(&signature.2).unwrap();
+            let e = group.reduce_exponent(&uint_from_be_bytes(&signature.1));
+
+            // g^s * y^(-e) = g^k for a valid signature
+            let r = group.mul(&group.pow_g(&s), &group.inv_pow(&y, &e));
+            challenge(group, &r, &self.1, message) == signature.1
+        })
+    }
+}
+
+// only the group and x are stored, the public key is recomputed when loading
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "(GroupId, Vec<u8>)", into = "(GroupId, Vec<u8>)")]
+pub struct SigningKey {
+    secret: Vec<u8>,
+    public: VerifyingKey,
+}
+
+impl TryFrom<(GroupId, Vec<u8>)> for SigningKey {
+    type Error = InvalidElement;
+
+    fn try_from((group_id, secret): (GroupId, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            let x = group.decode(&secret)?;
+            if x == Uint::ZERO || &x >= group.q() {
+                return Err(InvalidElement::OutOfRange);
+            }
+
+            let public = VerifyingKey(group_id, group.encode(&group.pow_g(&x)));
+            Ok(Self { secret, public })
+        })
+    }
+}
+
+impl From<SigningKey> for (GroupId, Vec<u8>) {
+    fn from(key: SigningKey) -> Self {
+        (key.public.0, key.secret)
+    }
+}
+
+impl SigningKey {
+    pub fn generate(group_id: GroupId) -> Self {
+        with_group!(group_id, |group| {
+            let secret = group.generate_exponent();
+            let public = VerifyingKey(group_id, group.encode(&group.pow_g(&secret)));
+
+            Self {
+                secret: group.encode(&secret),
+                public,
+            }
+        })
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.public
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let group_id = self.public.0;
+        with_group!(group_id, |group| {
+            let x = group.decode(&self.secret).unwrap();
+            let k = generate_k::<Sha256, _>(group.q(), &x, &sha256::hash(message));
+
+            let e = challenge(group, &group.pow_g(&k), &self.public.1, message);
+            let e_int = group.reduce_exponent(&uint_from_be_bytes(&e));
+            let s = group.add_exponents(&k, &group.mul_exponents(&e_int, &x));
+
+            Signature(group_id, e, group.encode(&s))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let key = SigningKey::generate(GroupId::Ffdhe3072);
+        let signature = key.sign(b"Hello, World!");
+        assert!(key.verifying_key().verify(b"Hello, World!", &signature));
+
+        // signatures are deterministic
+        assert_eq!(signature, key.sign(b"Hello, World!"));
+        assert_ne!(signature, key.sign(b"Hello, World?"));
+    }
+
+    #[test]
+    fn rejects_forgeries() {
+        let key = SigningKey::generate(GroupId::Ffdhe3072);
+        let signature = key.sign(b"Hello, World!");
+        let public = key.verifying_key();
+
+        assert!(!public.verify(b"Hello, World?", &signature));
+        let other = SigningKey::generate(GroupId::Ffdhe3072);
+        assert!(!other.verifying_key().verify(b"Hello, World!", &signature));
+
+        let mut tampered = signature.clone();
+        tampered.1[0] ^= 1;
+        assert!(!public.verify(b"Hello, World!", &tampered));
+        let mut tampered = signature.clone();
+        *tampered.2.last_mut().unwrap() ^= 1;
+        assert!(!public.verify(b"Hello, World!", &tampered));
+
+        let other_group = SigningKey::generate(GroupId::Modp3072);
+        assert!(!other_group
+            .verifying_key()
+            .verify(b"Hello, World!", &signature));
+    }
+
+    #[test]
+    fn deserialization_validates() {
+        let key = SigningKey::generate(GroupId::Ffdhe3072);
+        let signature = key.sign(b"Hello, World!");
+
+        let bytes = bincode::serialize(key.verifying_key()).unwrap();
+        assert_eq!(
+            bincode::deserialize::<VerifyingKey>(&bytes).ok().as_ref(),
+            Some(key.verifying_key())
+        );
+        let bytes = bincode::serialize(&key).unwrap();
+        let loaded = bincode::deserialize::<SigningKey>(&bytes).unwrap();
+        assert_eq!(loaded.verifying_key(), key.verifying_key());
+
+        let bytes = bincode::serialize(&signature).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Signature>(&bytes).ok(),
+            Some(signature)
+        );
+
+        let q = with_group!(GroupId::Ffdhe3072, |group| group.encode(group.q()));
+        let bytes = bincode::serialize(&Signature(GroupId::Ffdhe3072, [0; 32], q)).unwrap();
+        let error = bincode::deserialize::<Signature>(&bytes).err().unwrap();
+        assert_eq!(error.to_string(), InvalidElement::OutOfRange.to_string());
+    }
+}
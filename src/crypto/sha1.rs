@@ -0,0 +1,204 @@
+// SHA-1 is broken for collisions, so it's only here for HOTP/TOTP, where it's still the default
+// and only its use inside HMAC matters
+use super::hash::HashFunction;
+
+pub const DIGEST_BYTES: usize = 160 / 8;
+pub type Digest = [u8; DIGEST_BYTES];
+
+pub fn hash(data: &[u8]) -> Digest {
+    let padded = pad(data);
+    let parsed = parse_blocks(&padded);
+
+    let mut hash = START_HASH;
+    for block in parsed {
+        hash_round(block, &mut hash);
+    }
+
+    bytemuck::must_cast(hash.map(Word::to_be_bytes))
+}
+
+// the streaming form of `hash`, for when the input isn't available all at once
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [Word; 5],
+    buffer: [u8; BLOCK_BYTES],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl HashFunction for Sha1 {
+    const BLOCK_BYTES: usize = BLOCK_BYTES;
+    const DIGEST_BYTES: usize = DIGEST_BYTES;
+
+    type Block = [u8; BLOCK_BYTES];
+    type Digest = Digest;
+
+    fn new() -> Self {
+        Self {
+            state: START_HASH,
+            buffer: [0; BLOCK_BYTES],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+
+        while !data.is_empty() {
+            let to_copy = data.len().min(BLOCK_BYTES - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + to_copy]
+                .copy_from_slice(&data[..to_copy]);
+            self.buffer_len += to_copy;
+            data = &data[to_copy..];
+
+            if self.buffer_len == BLOCK_BYTES {
+                for block in parse_blocks(&self.buffer) {
+                    hash_round(block, &mut self.state);
+                }
+                self.buffer_len = 0;
+            }
+        }
+    }
+
+    fn finalize(mut self) -> Digest {
+        // same padding as SHA-256, so the same length fix-up works
+        let mut padded = pad(&self.buffer[..self.buffer_len]);
+        let len = padded.len();
+        padded[len - 8..].copy_from_slice(&(self.total_len * 8).to_be_bytes());
+
+        for block in parse_blocks(&padded) {
+            hash_round(block, &mut self.state);
+        }
+
+        bytemuck::must_cast(self.state.map(Word::to_be_bytes))
+    }
+}
+
+fn hash_round(block: Block, hash: &mut [Word; 5]) {
+    let mut schedule: [Word; 80] = [0; 80];
+
+    schedule[..16].copy_from_slice(&block);
+    for t in 16..80 {
+        schedule[t] = (schedule[t - 3] ^ schedule[t - 8] ^ schedule[t - 14] ^ schedule[t - 16])
+            .rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *hash;
+    for (t, &word) in schedule.iter().enumerate() {
+        let (f, k) = match t {
+            0..=19 => (ch(b, c, d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => (maj(b, c, d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    hash[0] = hash[0].wrapping_add(a);
+    hash[1] = hash[1].wrapping_add(b);
+    hash[2] = hash[2].wrapping_add(c);
+    hash[3] = hash[3].wrapping_add(d);
+    hash[4] = hash[4].wrapping_add(e);
+}
+
+type Word = u32;
+type Block = [Word; BLOCK_WORDS];
+
+pub const BLOCK_BYTES: usize = 512 / 8;
+const BLOCK_WORDS: usize = 512 / 32;
+const WORD_BYTES: usize = 32 / 8;
+
+const START_HASH: [Word; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+fn pad(data: &[u8]) -> Vec<u8> {
+    let last_block_len = data.len() % BLOCK_BYTES;
+
+    let k = (512 + 448 - last_block_len * 8 - 1) % 512;
+    debug_assert_eq!((k + 1) % 8, 0);
+    debug_assert!((k + 1) / 8 > 0);
+    let zero_bytes_to_add = (k + 1) / 8 - 1;
+
+    let mut output = data.to_owned();
+    output.push(0b10000000);
+    output.extend(std::iter::repeat_n(0u8, zero_bytes_to_add));
+    output.extend_from_slice(&((data.len() * 8) as u64).to_be_bytes());
+
+    debug_assert_eq!((output.len() * 8) % 512, 0);
+
+    output
+}
+
+fn parse_blocks(data: &[u8]) -> Vec<Block> {
+    assert_eq!(data.len() % BLOCK_BYTES, 0);
+
+    let num_blocks = data.len() / BLOCK_BYTES;
+    let mut output = Vec::with_capacity(num_blocks);
+
+    for b in 0..num_blocks {
+        let mut block = [0; BLOCK_WORDS];
+        for (w, word) in block.iter_mut().enumerate() {
+            let offset = b * BLOCK_BYTES + w * WORD_BYTES;
+            let bytes: [u8; WORD_BYTES] = data[offset..offset + WORD_BYTES].try_into().unwrap();
+            *word = Word::from_be_bytes(bytes);
+        }
+        output.push(block);
+    }
+
+    output
+}
+
+fn ch(x: Word, y: Word, z: Word) -> Word {
+    (x & y) ^ (!x & z)
+}
+
+fn maj(x: Word, y: Word, z: Word) -> Word {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // FIPS 180-2 appendix A
+    #[test]
+    fn fips_180_examples() {
+        assert_eq!(
+            hash(b"abc"),
+            hex!("a9993e364706816aba3e25717850c26c9cd0d89d")
+        );
+        assert_eq!(
+            hash(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            hex!("84983e441c3bd26ebaae4aa1f95129e5e54670f1")
+        );
+        assert_eq!(
+            hash(&[b'a'; 1_000_000]),
+            hex!("34aa973cd4c4daa4f61eeb2bdbad27316534016f")
+        );
+    }
+
+    #[test]
+    fn streaming_equals_one_shot() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        for chunk_size in [1, 7, 63, 64, 65, 1000] {
+            let mut hasher = Sha1::new();
+            for chunk in data.chunks(chunk_size) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), hash(&data));
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use super::hash::HashFunction;
+
 pub const DIGEST_BYTES: usize = 256 / 8;
 pub type Digest = [u8; DIGEST_BYTES];
 
@@ -13,6 +15,65 @@ pub fn hash(data: &[u8]) -> Digest {
     bytemuck::must_cast(hash.map(Word::to_be_bytes))
 }
 
+// the streaming form of `hash`, for when the input isn't available all at once
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [Word; 8],
+    buffer: [u8; BLOCK_BYTES],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl HashFunction for Sha256 {
+    const BLOCK_BYTES: usize = BLOCK_BYTES;
+    const DIGEST_BYTES: usize = DIGEST_BYTES;
+
+    type Block = [u8; BLOCK_BYTES];
+    type Digest = Digest;
+
+    fn new() -> Self {
+        Self {
+            state: START_HASH,
+            buffer: [0; BLOCK_BYTES],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+
+        while !data.is_empty() {
+            let to_copy = data.len().min(BLOCK_BYTES - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + to_copy]
+                .copy_from_slice(&data[..to_copy]);
+            self.buffer_len += to_copy;
+            data = &data[to_copy..];
+
+            if self.buffer_len == BLOCK_BYTES {
+                for block in parse_blocks(&self.buffer) {
+                    hash_round(block, &mut self.state);
+                }
+                self.buffer_len = 0;
+            }
+        }
+    }
+
+    fn finalize(mut self) -> Digest {
+        // the amount of padding only depends on the length modulo the block size, so padding the
+        // leftover bytes works as long as the length field is fixed up afterwards
+        let mut padded = pad(&self.buffer[..self.buffer_len]);
+        let len = padded.len();
+        padded[len - 8..].copy_from_slice(&(self.total_len * 8).to_be_bytes());
+
+        for block in parse_blocks(&padded) {
+            hash_round(block, &mut self.state);
+        }
+
+        bytemuck::must_cast(self.state.map(Word::to_be_bytes))
+    }
+}
+
 fn hash_round(block: Block, hash: &mut [Word; 8]) {
     let mut schedule: [Word; 64] = [0; 64];
 
@@ -84,7 +145,7 @@ fn pad(data: &[u8]) -> Vec<u8> {
 
     let mut output = data.to_owned();
     output.push(0b10000000);
-    output.extend(std::iter::repeat(0u8).take(zero_bytes_to_add));
+    output.extend(std::iter::repeat_n(0u8, zero_bytes_to_add));
     output.extend_from_slice(&((data.len() * 8) as u64).to_be_bytes());
 
     debug_assert_eq!((output.len() * 8) % 512, 0);
@@ -162,4 +223,17 @@ mod tests {
         let official_hash = Sha256::digest(data);
         assert_eq!(&my_hash, &*official_hash);
     }
+
+    #[test]
+    fn streaming_equals_one_shot() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        for chunk_size in [1, 7, 63, 64, 65, 1000] {
+            let mut hasher = <super::Sha256 as HashFunction>::new();
+            for chunk in data.chunks(chunk_size) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), hash(&data));
+        }
+    }
 }
@@ -0,0 +1,248 @@
+use super::hash::HashFunction;
+
+// SHA-512 (FIPS 180-4), which has the same structure as SHA-256 but with 64-bit words, 80 rounds
+// and 1024-bit blocks. Ed25519 is defined with it.
+
+pub const DIGEST_BYTES: usize = 512 / 8;
+pub type Digest = [u8; DIGEST_BYTES];
+
+pub fn hash(data: &[u8]) -> Digest {
+    let padded = pad(data);
+    let parsed = parse_blocks(&padded);
+
+    let mut hash = START_HASH;
+    for block in parsed {
+        hash_round(block, &mut hash);
+    }
+
+    bytemuck::must_cast(hash.map(Word::to_be_bytes))
+}
+
+// the streaming form of `hash`, for when the input isn't available all at once
+#[derive(Clone)]
+pub struct Sha512 {
+    state: [Word; 8],
+    buffer: [u8; BLOCK_BYTES],
+    buffer_len: usize,
+    total_len: u128,
+}
+
+impl HashFunction for Sha512 {
+    const BLOCK_BYTES: usize = BLOCK_BYTES;
+    const DIGEST_BYTES: usize = DIGEST_BYTES;
+
+    type Block = [u8; BLOCK_BYTES];
+    type Digest = Digest;
+
+    fn new() -> Self {
+        Self {
+            state: START_HASH,
+            buffer: [0; BLOCK_BYTES],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u128;
+
+        while !data.is_empty() {
+            let to_copy = data.len().min(BLOCK_BYTES - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + to_copy]
+                .copy_from_slice(&data[..to_copy]);
+            self.buffer_len += to_copy;
+            data = &data[to_copy..];
+
+            if self.buffer_len == BLOCK_BYTES {
+                for block in parse_blocks(&self.buffer) {
+                    hash_round(block, &mut self.state);
+                }
+                self.buffer_len = 0;
+            }
+        }
+    }
+
+    fn finalize(mut self) -> Digest {
+        // as in sha256, padding the leftover bytes works once the length field is fixed up
+        let mut padded = pad(&self.buffer[..self.buffer_len]);
+        let len = padded.len();
+        padded[len - 16..].copy_from_slice(&(self.total_len * 8).to_be_bytes());
+
+        for block in parse_blocks(&padded) {
+            hash_round(block, &mut self.state);
+        }
+
+        bytemuck::must_cast(self.state.map(Word::to_be_bytes))
+    }
+}
+
+fn hash_round(block: Block, hash: &mut [Word; 8]) {
+    let mut schedule: [Word; 80] = [0; 80];
+
+    schedule[..16].copy_from_slice(&block);
+    for t in 16..80 {
+        schedule[t] = s1(schedule[t - 2])
+            .wrapping_add(schedule[t - 7])
+            .wrapping_add(s0(schedule[t - 15]))
+            .wrapping_add(schedule[t - 16]);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *hash;
+    for t in 0..80 {
+        let temp1 = h
+            .wrapping_add(bs1(e))
+            .wrapping_add(ch(e, f, g))
+            .wrapping_add(K[t])
+            .wrapping_add(schedule[t]);
+        let temp2 = bs0(a).wrapping_add(maj(a, b, c));
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+
+    hash[0] = hash[0].wrapping_add(a);
+    hash[1] = hash[1].wrapping_add(b);
+    hash[2] = hash[2].wrapping_add(c);
+    hash[3] = hash[3].wrapping_add(d);
+    hash[4] = hash[4].wrapping_add(e);
+    hash[5] = hash[5].wrapping_add(f);
+    hash[6] = hash[6].wrapping_add(g);
+    hash[7] = hash[7].wrapping_add(h);
+}
+
+type Word = u64;
+type Block = [Word; BLOCK_WORDS];
+
+pub const BLOCK_BYTES: usize = 1024 / 8;
+const BLOCK_WORDS: usize = 1024 / 64;
+const WORD_BYTES: usize = 64 / 8;
+
+const START_HASH: [Word; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+#[rustfmt::skip]
+const K: [Word; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+fn pad(data: &[u8]) -> Vec<u8> {
+    let last_block_len = data.len() % BLOCK_BYTES;
+
+    let k = (1024 + 896 - last_block_len * 8 - 1) % 1024;
+    debug_assert_eq!((k + 1) % 8, 0);
+    debug_assert!((k + 1) / 8 > 0);
+    let zero_bytes_to_add = (k + 1) / 8 - 1;
+
+    let mut output = data.to_owned();
+    output.push(0b10000000);
+    output.extend(std::iter::repeat_n(0u8, zero_bytes_to_add));
+    output.extend_from_slice(&((data.len() * 8) as u128).to_be_bytes());
+
+    debug_assert_eq!((output.len() * 8) % 1024, 0);
+
+    output
+}
+
+fn parse_blocks(data: &[u8]) -> Vec<Block> {
+    assert_eq!(data.len() % BLOCK_BYTES, 0);
+
+    let num_blocks = data.len() / BLOCK_BYTES;
+    let mut output = Vec::with_capacity(num_blocks);
+
+    for b in 0..num_blocks {
+        let mut block = [0; BLOCK_WORDS];
+        for (w, word) in block.iter_mut().enumerate() {
+            let offset = b * BLOCK_BYTES + w * WORD_BYTES;
+            let bytes: [u8; WORD_BYTES] = data[offset..offset + WORD_BYTES].try_into().unwrap();
+            *word = Word::from_be_bytes(bytes);
+        }
+        output.push(block);
+    }
+
+    output
+}
+
+fn ch(x: Word, y: Word, z: Word) -> Word {
+    (x & y) ^ (!x & z)
+}
+
+fn maj(x: Word, y: Word, z: Word) -> Word {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+fn bs0(x: Word) -> Word {
+    x.rotate_right(28) ^ x.rotate_right(34) ^ x.rotate_right(39)
+}
+
+fn bs1(x: Word) -> Word {
+    x.rotate_right(14) ^ x.rotate_right(18) ^ x.rotate_right(41)
+}
+
+fn s0(x: Word) -> Word {
+    x.rotate_right(1) ^ x.rotate_right(8) ^ x.wrapping_shr(7)
+}
+
+fn s1(x: Word) -> Word {
+    x.rotate_right(19) ^ x.rotate_right(61) ^ x.wrapping_shr(6)
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha512};
+
+    use super::*;
+
+    #[test]
+    fn equals_real_sha() {
+        // one block, and lengths around the point where the length field spills into a new block
+        for len in [3, 111, 112, 127, 128, 1000] {
+            let data: Vec<u8> = (0..=255).cycle().take(len).collect();
+            assert_eq!(&hash(&data), &*Sha512::digest(&data));
+        }
+    }
+
+    #[test]
+    fn streaming_equals_one_shot() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        for chunk_size in [1, 7, 127, 128, 129, 1000] {
+            let mut hasher = <super::Sha512 as HashFunction>::new();
+            for chunk in data.chunks(chunk_size) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), hash(&data));
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use crypto_bigint::{NonZero, RandomMod, Uint, U64};
+use serde::{Deserialize, Serialize};
+
+use super::drbg::ThreadDrbg;
+use super::elgamal::AlicePub;
+use super::group::{uint_from_be_bytes, with_group, Group, InvalidElement};
+use super::hash::HashFunction;
+use super::homomorphic::{rerandomize_with, Ciphertext};
+use super::sha256::{self, Sha256};
+
+// a verifiable shuffle, for mix networks: the mixer permutes and re-randomizes a list of
+// ciphertexts, and proves that the output holds the same plaintexts as the input without
+// revealing which output came from which input. passing the ciphertexts through several mixers
+// unlinks them from their senders as long as one of the mixers is honest.
+//
+// the proof is the commitment-consistent argument of Terelius and Wikstrom ("Proofs of
+// restricted shuffles"), in the form used by Verificatum and CHVote: the mixer commits to the
+// permutation matrix with Pedersen commitments, and then proves in zero knowledge that
+//
+// - the committed matrix is a permutation matrix, since every column sums to 1 and, for random
+//   challenges u, the product of the permuted u is the product of the u (checked through a chain
+//   of commitments to the partial products),
+// - and the output, raised to the permuted u, is the input raised to u, up to re-encryption.
+//
+// the challenges come from Fiat-Shamir. it takes about 9 exponentiations per ciphertext to
+// prove, and 11 to verify.
+
+const GENERATOR_SEED: &[u8] = b"titanium-rose shuffle generator";
+const CHALLENGE_TAG: &[u8] = b"titanium-rose shuffle";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ShuffleProof {
+    // Pedersen commitments to the columns of the permutation matrix
+    permutation: Vec<Vec<u8>>,
+    // commitments to the partial products of the permuted challenges
+    chain: Vec<Vec<u8>>,
+    challenge: Vec<u8>,
+    responses: [Vec<u8>; 4],
+    chain_responses: Vec<Vec<u8>>,
+    permuted_responses: Vec<Vec<u8>>,
+}
+
+type Pair<const LIMBS: usize> = (Uint<LIMBS>, Uint<LIMBS>);
+
+// Fisher-Yates
+fn random_permutation(n: usize) -> Vec<usize> {
+    let mut permutation: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        let bound = NonZero::new(U64::from_u64(i as u64 + 1)).unwrap();
+        let j = U64::random_mod(&mut ThreadDrbg, &bound).as_words()[0];
+        permutation.swap(i, j as usize);
+    }
+
+    permutation
+}
+
+// h and h_1, ..., h_n, which nobody knows any relation between
+fn generators<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    n: usize,
+) -> (Uint<LIMBS>, Vec<Uint<LIMBS>>) {
+    let mut generators = (0..=n as u32).map(|i| {
+        let seed = [GENERATOR_SEED, &i.to_be_bytes()].concat();
+        group.derive_generator(&seed, 0)
+    });
+    let h = generators.next().unwrap();
+
+    (h, generators.collect())
+}
+
+fn product<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    factors: impl IntoIterator<Item = Uint<LIMBS>>,
+) -> Uint<LIMBS> {
+    factors
+        .into_iter()
+        .fold(Uint::ONE, |acc, x| group.mul(&acc, &x))
+}
+
+// the product of bases[i]^exponents[i]
+fn multi_pow<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    bases: impl IntoIterator<Item = Uint<LIMBS>>,
+    exponents: &[Uint<LIMBS>],
+) -> Uint<LIMBS> {
+    product(
+        group,
+        bases
+            .into_iter()
+            .zip(exponents)
+            .map(|(x, e)| group.pow(&x, e)),
+    )
+}
+
+fn hash_to_exponent<const LIMBS: usize>(group: &Group<LIMBS>, hasher: Sha256) -> Uint<LIMBS> {
+    group.reduce_exponent(&uint_from_be_bytes(&hasher.finalize()))
+}
+
+// everything the challenges depend on, before the chain commitments
+fn statement<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    y: &Uint<LIMBS>,
+    input: &[Pair<LIMBS>],
+    output: &[Pair<LIMBS>],
+    permutation: &[Uint<LIMBS>],
+) -> sha256::Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(CHALLENGE_TAG);
+    hasher.update(group.name().as_bytes());
+    hasher.update(&group.encode(y));
+    for (c1, c2) in input.iter().chain(output) {
+        hasher.update(&group.encode(c1));
+        hasher.update(&group.encode(c2));
+    }
+    for c in permutation {
+        hasher.update(&group.encode(c));
+    }
+
+    hasher.finalize()
+}
+
+// the challenges u_i, one per ciphertext
+fn challenges<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    statement: &sha256::Digest,
+    n: usize,
+) -> Vec<Uint<LIMBS>> {
+    (0..n as u32)
+        .map(|i| {
+            let mut hasher = Sha256::new();
+            hasher.update(statement);
+            hasher.update(&i.to_be_bytes());
+            hash_to_exponent(group, hasher)
+        })
+        .collect()
+}
+
+fn final_challenge<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    statement: &sha256::Digest,
+    chain: &[Uint<LIMBS>],
+    commitments: &[Uint<LIMBS>],
+) -> Uint<LIMBS> {
+    let mut hasher = Sha256::new();
+    hasher.update(statement);
+    for x in chain.iter().chain(commitments) {
+        hasher.update(&group.encode(x));
+    }
+
+    hash_to_exponent(group, hasher)
+}
+
+// permutes and re-randomizes the ciphertexts, and proves that it was done correctly
+pub fn shuffle(
+    public: &AlicePub,
+    ciphertexts: &[Ciphertext],
+) -> Result<(Vec<Ciphertext>, ShuffleProof), InvalidElement> {
+    let group_id = public.group();
+    if ciphertexts.iter().any(|c| c.group() != group_id) {
+        return Err(InvalidElement::GroupMismatch);
+    }
+
+    with_group!(group_id, |group| {
+        // public keys are validated when they're created
+        let y = group.decode(public.encoded()).unwrap();
+        let n = ciphertexts.len();
+        let input: Vec<_> = ciphertexts.iter().map(|c| c.elements(group)).collect();
+        let new_exponents = |n| {
+            (0..n)
+                .map(|_| group.generate_exponent())
+                .collect::<Vec<_>>()
+        };
+
+        // output i is a re-encryption of input permutation[i]
+        let permutation = random_permutation(n);
+        let randomness = new_exponents(n);
+        let output: Vec<_> = permutation
+            .iter()
+            .zip(&randomness)
+            .map(|(&j, r)| rerandomize_with(group, &y, &input[j], r))
+            .collect();
+
+        // column j of the permutation matrix has its 1 in row i, so it's committed to as
+        // g^r_j * h_i
+        let (h, hs) = generators(group, n);
+        let commitment_randomness = new_exponents(n);
+        let mut commitments = vec![Uint::ZERO; n];
+        for (i, &j) in permutation.iter().enumerate() {
+            commitments[j] = group.mul(&group.pow_g(&commitment_randomness[j]), &hs[i]);
+        }
+
+        let statement = statement(group, &y, &input, &output, &commitments);
+        let u = challenges(group, &statement, n);
+        let permuted_u: Vec<_> = permutation.iter().map(|&j| u[j]).collect();
+
+        // chain[i] = g^chain_randomness[i] * chain[i - 1]^permuted_u[i], starting from h
+        let chain_randomness = new_exponents(n);
+        let chain: Vec<_> = chain_randomness
+            .iter()
+            .zip(&permuted_u)
+            .scan(h, |previous, (r, u)| {
+                *previous = group.mul(&group.pow_g(r), &group.pow(previous, u));
+                Some(*previous)
+            })
+            .collect();
+
+        let w = new_exponents(4);
+        let chain_w = new_exponents(n);
+        let permuted_w = new_exponents(n);
+        let mut commitments_t = vec![
+            group.pow_g(&w[0]),
+            group.pow_g(&w[1]),
+            group.mul(
+                &group.pow_g(&w[2]),
+                &multi_pow(group, hs.clone(), &permuted_w),
+            ),
+            group.mul(
+                &group.inv_pow(&y, &w[3]),
+                &multi_pow(group, output.iter().map(|c| c.1), &permuted_w),
+            ),
+            group.mul(
+                &group.inv_pow(group.g(), &w[3]),
+                &multi_pow(group, output.iter().map(|c| c.0), &permuted_w),
+            ),
+        ];
+        let previous = std::iter::once(&h).chain(&chain);
+        for ((w, w_prime), previous) in chain_w.iter().zip(&permuted_w).zip(previous) {
+            commitments_t.push(group.mul(&group.pow_g(w), &group.pow(previous, w_prime)));
+        }
+        let e = final_challenge(group, &statement, &chain, &commitments_t);
+
+        // the secrets behind each of the commitments above. v[i] is the product of the permuted
+        // challenges after i, which chain[n - 1] raises chain_randomness[i] to.
+        let mut v = vec![Uint::ONE; n];
+        for i in (1..n).rev() {
+            v[i - 1] = group.mul_exponents(&permuted_u[i], &v[i]);
+        }
+        let dot = |a: &[Uint<_>], b: &[Uint<_>]| {
+            a.iter().zip(b).fold(Uint::ZERO, |acc, (a, b)| {
+                group.add_exponents(&acc, &group.mul_exponents(a, b))
+            })
+        };
+        let secrets = [
+            commitment_randomness
+                .iter()
+                .fold(Uint::ZERO, |acc, r| group.add_exponents(&acc, r)),
+            dot(&chain_randomness, &v),
+            dot(&commitment_randomness, &u),
+            dot(&randomness, &permuted_u),
+        ];
+
+        let respond = |w: &[Uint<_>], secrets: &[Uint<_>]| {
+            w.iter()
+                .zip(secrets)
+                .map(|(w, x)| group.encode(&group.add_exponents(w, &group.mul_exponents(&e, x))))
+                .collect::<Vec<_>>()
+        };
+        let encode_all = |xs: &[Uint<_>]| xs.iter().map(|x| group.encode(x)).collect();
+
+        let proof = ShuffleProof {
+            permutation: encode_all(&commitments),
+            chain: encode_all(&chain),
+            challenge: group.encode(&e),
+            responses: respond(&w, &secrets).try_into().unwrap(),
+            chain_responses: respond(&chain_w, &chain_randomness),
+            permuted_responses: respond(&permuted_w, &permuted_u),
+        };
+        let output = output
+            .iter()
+            .map(|c| Ciphertext::from_elements(group_id, group, c))
+            .collect();
+
+        Ok((output, proof))
+    })
+}
+
+impl ShuffleProof {
+    // checks that `output` is a permutation of re-encryptions of `input`
+    pub fn verify(&self, public: &AlicePub, input: &[Ciphertext], output: &[Ciphertext]) -> bool {
+        let group_id = public.group();
+        let n = input.len();
+        if input.iter().chain(output).any(|c| c.group() != group_id)
+            || [
+                output.len(),
+                self.permutation.len(),
+                self.chain.len(),
+                self.chain_responses.len(),
+                self.permuted_responses.len(),
+            ]
+            .iter()
+            .any(|&len| len != n)
+        {
+            return false;
+        }
+
+        with_group!(group_id, |group| {
+            let elements = |xs: &[Vec<u8>]| -> Option<Vec<_>> {
+                xs.iter()
+                    .map(|x| {
+                        let x = group.decode(x).ok()?;
+                        group.check_subgroup(&x).ok().map(|_| x)
+                    })
+                    .collect()
+            };
+            let exponents = |xs: &[Vec<u8>]| -> Option<Vec<_>> {
+                xs.iter()
+                    .map(|x| group.decode(x).ok().filter(|x| x < group.q()))
+                    .collect()
+            };
+            let (Some(commitments), Some(chain), Some(e), Some(s), Some(chain_s), Some(permuted_s)) = (
+                elements(&self.permutation),
+                elements(&self.chain),
+                exponents(std::slice::from_ref(&self.challenge)),
+                exponents(&self.responses),
+                exponents(&self.chain_responses),
+                exponents(&self.permuted_responses),
+            ) else {
+                return false;
+            };
+            let e = e[0];
+
+            let y = group.decode(public.encoded()).unwrap();
+            let input: Vec<_> = input.iter().map(|c| c.elements(group)).collect();
+            let output: Vec<_> = output.iter().map(|c| c.elements(group)).collect();
+            let (h, hs) = generators(group, n);
+
+            let statement = statement(group, &y, &input, &output, &commitments);
+            let u = challenges(group, &statement, n);
+
+            // what each commitment of the prover commits to: the sum of the permutation
+            // commitment randomness, the last link of the chain, the permuted challenges, and
+            // the input raised to the challenges
+            let sum = group.mul(
+                &product(group, commitments.iter().copied()),
+                &group.inv_pow(&product(group, hs.iter().copied()), &Uint::ONE),
+            );
+            let u_product = u
+                .iter()
+                .fold(Uint::ONE, |acc, u| group.mul_exponents(&acc, u));
+            let last = group.mul(
+                chain.last().unwrap_or(&h),
+                &group.inv_pow(&group.pow(&h, &u_product), &Uint::ONE),
+            );
+            let permuted = multi_pow(group, commitments.iter().copied(), &u);
+            let input_c2 = multi_pow(group, input.iter().map(|c| c.1), &u);
+            let input_c1 = multi_pow(group, input.iter().map(|c| c.0), &u);
+
+            let mut commitments_t = vec![
+                group.mul(&group.pow_g(&s[0]), &group.inv_pow(&sum, &e)),
+                group.mul(&group.pow_g(&s[1]), &group.inv_pow(&last, &e)),
+                product(
+                    group,
+                    [
+                        group.pow_g(&s[2]),
+                        multi_pow(group, hs, &permuted_s),
+                        group.inv_pow(&permuted, &e),
+                    ],
+                ),
+                product(
+                    group,
+                    [
+                        group.inv_pow(&y, &s[3]),
+                        multi_pow(group, output.iter().map(|c| c.1), &permuted_s),
+                        group.inv_pow(&input_c2, &e),
+                    ],
+                ),
+                product(
+                    group,
+                    [
+                        group.inv_pow(group.g(), &s[3]),
+                        multi_pow(group, output.iter().map(|c| c.0), &permuted_s),
+                        group.inv_pow(&input_c1, &e),
+                    ],
+                ),
+            ];
+            let previous = std::iter::once(&h).chain(&chain);
+            for (((s, s_prime), previous), current) in
+                chain_s.iter().zip(&permuted_s).zip(previous).zip(&chain)
+            {
+                commitments_t.push(product(
+                    group,
+                    [
+                        group.pow_g(s),
+                        group.pow(previous, s_prime),
+                        group.inv_pow(current, &e),
+                    ],
+                ));
+            }
+
+            final_challenge(group, &statement, &chain, &commitments_t) == e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::elgamal::Alice;
+    use crate::crypto::group::GroupId;
+    use crate::crypto::homomorphic::{decrypt, encrypt};
+
+    fn decrypt_sorted(alice: &Alice, ciphertexts: &[Ciphertext]) -> Vec<u64> {
+        let mut plaintexts: Vec<_> = ciphertexts
+            .iter()
+            .map(|c| decrypt(alice, c, 10).unwrap().unwrap())
+            .collect();
+        plaintexts.sort();
+        plaintexts
+    }
+
+    #[test]
+    fn it_works() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let public = alice.get_public();
+        let input: Vec<_> = [3, 1, 4, 1, 5].map(|m| encrypt(public, m)).to_vec();
+
+        // two mixers in a row
+        let (middle, first) = shuffle(public, &input).unwrap();
+        let (output, second) = shuffle(public, &middle).unwrap();
+        assert!(first.verify(public, &input, &middle));
+        assert!(second.verify(public, &middle, &output));
+        assert!(output.iter().all(|c| !input.contains(c)));
+        assert_eq!(decrypt_sorted(&alice, &output), [1, 1, 3, 4, 5]);
+
+        let bytes = bincode::serialize(&first).unwrap();
+        let proof: ShuffleProof = bincode::deserialize(&bytes).unwrap();
+        assert!(proof.verify(public, &input, &middle));
+
+        let (output, proof) = shuffle(public, &[]).unwrap();
+        assert!(output.is_empty());
+        assert!(proof.verify(public, &[], &[]));
+    }
+
+    #[test]
+    fn rejects_invalid_shuffles() {
+        let alice = Alice::generate(GroupId::Ffdhe3072);
+        let public = alice.get_public();
+        let input: Vec<_> = [0, 1, 2].map(|m| encrypt(public, m)).to_vec();
+        let (output, proof) = shuffle(public, &input).unwrap();
+
+        // a mixer that swaps in a ciphertext of its own
+        let mut replaced = output.clone();
+        replaced[0] = encrypt(public, 2);
+        assert!(!proof.verify(public, &input, &replaced));
+
+        // or drops one, or reorders the output after proving
+        assert!(!proof.verify(public, &input, &output[..2]));
+        let mut reordered = output.clone();
+        reordered.swap(0, 1);
+        assert!(!proof.verify(public, &input, &reordered));
+
+        let mut tampered = proof.clone();
+        tampered.responses[3] = tampered.responses[2].clone();
+        assert!(!tampered.verify(public, &input, &output));
+
+        let other = Alice::generate(GroupId::Ffdhe3072);
+        assert!(!proof.verify(other.get_public(), &input, &output));
+    }
+}
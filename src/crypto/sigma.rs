@@ -0,0 +1,308 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::dh::{DhPublic, KeyPair};
+use super::group::{GroupId, InvalidElement};
+use super::hkdf;
+use super::hmac::{self, hmac};
+use super::schnorr::{Signature, SigningKey, VerifyingKey};
+use super::sha256::{self, DIGEST_BYTES};
+use super::SymmetricKey;
+
+// the SIGMA authenticated key exchange (Krawczyk, "SIGMA: the 'SIGn-and-MAc' approach to
+// authenticated Diffie-Hellman"). on top of an ephemeral Diffie-Hellman exchange, each side signs
+// both public values with its long-term identity key, and MACs its identity under a key derived
+// from the shared secret:
+//
+//   initiator -> responder: g^x
+//   responder -> initiator: g^y, B, sig_B(g^x, g^y), mac_km(B)
+//   initiator -> responder: A, sig_A(g^y, g^x), mac_km(A)
+//
+// the signature ties the identity to this exchange, and the MAC shows that whoever holds the
+// identity also knows the shared secret, so a man-in-the-middle can't pass a peer's signature off
+// as their own.
+
+const SALT: &[u8] = b"titanium-rose sigma v1";
+const MAC_KEY_INFO: &[u8] = b"titanium-rose sigma mac key";
+const SESSION_KEY_INFO: &[u8] = b"titanium-rose sigma session key";
+
+// everything signed or MACed starts with the role of its author, so that nothing can be reflected
+// back to the side that produced it
+const INITIATOR: &[u8] = b"initiator";
+const RESPONDER: &[u8] = b"responder";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HandshakeError {
+    InvalidElement(InvalidElement),
+    BadSignature,
+    BadMac,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidElement(error) => error.fmt(f),
+            Self::BadSignature => write!(f, "the peer's signature is invalid"),
+            Self::BadMac => write!(f, "the peer's identity is not bound to the shared key"),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+impl From<InvalidElement> for HandshakeError {
+    fn from(error: InvalidElement) -> Self {
+        Self::InvalidElement(error)
+    }
+}
+
+// the second message
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Response {
+    public: DhPublic,
+    identity: VerifyingKey,
+    signature: Signature,
+    mac: sha256::Digest,
+}
+
+impl Response {
+    pub fn group(&self) -> GroupId {
+        self.public.group()
+    }
+}
+
+// the third message
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Finish {
+    identity: VerifyingKey,
+    signature: Signature,
+    mac: sha256::Digest,
+}
+
+// the result of a completed handshake: a key that only the verified peer shares
+#[derive(Clone)]
+pub struct Session {
+    pub key: SymmetricKey,
+    pub peer: VerifyingKey,
+}
+
+struct Keys {
+    mac: hmac::Key,
+    session: SymmetricKey,
+}
+
+fn derive_keys(shared: &[u8], initiator: &DhPublic, responder: &DhPublic) -> Keys {
+    let ikm = [shared, initiator.encoded(), responder.encoded()].concat();
+    let prk = hkdf::extract(SALT, &ikm);
+
+    let mut mac = [0u8; hmac::KEY_BYTES];
+    hkdf::expand(&prk, MAC_KEY_INFO, &mut mac);
+    let mut session = [0u8; DIGEST_BYTES];
+    hkdf::expand(&prk, SESSION_KEY_INFO, &mut session);
+
+    Keys {
+        mac: hmac::Key(mac),
+        session: SymmetricKey::from_shared_secret(&session),
+    }
+}
+
+// the peer's public value followed by the author's own
+fn signed_data(role: &[u8], own: &DhPublic, peer: &DhPublic) -> Vec<u8> {
+    let group = own.group().name().as_bytes();
+    [role, group, peer.encoded(), own.encoded()].concat()
+}
+
+fn identity_mac(keys: &Keys, role: &[u8], identity: &VerifyingKey) -> sha256::Digest {
+    let group = identity.group().name().as_bytes();
+    hmac(&keys.mac, &[role, group, identity.encoded()].concat())
+}
+
+fn check_peer(
+    keys: &Keys,
+    role: &[u8],
+    signed: &[u8],
+    identity: &VerifyingKey,
+    signature: &Signature,
+    mac: &sha256::Digest,
+) -> Result<(), HandshakeError> {
+    if !identity.verify(signed, signature) {
+        return Err(HandshakeError::BadSignature);
+    }
+    if identity_mac(keys, role, identity) != *mac {
+        return Err(HandshakeError::BadMac);
+    }
+
+    Ok(())
+}
+
+// the side that starts the exchange, i.e. Alice
+#[derive(Clone)]
+pub struct Initiator {
+    identity: SigningKey,
+    pair: KeyPair,
+}
+
+impl Initiator {
+    pub fn start(identity: &SigningKey, group_id: GroupId) -> Self {
+        Self {
+            identity: identity.clone(),
+            pair: KeyPair::generate(group_id),
+        }
+    }
+
+    pub fn get_public(&self) -> &DhPublic {
+        &self.pair.public
+    }
+
+    // checks the responder's identity, and produces the message that proves ours
+    pub fn finish(&self, response: &Response) -> Result<(Finish, Session), HandshakeError> {
+        let ours = &self.pair.public;
+        let shared = self.pair.agree(&response.public)?;
+        let keys = derive_keys(&shared, ours, &response.public);
+
+        check_peer(
+            &keys,
+            RESPONDER,
+            &signed_data(RESPONDER, &response.public, ours),
+            &response.identity,
+            &response.signature,
+            &response.mac,
+        )?;
+
+        let identity = self.identity.verifying_key();
+        let finish = Finish {
+            identity: identity.clone(),
+            signature: self
+                .identity
+                .sign(&signed_data(INITIATOR, ours, &response.public)),
+            mac: identity_mac(&keys, INITIATOR, identity),
+        };
+        let session = Session {
+            key: keys.session,
+            peer: response.identity.clone(),
+        };
+
+        Ok((finish, session))
+    }
+}
+
+// the side that answers, i.e. Bob
+pub struct Responder {
+    initiator: DhPublic,
+    public: DhPublic,
+    keys: Keys,
+}
+
+impl Responder {
+    pub fn respond(identity: &SigningKey, initiator: &DhPublic) -> (Self, Response) {
+        let pair = KeyPair::generate(initiator.group());
+        // the initiator's public value was validated, and ours is in the same group
+        let shared = pair.agree(initiator).unwrap();
+        let keys = derive_keys(&shared, initiator, &pair.public);
+
+        let response = Response {
+            public: pair.public.clone(),
+            identity: identity.verifying_key().clone(),
+            signature: identity.sign(&signed_data(RESPONDER, &pair.public, initiator)),
+            mac: identity_mac(&keys, RESPONDER, identity.verifying_key()),
+        };
+        let responder = Self {
+            initiator: initiator.clone(),
+            public: pair.public,
+            keys,
+        };
+
+        (responder, response)
+    }
+
+    pub fn finish(&self, finish: &Finish) -> Result<Session, HandshakeError> {
+        check_peer(
+            &self.keys,
+            INITIATOR,
+            &signed_data(INITIATOR, &self.initiator, &self.public),
+            &finish.identity,
+            &finish.signature,
+            &finish.mac,
+        )?;
+
+        Ok(Session {
+            key: self.keys.session.clone(),
+            peer: finish.identity.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identities() -> (SigningKey, SigningKey) {
+        (
+            SigningKey::generate(GroupId::Ffdhe3072),
+            SigningKey::generate(GroupId::Ffdhe3072),
+        )
+    }
+
+    #[test]
+    fn it_works() {
+        let (alice, bob) = identities();
+        let initiator = Initiator::start(&alice, GroupId::Ffdhe3072);
+        let (responder, response) = Responder::respond(&bob, initiator.get_public());
+
+        let (finish, alice_session) = initiator.finish(&response).unwrap();
+        let bob_session = responder.finish(&finish).unwrap();
+
+        assert_eq!(alice_session.key, bob_session.key);
+        assert_eq!(&alice_session.peer, bob.verifying_key());
+        assert_eq!(&bob_session.peer, alice.verifying_key());
+    }
+
+    #[test]
+    fn detects_man_in_the_middle() {
+        let (alice, bob) = identities();
+        let initiator = Initiator::start(&alice, GroupId::Ffdhe3072);
+        let (_, response) = Responder::respond(&bob, initiator.get_public());
+
+        // Mallory answers with her own value but keeps Bob's identity and signature
+        let mallory = Initiator::start(&alice, GroupId::Ffdhe3072);
+        let forged = Response {
+            public: mallory.get_public().clone(),
+            ..response.clone()
+        };
+        assert_eq!(
+            initiator.finish(&forged).err(),
+            Some(HandshakeError::BadSignature)
+        );
+
+        // or relays Bob's response to an exchange of her own
+        let (mallory_responder, _) = Responder::respond(&bob, mallory.get_public());
+        let (finish, _) = initiator.finish(&response).unwrap();
+        assert_eq!(
+            mallory_responder.finish(&finish).err(),
+            Some(HandshakeError::BadSignature)
+        );
+
+        let mut tampered = response.clone();
+        tampered.mac[0] ^= 1;
+        assert_eq!(
+            initiator.finish(&tampered).err(),
+            Some(HandshakeError::BadMac)
+        );
+    }
+
+    #[test]
+    fn messages_serialize() {
+        let (alice, bob) = identities();
+        let initiator = Initiator::start(&alice, GroupId::Ffdhe3072);
+        let (responder, response) = Responder::respond(&bob, initiator.get_public());
+
+        let bytes = bincode::serialize(&response).unwrap();
+        let response: Response = bincode::deserialize(&bytes).unwrap();
+        let (finish, _) = initiator.finish(&response).unwrap();
+
+        let bytes = bincode::serialize(&finish).unwrap();
+        let finish: Finish = bincode::deserialize(&bytes).unwrap();
+        assert!(responder.finish(&finish).is_ok());
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crypto_bigint::Uint;
+use serde::{Deserialize, Serialize};
+
+use super::group::{
+    uint_from_be_bytes, with_group, Group, GroupId, InvalidElement, GENERATOR_SEED,
+};
+use super::hkdf;
+use super::hmac::{self, hmac};
+use super::pbkdf2::pbkdf2;
+use super::sha256::{self, Sha256, DIGEST_BYTES};
+use super::{SymmetricKey, DEFAULT_PASSWORD_ITERATIONS};
+
+// SPAKE2 (RFC 9382), a key exchange authenticated by a short code that both sides type in. the
+// code is hashed to an exponent w, and each side blinds its Diffie-Hellman value with a fixed
+// element raised to w:
+//
+//   initiator -> responder: pA = g^x M^w
+//   responder -> initiator: pB = g^y N^w, cB
+//   initiator -> responder: cA
+//
+// both sides then compute K = g^xy, and confirm with a MAC over the transcript. a passive
+// eavesdropper learns nothing about the code, and an active attacker gets to test one guess per
+// exchange, which the confirmation MAC turns into a failed handshake.
+
+const PASSWORD_SALT: &[u8] = b"titanium-rose spake2";
+const CONFIRMATION_INFO: &[u8] = b"ConfirmationKeys";
+
+// the identities of RFC 9382. they're the same for everyone, so only the role is bound.
+const INITIATOR: &[u8] = b"titanium-rose initiator";
+const RESPONDER: &[u8] = b"titanium-rose responder";
+
+// w is taken from this many bytes of PBKDF2 output, which is plenty for a code that is much
+// shorter than that
+const PASSWORD_HASH_BYTES: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HandshakeError {
+    InvalidElement(InvalidElement),
+    // the other side typed a different code, or someone in between tried to guess it
+    BadConfirmation,
+    // the initiator already checked a response, and doesn't get to check another one
+    AlreadyFinished,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidElement(error) => error.fmt(f),
+            Self::BadConfirmation => write!(f, "the other side doesn't know the same code"),
+            Self::AlreadyFinished => write!(f, "this exchange is over, start a new one"),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+impl From<InvalidElement> for HandshakeError {
+    fn from(error: InvalidElement) -> Self {
+        Self::InvalidElement(error)
+    }
+}
+
+// M and N, derived like every other generator, so that nobody knows their discrete logarithms
+fn m<const LIMBS: usize>(group: &Group<LIMBS>) -> Uint<LIMBS> {
+    group.derive_generator(GENERATOR_SEED, 2)
+}
+
+fn n<const LIMBS: usize>(group: &Group<LIMBS>) -> Uint<LIMBS> {
+    group.derive_generator(GENERATOR_SEED, 3)
+}
+
+// w, the code hashed with PBKDF2. the hash doesn't stop online guessing, which the protocol
+// limits anyway, but it slows down whoever gets hold of a transcript together with a session key.
+fn password_exponent<const LIMBS: usize>(group: &Group<LIMBS>, code: &[u8]) -> Uint<LIMBS> {
+    let salt = [PASSWORD_SALT, group.name().as_bytes()].concat();
+    let mut hash = [0u8; PASSWORD_HASH_BYTES];
+    pbkdf2(code, &salt, DEFAULT_PASSWORD_ITERATIONS, &mut hash);
+
+    group.reduce_exponent(&uint_from_be_bytes(&hash))
+}
+
+// a blinded public value, pA or pB, sent together with its group
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "(GroupId, Vec<u8>)")]
+pub struct Share(GroupId, Vec<u8>);
+
+impl TryFrom<(GroupId, Vec<u8>)> for Share {
+    type Error = InvalidElement;
+
+    fn try_from((group_id, share): (GroupId, Vec<u8>)) -> Result<Self, Self::Error> {
+        with_group!(group_id, |group| {
+            group.check_subgroup(&group.decode(&share)?)
+        })?;
+        Ok(Self(group_id, share))
+    }
+}
+
+impl Share {
+    pub fn group(&self) -> GroupId {
+        self.0
+    }
+}
+
+// the second message
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Response {
+    share: Share,
+    confirmation: sha256::Digest,
+}
+
+impl Response {
+    pub fn group(&self) -> GroupId {
+        self.share.group()
+    }
+}
+
+// the third message
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Confirmation(sha256::Digest);
+
+struct Keys {
+    session: SymmetricKey,
+    initiator: sha256::Digest,
+    responder: sha256::Digest,
+}
+
+// TT of RFC 9382: every field prefixed with its length as 8 little-endian bytes
+fn transcript(fields: &[&[u8]]) -> Vec<u8> {
+    let mut transcript = Vec::new();
+    for field in fields {
+        transcript.extend_from_slice(&(field.len() as u64).to_le_bytes());
+        transcript.extend_from_slice(field);
+    }
+
+    transcript
+}
+
+// Ke || Ka = H(TT), and KcA || KcB = HKDF(Ka, "ConfirmationKeys"). the session key is Ke, and the
+// confirmations are the MACs of TT under KcA and KcB.
+fn key_schedule(transcript: &[u8]) -> Keys {
+    let hash = sha256::hash(transcript);
+    let (ke, ka) = hash.split_at(DIGEST_BYTES / 2);
+
+    let mut kc = [0u8; DIGEST_BYTES];
+    hkdf::hkdf(&[], ka, CONFIRMATION_INFO, &mut kc);
+    let (kca, kcb) = kc.split_at(DIGEST_BYTES / 2);
+
+    Keys {
+        session: SymmetricKey::from_shared_secret(ke),
+        initiator: hmac(&hmac::Key::<Sha256>::from_bytes(kca), transcript),
+        responder: hmac(&hmac::Key::<Sha256>::from_bytes(kcb), transcript),
+    }
+}
+
+fn derive_keys<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    initiator: &Uint<LIMBS>,
+    responder: &Uint<LIMBS>,
+    k: &Uint<LIMBS>,
+    w: &Uint<LIMBS>,
+) -> Result<Keys, InvalidElement> {
+    // only a peer who already knows w can force this
+    if k == &Uint::ONE {
+        return Err(InvalidElement::Trivial);
+    }
+
+    Ok(key_schedule(&transcript(&[
+        INITIATOR,
+        RESPONDER,
+        &group.encode(initiator),
+        &group.encode(responder),
+        &group.encode(k),
+        &group.encode(w),
+    ])))
+}
+
+// K = (peer / blind^w)^x, which is g^xy if both sides used the same w
+fn unblind<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    share: &Uint<LIMBS>,
+    blind: &Uint<LIMBS>,
+    w: &Uint<LIMBS>,
+    secret: &Uint<LIMBS>,
+) -> Uint<LIMBS> {
+    group.pow(&group.mul(share, &group.inv_pow(blind, w)), secret)
+}
+
+// the side that starts the exchange, i.e. Alice. every response tested against x and w is a
+// guess at the code, so only the first one is ever checked, by this initiator or any of its
+// clones.
+#[derive(Clone)]
+pub struct Initiator {
+    w: Vec<u8>,
+    secret: Vec<u8>,
+    share: Share,
+    finished: Arc<AtomicBool>,
+}
+
+impl Initiator {
+    pub fn start(code: &[u8], group_id: GroupId) -> Self {
+        with_group!(group_id, |group| {
+            let w = password_exponent(group, code);
+            let secret = group.generate_exponent();
+            let share = group.mul(&group.pow_g(&secret), &group.pow(&m(group), &w));
+
+            Self {
+                w: group.encode(&w),
+                secret: group.encode(&secret),
+                share: Share(group_id, group.encode(&share)),
+                finished: Arc::new(AtomicBool::new(false)),
+            }
+        })
+    }
+
+    pub fn get_share(&self) -> &Share {
+        &self.share
+    }
+
+    // checks that the responder used the same code, and produces the message that shows ours
+    pub fn finish(
+        &self,
+        response: &Response,
+    ) -> Result<(Confirmation, SymmetricKey), HandshakeError> {
+        if self.finished.swap(true, Ordering::SeqCst) {
+            return Err(HandshakeError::AlreadyFinished);
+        }
+
+        let group_id = self.share.group();
+        if response.group() != group_id {
+            return Err(InvalidElement::GroupMismatch.into());
+        }
+
+        let keys = with_group!(group_id, |group| {
+            let w = group.decode(&self.w)?;
+            let ours = group.decode(&self.share.1)?;
+            let theirs = group.decode(&response.share.1)?;
+            let k = unblind(group, &theirs, &n(group), &w, &group.decode(&self.secret)?);
+            derive_keys(group, &ours, &theirs, &k, &w)
+        })?;
+
+        if keys.responder != response.confirmation {
+            return Err(HandshakeError::BadConfirmation);
+        }
+
+        Ok((Confirmation(keys.initiator), keys.session))
+    }
+}
+
+// the side that answers, i.e. Bob
+pub struct Responder {
+    confirmation: sha256::Digest,
+    session: SymmetricKey,
+}
+
+impl Responder {
+    pub fn respond(code: &[u8], initiator: &Share) -> Result<(Self, Response), HandshakeError> {
+        let group_id = initiator.group();
+        let (share, keys) = with_group!(group_id, |group| {
+            let w = password_exponent(group, code);
+            let secret = group.generate_exponent();
+            let share = group.mul(&group.pow_g(&secret), &group.pow(&n(group), &w));
+
+            let theirs = group.decode(&initiator.1)?;
+            let k = unblind(group, &theirs, &m(group), &w, &secret);
+            let keys = derive_keys(group, &theirs, &share, &k, &w)?;
+            Ok::<_, InvalidElement>((Share(group_id, group.encode(&share)), keys))
+        })?;
+
+        let response = Response {
+            share,
+            confirmation: keys.responder,
+        };
+        let responder = Self {
+            confirmation: keys.initiator,
+            session: keys.session,
+        };
+
+        Ok((responder, response))
+    }
+
+    pub fn finish(&self, confirmation: &Confirmation) -> Result<SymmetricKey, HandshakeError> {
+        if confirmation.0 != self.confirmation {
+            return Err(HandshakeError::BadConfirmation);
+        }
+
+        Ok(self.session.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let initiator = Initiator::start(b"1234-5678", GroupId::Ffdhe3072);
+        let (responder, response) =
+            Responder::respond(b"1234-5678", initiator.get_share()).unwrap();
+
+        let (confirmation, alice_key) = initiator.finish(&response).unwrap();
+        let bob_key = responder.finish(&confirmation).unwrap();
+        assert_eq!(alice_key, bob_key);
+    }
+
+    #[test]
+    fn rejects_wrong_code() {
+        let initiator = Initiator::start(b"1234-5678", GroupId::Ffdhe3072);
+        let (responder, response) =
+            Responder::respond(b"1234-5679", initiator.get_share()).unwrap();
+        assert_eq!(
+            initiator.finish(&response).err(),
+            Some(HandshakeError::BadConfirmation)
+        );
+
+        // the initiator is used up, so the right code doesn't help after a wrong guess
+        let (_, retry) = Responder::respond(b"1234-5678", initiator.get_share()).unwrap();
+        assert_eq!(
+            initiator.clone().finish(&retry).err(),
+            Some(HandshakeError::AlreadyFinished)
+        );
+
+        // and the responder's own MAC, reflected back, doesn't pass for the initiator's
+        let reflected = Confirmation(response.confirmation);
+        assert_eq!(
+            responder.finish(&reflected).err(),
+            Some(HandshakeError::BadConfirmation)
+        );
+    }
+
+    #[test]
+    fn messages_serialize() {
+        let initiator = Initiator::start(b"correct horse", GroupId::Ffdhe3072);
+        let bytes = bincode::serialize(initiator.get_share()).unwrap();
+        let share: Share = bincode::deserialize(&bytes).unwrap();
+        let (responder, response) = Responder::respond(b"correct horse", &share).unwrap();
+
+        let bytes = bincode::serialize(&response).unwrap();
+        let response: Response = bincode::deserialize(&bytes).unwrap();
+        let (confirmation, _) = initiator.finish(&response).unwrap();
+
+        let bytes = bincode::serialize(&confirmation).unwrap();
+        let confirmation: Confirmation = bincode::deserialize(&bytes).unwrap();
+        assert!(responder.finish(&confirmation).is_ok());
+
+        // the trivial element is rejected before any exponentiation with w
+        let trivial = bincode::serialize(&(GroupId::Ffdhe3072, {
+            let mut one = vec![0u8; GroupId::Ffdhe3072.bits() / 8];
+            *one.last_mut().unwrap() = 1;
+            one
+        }))
+        .unwrap();
+        assert!(bincode::deserialize::<Share>(&trivial).is_err());
+    }
+}
@@ -0,0 +1,555 @@
+use std::fmt;
+
+use crypto_bigint::Uint;
+use serde::{Deserialize, Serialize};
+
+use super::elgamal::{int_to_secret, AlicePub, BobEphemeral};
+use super::group::{uint_from_be_bytes, with_group, Group, GroupId, InvalidElement};
+use super::hash::HashFunction;
+use super::sha256::Sha256;
+use super::SymmetricKey;
+
+// threshold ElGamal: the decryption key is shared among n parties so that any t of them can
+// decrypt a BobEphemeral, while fewer learn nothing about it. there is no trusted dealer, the key
+// is created with Pedersen's distributed key generation: every party deals a share of a random
+// secret with Feldman's verifiable secret sharing, and the key is the sum of all of them.
+//
+// each party i picks a random polynomial f_i of degree t - 1, broadcasts the commitments
+// g^(coefficients of f_i) and privately sends f_i(j) to party j, who checks it against the
+// commitments. party j's share of the key is x_j = sum of f_i(j), and the public key is
+// y = product of g^f_i(0). this simple form lets the last party to deal bias y slightly, which
+// doesn't matter for encryption.
+//
+// parties are numbered from 1, since party j's share is a polynomial evaluated at j.
+
+const PROOF_TAG: &[u8] = b"titanium-rose partial decryption";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThresholdError {
+    InvalidElement(InvalidElement),
+    InvalidParameters,
+    // the dealings or shares aren't exactly one from every party
+    MissingDealings,
+    // a share didn't match its dealer's commitments
+    BadShare { dealer: u32 },
+    // a partial decryption's proof didn't verify
+    BadProof { party: u32 },
+    NotEnoughShares,
+}
+
+impl fmt::Display for ThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidElement(error) => error.fmt(f),
+            Self::InvalidParameters => write!(f, "invalid threshold or party number"),
+            Self::MissingDealings => write!(f, "expected one dealing and share from every party"),
+            Self::BadShare { dealer } => {
+                write!(
+                    f,
+                    "party {dealer} dealt a share that doesn't match its commitments"
+                )
+            }
+            Self::BadProof { party } => {
+                write!(f, "party {party}'s partial decryption has an invalid proof")
+            }
+            Self::NotEnoughShares => write!(f, "not enough partial decryptions"),
+        }
+    }
+}
+
+impl std::error::Error for ThresholdError {}
+
+impl From<InvalidElement> for ThresholdError {
+    fn from(error: InvalidElement) -> Self {
+        Self::InvalidElement(error)
+    }
+}
+
+// what a party broadcasts to everyone: g^a_k for every coefficient a_k of its polynomial
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Dealing {
+    from: u32,
+    commitments: Vec<Vec<u8>>,
+}
+
+// f_i(j), which party i sends to party j only
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Share {
+    from: u32,
+    to: u32,
+    value: Vec<u8>,
+}
+
+// a party during key generation
+pub struct Participant {
+    group: GroupId,
+    index: u32,
+    parties: u32,
+    coefficients: Vec<Vec<u8>>,
+}
+
+// f(x) = a_0 + a_1 * x + ... (mod q)
+fn evaluate<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    coefficients: &[Uint<LIMBS>],
+    x: u32,
+) -> Uint<LIMBS> {
+    let x = Uint::from_u32(x);
+    coefficients.iter().rev().fold(Uint::ZERO, |acc, a| {
+        group.add_exponents(&group.mul_exponents(&acc, &x), a)
+    })
+}
+
+// g^f(x), from the commitments g^a_k
+fn evaluate_in_exponent<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    commitments: &[Uint<LIMBS>],
+    x: u32,
+) -> Uint<LIMBS> {
+    let x = Uint::from_u32(x);
+    commitments
+        .iter()
+        .rev()
+        .fold(Uint::ONE, |acc, c| group.mul(&group.pow(&acc, &x), c))
+}
+
+impl Participant {
+    // party `index` of `parties`, any `threshold` of which will be able to decrypt
+    pub fn new(
+        group_id: GroupId,
+        index: u32,
+        threshold: u32,
+        parties: u32,
+    ) -> Result<Self, ThresholdError> {
+        if !(1..=parties).contains(&index) || !(1..=parties).contains(&threshold) {
+            return Err(ThresholdError::InvalidParameters);
+        }
+
+        let coefficients = with_group!(group_id, |group| {
+            (0..threshold)
+                .map(|_| group.encode(&group.generate_exponent()))
+                .collect()
+        });
+
+        Ok(Self {
+            group: group_id,
+            index,
+            parties,
+            coefficients,
+        })
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn dealing(&self) -> Dealing {
+        with_group!(self.group, |group| {
+            let commitments = self
+                .coefficients
+                .iter()
+                .map(|a| group.encode(&group.pow_g(&group.decode(a).unwrap())))
+                .collect();
+
+            Dealing {
+                from: self.index,
+                commitments,
+            }
+        })
+    }
+
+    pub fn share_for(&self, to: u32) -> Share {
+        with_group!(self.group, |group| {
+            let coefficients: Vec<_> = self
+                .coefficients
+                .iter()
+                .map(|a| group.decode(a).unwrap())
+                .collect();
+
+            Share {
+                from: self.index,
+                to,
+                value: group.encode(&evaluate(group, &coefficients, to)),
+            }
+        })
+    }
+
+    // checks everyone's shares for us against their dealings, and combines them into our share
+    // of the key. `dealings` has to include our own, and `shares` the one we dealt to ourselves.
+    pub fn finish(
+        &self,
+        dealings: &[Dealing],
+        shares: &[Share],
+    ) -> Result<KeyShare, ThresholdError> {
+        let threshold = self.coefficients.len();
+
+        let mut dealings: Vec<&Dealing> = dealings.iter().collect();
+        let mut shares: Vec<&Share> = shares.iter().filter(|s| s.to == self.index).collect();
+        dealings.sort_by_key(|d| d.from);
+        shares.sort_by_key(|s| s.from);
+        let expected = (1..=self.parties).collect::<Vec<_>>();
+        if dealings.iter().map(|d| d.from).ne(expected.iter().copied())
+            || shares.iter().map(|s| s.from).ne(expected.iter().copied())
+        {
+            return Err(ThresholdError::MissingDealings);
+        }
+
+        with_group!(self.group, |group| {
+            // sums of everyone's commitments, i.e. the commitments to the sum of the polynomials
+            let mut combined = vec![Uint::ONE; threshold];
+            let mut secret = Uint::ZERO;
+
+            for (dealing, share) in dealings.iter().zip(&shares) {
+                if dealing.commitments.len() != threshold {
+                    return Err(ThresholdError::InvalidParameters);
+                }
+                let commitments = dealing
+                    .commitments
+                    .iter()
+                    .map(|c| {
+                        let c = group.decode(c)?;
+                        group.check_subgroup(&c)?;
+                        Ok(c)
+                    })
+                    .collect::<Result<Vec<_>, InvalidElement>>()?;
+
+                let value = group.decode(&share.value)?;
+                if &value >= group.q()
+                    || group.pow_g(&value) != evaluate_in_exponent(group, &commitments, self.index)
+                {
+                    return Err(ThresholdError::BadShare {
+                        dealer: dealing.from,
+                    });
+                }
+
+                secret = group.add_exponents(&secret, &value);
+                for (sum, c) in combined.iter_mut().zip(&commitments) {
+                    *sum = group.mul(sum, c);
+                }
+            }
+
+            let verification_keys = (1..=self.parties)
+                .map(|j| group.encode(&evaluate_in_exponent(group, &combined, j)))
+                .collect();
+
+            let public = PublicKey {
+                key: AlicePub::try_from((self.group, group.encode(&combined[0])))?,
+                threshold: threshold as u32,
+                verification_keys,
+            };
+
+            Ok(KeyShare {
+                index: self.index,
+                secret: group.encode(&secret),
+                public,
+            })
+        })
+    }
+}
+
+// everything needed to encrypt to the group and to combine partial decryptions
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PublicKey {
+    key: AlicePub,
+    threshold: u32,
+    // g^x_j for every party j
+    verification_keys: Vec<Vec<u8>>,
+}
+
+// one party's share x_j of the decryption key
+pub struct KeyShare {
+    index: u32,
+    secret: Vec<u8>,
+    public: PublicKey,
+}
+
+// c1^x_j, with a Chaum-Pedersen proof that it uses the same exponent as the verification key
+// g^x_j
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PartialDecryption {
+    from: u32,
+    value: Vec<u8>,
+    challenge: Vec<u8>,
+    response: Vec<u8>,
+}
+
+fn proof_challenge<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    elements: [&Uint<LIMBS>; 6],
+) -> Uint<LIMBS> {
+    let mut hasher = Sha256::new();
+    hasher.update(PROOF_TAG);
+    hasher.update(group.name().as_bytes());
+    for x in elements {
+        hasher.update(&group.encode(x));
+    }
+
+    group.reduce_exponent(&uint_from_be_bytes(&hasher.finalize()))
+}
+
+impl KeyShare {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn public(&self) -> &PublicKey {
+        &self.public
+    }
+
+    pub fn partial_decrypt(&self, eph: &BobEphemeral) -> Result<PartialDecryption, ThresholdError> {
+        let group_id = self.public.key.group();
+        if eph.group() != group_id {
+            return Err(InvalidElement::GroupMismatch.into());
+        }
+
+        with_group!(group_id, |group| {
+            let x = group.decode(&self.secret)?;
+            let c1 = group.decode(eph.encoded().0)?;
+            let y = group.decode(&self.public.verification_keys[self.index as usize - 1])?;
+            let d = group.pow(&c1, &x);
+
+            // a Schnorr-style proof of knowledge of x for both bases at once
+            let w = group.generate_exponent();
+            let (a, b) = (group.pow_g(&w), group.pow(&c1, &w));
+            let e = proof_challenge(group, [group.g(), &y, &c1, &d, &a, &b]);
+            let z = group.add_exponents(&w, &group.mul_exponents(&e, &x));
+
+            Ok(PartialDecryption {
+                from: self.index,
+                value: group.encode(&d),
+                challenge: group.encode(&e),
+                response: group.encode(&z),
+            })
+        })
+    }
+}
+
+// the Lagrange coefficient of party j for interpolating at 0 from the parties in `indices`:
+// the product of m / (m - j) over every other m
+fn lagrange_at_zero<const LIMBS: usize>(
+    group: &Group<LIMBS>,
+    indices: &[u32],
+    j: u32,
+) -> Uint<LIMBS> {
+    let j_int = Uint::from_u32(j);
+    indices
+        .iter()
+        .filter(|&&m| m != j)
+        .fold(Uint::ONE, |acc, &m| {
+            let m = Uint::from_u32(m);
+            let denominator = group.sub_exponents(&m, &j_int);
+            let term = group.mul_exponents(&m, &group.invert_exponent(&denominator));
+            group.mul_exponents(&acc, &term)
+        })
+}
+
+impl PublicKey {
+    // what Bob encrypts to, exactly as for a single Alice
+    pub fn key(&self) -> &AlicePub {
+        &self.key
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    // checks the partial decryptions and combines the first `threshold` valid ones. invalid ones
+    // are skipped, so a party sending garbage can't stop the others from decrypting; if too few are
+    // left, the first invalid one is reported.
+    pub fn combine(
+        &self,
+        eph: &BobEphemeral,
+        partials: &[PartialDecryption],
+    ) -> Result<SymmetricKey, ThresholdError> {
+        let group_id = self.key.group();
+        if eph.group() != group_id {
+            return Err(InvalidElement::GroupMismatch.into());
+        }
+
+        with_group!(group_id, |group| {
+            let c1 = group.decode(eph.encoded().0)?;
+            let c2 = group.decode(eph.encoded().1)?;
+
+            let mut indices: Vec<u32> = Vec::new();
+            let mut values = Vec::new();
+            let mut first_error = None;
+            for partial in partials {
+                if indices.len() == self.threshold as usize {
+                    break;
+                }
+                if indices.contains(&partial.from) {
+                    continue;
+                }
+                match self.check_partial(group, &c1, partial) {
+                    Ok(d) => {
+                        indices.push(partial.from);
+                        values.push(d);
+                    }
+                    Err(error) => {
+                        first_error.get_or_insert(error);
+                    }
+                }
+            }
+            if indices.len() < self.threshold as usize {
+                return Err(first_error.unwrap_or(ThresholdError::NotEnoughShares));
+            }
+
+            // c1^x = product of d_j^lambda_j
+            let mut key = Uint::ONE;
+            for (d, &j) in values.iter().zip(&indices) {
+                key = group.mul(&key, &group.pow(d, &lagrange_at_zero(group, &indices, j)));
+            }
+
+            let secret = group.mul(&c2, &group.inv_pow(&key, &Uint::ONE));
+            Ok(SymmetricKey::from_shared_secret(&int_to_secret(&secret)))
+        })
+    }
+
+    // d_j = c1^x_j from a partial decryption, if its proof verifies
+    fn check_partial<const LIMBS: usize>(
+        &self,
+        group: &Group<LIMBS>,
+        c1: &Uint<LIMBS>,
+        partial: &PartialDecryption,
+    ) -> Result<Uint<LIMBS>, ThresholdError> {
+        let bad_proof = ThresholdError::BadProof {
+            party: partial.from,
+        };
+        let Some(y) = (partial.from as usize)
+            .checked_sub(1)
+            .and_then(|j| self.verification_keys.get(j))
+        else {
+            return Err(ThresholdError::InvalidParameters);
+        };
+        let y = group.decode(y)?;
+        let d = group.decode(&partial.value)?;
+        group.check_subgroup(&d)?;
+        let e = group.decode(&partial.challenge)?;
+        let z = group.decode(&partial.response)?;
+        if &e >= group.q() || &z >= group.q() {
+            return Err(bad_proof);
+        }
+
+        // the commitments are g^z * y^(-e) and c1^z * d^(-e)
+        let a = group.mul(&group.pow_g(&z), &group.inv_pow(&y, &e));
+        let b = group.mul(&group.pow(c1, &z), &group.inv_pow(&d, &e));
+        if proof_challenge(group, [group.g(), &y, c1, &d, &a, &b]) != e {
+            return Err(bad_proof);
+        }
+
+        Ok(d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::elgamal::Bob;
+
+    // runs the key generation for n parties in-process
+    fn generate(threshold: u32, parties: u32) -> Vec<KeyShare> {
+        let participants: Vec<Participant> = (1..=parties)
+            .map(|i| Participant::new(GroupId::Ffdhe3072, i, threshold, parties).unwrap())
+            .collect();
+        let dealings: Vec<Dealing> = participants.iter().map(Participant::dealing).collect();
+
+        participants
+            .iter()
+            .map(|p| {
+                let shares: Vec<Share> = participants
+                    .iter()
+                    .map(|q| q.share_for(p.index()))
+                    .collect();
+                p.finish(&dealings, &shares).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn any_threshold_of_parties_can_decrypt() {
+        let shares = generate(3, 5);
+        let public = shares[0].public();
+        assert!(shares.iter().all(|share| share.public() == public));
+
+        let bob = Bob::generate();
+        let eph = bob.encrypt_for_alice(public.key());
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let partials: Vec<_> = subset
+                .iter()
+                .map(|&i| shares[i].partial_decrypt(&eph).unwrap())
+                .collect();
+            assert_eq!(
+                public.combine(&eph, &partials).unwrap(),
+                bob.extract_shared_secret()
+            );
+        }
+
+        let partials: Vec<_> = shares[..2]
+            .iter()
+            .map(|share| share.partial_decrypt(&eph).unwrap())
+            .collect();
+        // the same party twice doesn't count
+        let repeated = [partials.clone(), partials.clone()].concat();
+        assert_eq!(
+            public.combine(&eph, &repeated).err(),
+            Some(ThresholdError::NotEnoughShares)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_partial_decryptions() {
+        let shares = generate(2, 3);
+        let public = shares[0].public();
+        let bob = Bob::generate();
+        let eph = bob.encrypt_for_alice(public.key());
+
+        let mut partials: Vec<_> = shares[..2]
+            .iter()
+            .map(|share| share.partial_decrypt(&eph).unwrap())
+            .collect();
+        // a valid element, but not c1^x_2
+        partials[1].value = partials[0].value.clone();
+        assert_eq!(
+            public.combine(&eph, &partials).err(),
+            Some(ThresholdError::BadProof { party: 2 })
+        );
+
+        // with enough valid ones as well, the bad one is skipped, even when it comes first
+        let mut partials = vec![
+            partials[1].clone(),
+            shares[2].partial_decrypt(&eph).unwrap(),
+        ];
+        partials.push(shares[1].partial_decrypt(&eph).unwrap());
+        assert_eq!(
+            public.combine(&eph, &partials).unwrap(),
+            bob.extract_shared_secret()
+        );
+    }
+
+    #[test]
+    fn rejects_bad_shares() {
+        let participants: Vec<Participant> = (1..=3)
+            .map(|i| Participant::new(GroupId::Ffdhe3072, i, 2, 3).unwrap())
+            .collect();
+        let dealings: Vec<Dealing> = participants.iter().map(Participant::dealing).collect();
+
+        let mut shares: Vec<Share> = participants.iter().map(|q| q.share_for(1)).collect();
+        // party 3 sends party 1 the share meant for party 2
+        shares[2] = participants[2].share_for(2);
+        shares[2].to = 1;
+        assert_eq!(
+            participants[0].finish(&dealings, &shares).err(),
+            Some(ThresholdError::BadShare { dealer: 3 })
+        );
+
+        assert_eq!(
+            participants[0].finish(&dealings[..2], &shares).err(),
+            Some(ThresholdError::MissingDealings)
+        );
+        assert_eq!(
+            Participant::new(GroupId::Ffdhe3072, 4, 2, 3).err(),
+            Some(ThresholdError::InvalidParameters)
+        );
+    }
+}
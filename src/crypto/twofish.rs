@@ -1,4 +1,6 @@
-use rand_core::{OsRng, RngCore};
+use rand_core::RngCore;
+
+use super::drbg::ThreadDrbg;
 
 pub const KEY_BYTES: usize = 256 / 8;
 pub const BLOCK_BYTES: usize = 128 / 8;
@@ -76,7 +78,7 @@ type Word = u32;
 impl Key {
     pub fn generate() -> Self {
         let mut data = [0u8; KEY_BYTES];
-        OsRng.fill_bytes(&mut data);
+        ThreadDrbg.fill_bytes(&mut data);
 
         Self(data)
     }
@@ -0,0 +1,268 @@
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::drbg::ThreadDrbg;
+use super::field25519::Fe;
+use super::group::InvalidElement;
+use super::SymmetricKey;
+
+// X25519 (RFC 7748): Diffie-Hellman on the Montgomery curve v^2 = u^3 + 486662 u^2 + u over
+// GF(2^255 - 19). public values are 32-byte u-coordinates, and a scalar multiplication is a few
+// thousand field operations, compared to the multi-kilobyte numbers of the finite field groups.
+
+pub const KEY_BYTES: usize = 32;
+
+pub const BASEPOINT: [u8; KEY_BYTES] = {
+    let mut u = [0u8; KEY_BYTES];
+    u[0] = 9;
+    u
+};
+
+// (486662 - 2) / 4
+const A24: Fe = Fe::from_u64(121665);
+
+// the Montgomery ladder from RFC 7748, section 5, over all 255 bits of k as given
+fn ladder(k: &[u8; KEY_BYTES], u: &Fe) -> Fe {
+    let x1 = *u;
+    let (mut x2, mut z2) = (Fe::ONE, Fe::ZERO);
+    let (mut x3, mut z3) = (*u, Fe::ONE);
+    let mut swap = 0;
+
+    for t in (0..255).rev() {
+        let bit = u64::from((k[t / 8] >> (t % 8)) & 1);
+        swap ^= bit;
+        Fe::conditional_swap(&mut x2, &mut x3, swap);
+        Fe::conditional_swap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2.add(&z2);
+        let aa = a.square();
+        let b = x2.sub(&z2);
+        let bb = b.square();
+        let e = aa.sub(&bb);
+        let c = x3.add(&z3);
+        let d = x3.sub(&z3);
+        let da = d.mul(&a);
+        let cb = c.mul(&b);
+        x3 = da.add(&cb).square();
+        z3 = x1.mul(&da.sub(&cb).square());
+        x2 = aa.mul(&bb);
+        z2 = e.mul(&aa.add(&A24.mul(&e)));
+    }
+    Fe::conditional_swap(&mut x2, &mut x3, swap);
+    Fe::conditional_swap(&mut z2, &mut z3, swap);
+
+    x2.mul(&z2.invert())
+}
+
+// the X25519 function: the scalar is clamped to a multiple of 8 with its top bit at 254
+pub fn x25519(k: &[u8; KEY_BYTES], u: &[u8; KEY_BYTES]) -> [u8; KEY_BYTES] {
+    let mut k = *k;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+
+    ladder(&k, &Fe::from_bytes(u)).to_bytes()
+}
+
+// a u-coordinate that isn't of small order. every clamped scalar is a multiple of 8, which sends
+// the points of small order (on the curve and on its twist) to 0, so agreeing with one of them
+// would give a key that doesn't depend on our secret at all.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "[u8; KEY_BYTES]")]
+pub struct X25519Public([u8; KEY_BYTES]);
+
+impl TryFrom<[u8; KEY_BYTES]> for X25519Public {
+    type Error = InvalidElement;
+
+    fn try_from(u: [u8; KEY_BYTES]) -> Result<Self, Self::Error> {
+        let mut eight = [0u8; KEY_BYTES];
+        eight[0] = 8;
+        if ladder(&eight, &Fe::from_bytes(&u)).to_bytes() == [0; KEY_BYTES] {
+            return Err(InvalidElement::Trivial);
+        }
+
+        Ok(Self(u))
+    }
+}
+
+impl X25519Public {
+    pub fn as_bytes(&self) -> &[u8; KEY_BYTES] {
+        &self.0
+    }
+}
+
+#[derive(Clone)]
+struct KeyPair {
+    secret: [u8; KEY_BYTES],
+    public: X25519Public,
+}
+
+impl KeyPair {
+    fn generate() -> Self {
+        let mut secret = [0u8; KEY_BYTES];
+        ThreadDrbg.fill_bytes(&mut secret);
+        let public = X25519Public(x25519(&secret, &BASEPOINT));
+
+        Self { secret, public }
+    }
+}
+
+// as in dh, the keys are bound to both public values
+fn session_key(shared: &[u8], initiator: &X25519Public, responder: &X25519Public) -> SymmetricKey {
+    SymmetricKey::from_shared_secret(&[shared, &initiator.0, &responder.0].concat())
+}
+
+// the side that starts the exchange, i.e. Alice
+#[derive(Clone)]
+pub struct Initiator(KeyPair);
+
+impl Initiator {
+    pub fn generate() -> Self {
+        Self(KeyPair::generate())
+    }
+
+    pub fn get_public(&self) -> &X25519Public {
+        &self.0.public
+    }
+
+    // the response was checked not to be of small order when it was created, so this can't fail
+    pub fn extract_shared_secret(&self, response: &X25519Public) -> SymmetricKey {
+        let shared = x25519(&self.0.secret, &response.0);
+        session_key(&shared, &self.0.public, response)
+    }
+}
+
+// the side that answers, i.e. Bob
+pub struct Responder {
+    public: X25519Public,
+    key: SymmetricKey,
+}
+
+impl Responder {
+    pub fn respond(initiator: &X25519Public) -> Self {
+        let pair = KeyPair::generate();
+        let shared = x25519(&pair.secret, &initiator.0);
+        let key = session_key(&shared, initiator, &pair.public);
+
+        Self {
+            public: pair.public,
+            key,
+        }
+    }
+
+    pub fn get_public(&self) -> &X25519Public {
+        &self.public
+    }
+
+    pub fn extract_shared_secret(&self) -> SymmetricKey {
+        self.key.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // RFC 7748, section 5.2
+    #[test]
+    fn rfc7748_vectors() {
+        let cases = [
+            (
+                hex!("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4"),
+                hex!("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c"),
+                hex!("c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552"),
+            ),
+            (
+                hex!("4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d"),
+                hex!("e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493"),
+                hex!("95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957"),
+            ),
+        ];
+        for (k, u, expected) in cases {
+            assert_eq!(x25519(&k, &u), expected);
+        }
+
+        // the output becomes the next scalar, and the scalar the next u
+        let (mut k, mut u) = (BASEPOINT, BASEPOINT);
+        for i in 1..=1000 {
+            (k, u) = (x25519(&k, &u), k);
+            if i == 1 {
+                assert_eq!(
+                    k,
+                    hex!("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079")
+                );
+            }
+        }
+        assert_eq!(
+            k,
+            hex!("684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51")
+        );
+    }
+
+    // RFC 7748, section 6.1
+    #[test]
+    fn rfc7748_diffie_hellman() {
+        let alice = hex!("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = hex!("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_public = x25519(&alice, &BASEPOINT);
+        let bob_public = x25519(&bob, &BASEPOINT);
+
+        assert_eq!(
+            alice_public,
+            hex!("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob_public,
+            hex!("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+
+        let shared = hex!("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(x25519(&alice, &bob_public), shared);
+        assert_eq!(x25519(&bob, &alice_public), shared);
+    }
+
+    #[test]
+    fn it_works() {
+        let initiator = Initiator::generate();
+        let responder = Responder::respond(initiator.get_public());
+        let other = Responder::respond(initiator.get_public());
+
+        assert_eq!(
+            initiator.extract_shared_secret(responder.get_public()),
+            responder.extract_shared_secret()
+        );
+        assert_ne!(
+            initiator.extract_shared_secret(other.get_public()),
+            responder.extract_shared_secret()
+        );
+
+        let bytes = bincode::serialize(initiator.get_public()).unwrap();
+        assert_eq!(bytes.len(), KEY_BYTES);
+        let public: X25519Public = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(&public, initiator.get_public());
+    }
+
+    #[test]
+    fn rejects_small_order_points() {
+        let mut one = [0u8; KEY_BYTES];
+        one[0] = 1;
+        // points of order 8 on the curve, and p itself, which is 0
+        let cases = [
+            [0u8; KEY_BYTES],
+            one,
+            hex!("e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800"),
+            hex!("5f9c95bca3508c24b1d0b1559c83ef5b04445cc4581c8e86d8224eddd09f1157"),
+            hex!("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f"),
+        ];
+        for u in cases {
+            assert_eq!(X25519Public::try_from(u), Err(InvalidElement::Trivial));
+            let bytes = bincode::serialize(&u).unwrap();
+            assert!(bincode::deserialize::<X25519Public>(&bytes).is_err());
+        }
+
+        assert!(X25519Public::try_from(BASEPOINT).is_ok());
+    }
+}
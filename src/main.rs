@@ -1,21 +1,51 @@
 #![allow(clippy::large_enum_variant)]
 
-#[cfg(debug_assertions)]
 use std::env;
-
+use std::fs;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use base64::engine::general_purpose::STANDARD_NO_PAD;
 use base64::Engine;
 use eframe::egui::text::LayoutJob;
-use eframe::egui::{self, Button, Galley, RichText, ScrollArea, Style, TextEdit, ViewportBuilder};
+use eframe::egui::{
+    self, Button, Galley, ProgressBar, RichText, ScrollArea, Style, TextEdit, ViewportBuilder,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
+use titanium_rose::crypto::commitment::{self, Committer, HashCommitments, Responder};
+use titanium_rose::crypto::dh::{self, DhPublic};
 use titanium_rose::crypto::elgamal::{Alice, AlicePub, Bob, BobEphemeral};
+use titanium_rose::crypto::group::{self, GroupId, GroupParams, SelfCheck};
+use titanium_rose::crypto::otp::{self, OtpAuth, OtpKind};
+use titanium_rose::crypto::paramgen::{self, Progress};
+use titanium_rose::crypto::sas;
+use titanium_rose::crypto::schnorr::SigningKey;
+use titanium_rose::crypto::sha256::{self, Digest};
+use titanium_rose::crypto::sigma;
+use titanium_rose::crypto::spake2;
+use titanium_rose::crypto::x25519::{self, X25519Public};
 use titanium_rose::crypto::SymmetricKey;
 
-fn main() {
+fn main() -> ExitCode {
+    if env::args().any(|arg| arg == "--self-check") {
+        return self_check_cli();
+    }
+
+    // the built-in groups are checked in the background on every start, and the results are
+    // shown on the diagnostics screen
+    thread::spawn(|| SELF_CHECK.get_or_init(group::self_check_all));
+    thread::spawn(identity);
+
     let native_options = eframe::NativeOptions {
         viewport: ViewportBuilder::default()
             .with_resizable(false)
@@ -29,12 +59,120 @@ fn main() {
         Box::new(|cc| Box::new(MyEguiApp::new(cc))),
     )
     .unwrap();
+
+    ExitCode::SUCCESS
+}
+
+static SELF_CHECK: OnceLock<Vec<SelfCheck>> = OnceLock::new();
+
+static IDENTITY: OnceLock<SigningKey> = OnceLock::new();
+// why the identity above is only a temporary one, if it is
+static IDENTITY_PROBLEM: OnceLock<String> = OnceLock::new();
+
+// the long-term key that authenticates us in the SIGMA exchange. it's kept in a file, so that
+// peers see the same fingerprint every time.
+fn identity() -> &'static SigningKey {
+    IDENTITY.get_or_init(|| {
+        load_identity().unwrap_or_else(|problem| {
+            // a damaged file is left alone rather than replaced, and this run gets a temporary
+            // identity
+            IDENTITY_PROBLEM.get_or_init(|| problem);
+            SigningKey::generate(GroupId::default())
+        })
+    })
+}
+
+// `TITANIUM_ROSE_IDENTITY`, or a file in the per-user config directory
+fn identity_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("TITANIUM_ROSE_IDENTITY") {
+        return Some(PathBuf::from(path));
+    }
+
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let config = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| home().map(|home| home.join(".config")))
+    };
+
+    config.map(|dir| dir.join("titanium-rose").join("identity"))
+}
+
+fn load_identity() -> Result<SigningKey, String> {
+    let path = identity_path().ok_or("There's no config directory to keep your identity in")?;
+
+    match fs::read(&path) {
+        Ok(bytes) => {
+            return bincode::deserialize(&bytes)
+                .map_err(|e| format!("Your identity in {} is damaged: {e}", path.display()))
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(format!(
+                "Couldn't read your identity in {}: {e}",
+                path.display()
+            ))
+        }
+        Err(_) => {}
+    }
+
+    let key = SigningKey::generate(GroupId::default());
+    save_identity(&path, &key)
+        .map_err(|e| format!("Couldn't save your identity to {}: {e}", path.display()))?;
+    Ok(key)
+}
+
+// the file is readable by the user only, and an existing one is never overwritten
+fn save_identity(path: &Path, key: &SigningKey) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        builder.mode(0o700);
+        builder.create(dir)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options
+        .open(path)?
+        .write_all(&bincode::serialize(key).unwrap())
+}
+
+fn self_check_cli() -> ExitCode {
+    let mut success = true;
+    for check in group::self_check_all() {
+        let status = match check.result {
+            Ok(()) => "ok".to_owned(),
+            Err(error) => {
+                success = false;
+                format!("FAILED: {error}")
+            }
+        };
+        println!("{:<28} {:>5} bits  {status}", check.name, check.bits);
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
 
-#[derive(Default)]
 enum MyEguiApp {
-    #[default]
-    Initial,
+    Initial {
+        group: GroupId,
+        exchange: KeyExchange,
+        code: String,
+        // why the last session was abandoned, if it was
+        error: Option<String>,
+    },
     AliceSetup(AliceSetup),
     BobSetup(BobSetup),
     Final {
@@ -50,19 +188,380 @@ enum MyEguiApp {
         decrypting: bool,
         failed_to_decrypt: bool,
         decrypt_channel: (Sender<String>, Receiver<Option<String>>),
+
+        peer: Option<String>,
+        transcript: Digest,
+        sas_style: sas::Style,
+        verified: bool,
     },
+    Otp {
+        entries: Vec<OtpAuth>,
+        input: String,
+        error: Option<String>,
+    },
+    ParamGen(ParamGen),
+    LoadGroup(LoadGroup),
+    CoinFlip(CoinFlip),
+    Diagnostics,
 }
 
 enum AliceSetup {
-    Generating(mpsc::Receiver<Alice>),
-    WaitingForBob(Alice, &'static str, String, bool),
-    Computing(mpsc::Receiver<SymmetricKey>),
+    Generating(mpsc::Receiver<AliceSecret>),
+    WaitingForBob(AliceSecret, &'static str, String, Option<String>),
+    // keeps the previous screen's state, to return to it if Bob's response is rejected
+    Computing(
+        mpsc::Receiver<Result<(Established, Option<AliceFinish>), String>>,
+        AliceSecret,
+        &'static str,
+        String,
+    ),
+    // the authenticated exchanges have a third message, from Alice to Bob
+    Finishing(Established, &'static str),
 }
 
+// Alice's message and the shared code are kept, to return to them if the response fails
 enum BobSetup {
-    WaitingForAlice(Bob, String, bool),
-    Generating(Bob, mpsc::Receiver<BobEphemeral>),
-    Final(Bob, &'static str),
+    WaitingForAlice(String, String, Option<String>),
+    Generating(
+        mpsc::Receiver<Result<(BobMessage, BobState, Digest), String>>,
+        String,
+        String,
+    ),
+    Final(BobState, Digest, &'static str, String, Option<String>),
+}
+
+// a completed key exchange. `peer` is who the exchange was authenticated with, if it was: an
+// identity fingerprint, or that the shared code was known. `transcript` is what the short
+// authentication string is made from.
+#[derive(Clone)]
+struct Established {
+    key: SymmetricKey,
+    peer: Option<String>,
+    transcript: Digest,
+}
+
+enum BobState {
+    Done(SymmetricKey),
+    AwaitingFinish(sigma::Responder),
+    AwaitingConfirmation(spake2::Responder),
+}
+
+// who the other side of a SPAKE2 exchange is, as far as anyone can tell
+const SPAKE2_PEER: &str = "whoever knows the shared code";
+
+impl BobState {
+    // checks Alice's third message, and returns the key along with who it's shared with
+    fn finish(&self, finish: AliceFinish) -> Result<(SymmetricKey, String), String> {
+        match (self, finish) {
+            (Self::AwaitingFinish(responder), AliceFinish::Authenticated(finish)) => {
+                let session = responder
+                    .finish(&finish)
+                    .map_err(|e| format!("invalid input: {e}"))?;
+                Ok((session.key, session.peer.fingerprint()))
+            }
+            (Self::AwaitingConfirmation(responder), AliceFinish::Spake2(confirmation)) => {
+                let key = responder
+                    .finish(&confirmation)
+                    .map_err(|e| format!("invalid input: {e}"))?;
+                Ok((key, SPAKE2_PEER.to_owned()))
+            }
+            _ => Err("invalid input: Alice confirmed the wrong key exchange".to_owned()),
+        }
+    }
+}
+
+// how the session key is established. Alice picks, and Bob learns it from her message.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum KeyExchange {
+    // both sides contribute to the key, but neither knows who the other is
+    #[default]
+    DiffieHellman,
+    // Diffie-Hellman where both sides prove their long-term identity (SIGMA)
+    Authenticated,
+    // the same over Curve25519, with short public values and no choice of group
+    X25519,
+    // Bob picks the key and encrypts it to Alice
+    ElGamal,
+    // Diffie-Hellman authenticated by a short code that both sides type in (SPAKE2)
+    Spake2,
+}
+
+// new variants go at the end, so that the tags of the existing ones stay the same
+#[derive(Serialize, Deserialize)]
+enum AliceMessage {
+    DiffieHellman(DhPublic),
+    ElGamal(AlicePub),
+    Authenticated(DhPublic),
+    X25519(X25519Public),
+    Spake2(spake2::Share),
+}
+
+#[derive(Serialize, Deserialize)]
+enum BobMessage {
+    DiffieHellman(DhPublic),
+    ElGamal(BobEphemeral),
+    Authenticated(sigma::Response),
+    X25519(X25519Public),
+    Spake2(spake2::Response),
+}
+
+// the third message, for the exchanges that have one
+#[derive(Serialize, Deserialize)]
+enum AliceFinish {
+    Authenticated(sigma::Finish),
+    Spake2(spake2::Confirmation),
+}
+
+#[derive(Clone)]
+enum AliceSecret {
+    Authenticated(sigma::Initiator),
+    DiffieHellman(dh::Initiator),
+    X25519(x25519::Initiator),
+    ElGamal(Alice),
+    Spake2(spake2::Initiator),
+}
+
+impl AliceSecret {
+    fn generate(exchange: KeyExchange, group: GroupId, code: &str) -> Self {
+        match exchange {
+            KeyExchange::Authenticated => {
+                Self::Authenticated(sigma::Initiator::start(identity(), group))
+            }
+            KeyExchange::DiffieHellman => Self::DiffieHellman(dh::Initiator::generate(group)),
+            KeyExchange::X25519 => Self::X25519(x25519::Initiator::generate()),
+            KeyExchange::ElGamal => Self::ElGamal(Alice::generate(group)),
+            KeyExchange::Spake2 => Self::Spake2(spake2::Initiator::start(code.as_bytes(), group)),
+        }
+    }
+
+    fn message(&self) -> AliceMessage {
+        match self {
+            Self::Authenticated(initiator) => {
+                AliceMessage::Authenticated(initiator.get_public().clone())
+            }
+            Self::DiffieHellman(initiator) => {
+                AliceMessage::DiffieHellman(initiator.get_public().clone())
+            }
+            Self::X25519(initiator) => AliceMessage::X25519(initiator.get_public().clone()),
+            Self::ElGamal(alice) => AliceMessage::ElGamal(alice.get_public().clone()),
+            Self::Spake2(initiator) => AliceMessage::Spake2(initiator.get_share().clone()),
+        }
+    }
+
+    // the cheap checks on Bob's response, so that the expensive part can't fail later
+    fn check_response(&self, response: &BobMessage) -> Result<(), String> {
+        let (ours, theirs) = match (self, response) {
+            (Self::Authenticated(initiator), BobMessage::Authenticated(response)) => {
+                (initiator.get_public().group(), response.group())
+            }
+            (Self::DiffieHellman(initiator), BobMessage::DiffieHellman(public)) => {
+                (initiator.get_public().group(), public.group())
+            }
+            // there's only the one curve
+            (Self::X25519(_), BobMessage::X25519(_)) => return Ok(()),
+            (Self::ElGamal(alice), BobMessage::ElGamal(eph)) => {
+                (alice.get_public().group(), eph.group())
+            }
+            (Self::Spake2(initiator), BobMessage::Spake2(response)) => {
+                (initiator.get_share().group(), response.group())
+            }
+            _ => return Err("invalid input: Bob answered with the wrong key exchange".to_owned()),
+        };
+
+        if ours != theirs {
+            return Err(format!(
+                "invalid input: Bob answered in {theirs}, not {ours}"
+            ));
+        }
+
+        Ok(())
+    }
+
+    // the authenticated exchanges can still fail here, if Bob's identity or code doesn't check
+    // out. they also yield the last message of the handshake.
+    fn extract_shared_secret(
+        &self,
+        response: BobMessage,
+    ) -> Result<(Established, Option<AliceFinish>), String> {
+        let transcript = sas::transcript_hash(
+            &bincode::serialize(&self.message()).unwrap(),
+            &bincode::serialize(&response).unwrap(),
+        );
+
+        let key = match (self, response) {
+            (Self::Authenticated(initiator), BobMessage::Authenticated(response)) => {
+                let (finish, session) = initiator
+                    .finish(&response)
+                    .map_err(|e| format!("invalid input: {e}"))?;
+                let established = Established {
+                    key: session.key,
+                    peer: Some(session.peer.fingerprint()),
+                    transcript,
+                };
+                return Ok((established, Some(AliceFinish::Authenticated(finish))));
+            }
+            (Self::Spake2(initiator), BobMessage::Spake2(response)) => {
+                let (confirmation, key) = initiator
+                    .finish(&response)
+                    .map_err(|e| format!("invalid input: {e}"))?;
+                let established = Established {
+                    key,
+                    peer: Some(SPAKE2_PEER.to_owned()),
+                    transcript,
+                };
+                return Ok((established, Some(AliceFinish::Spake2(confirmation))));
+            }
+            (Self::DiffieHellman(initiator), BobMessage::DiffieHellman(public)) => {
+                initiator.extract_shared_secret(&public)
+            }
+            (Self::X25519(initiator), BobMessage::X25519(public)) => {
+                Ok(initiator.extract_shared_secret(&public))
+            }
+            (Self::ElGamal(alice), BobMessage::ElGamal(eph)) => alice.extract_shared_secret(eph),
+            _ => unreachable!("the response was checked"),
+        };
+
+        let key = key.expect("the response was checked");
+        let established = Established {
+            key,
+            peer: None,
+            transcript,
+        };
+        Ok((established, None))
+    }
+}
+
+// Bob's response, and the digest of both messages for the short authentication string. `code` is
+// only used by SPAKE2.
+fn respond_to_alice(
+    message: AliceMessage,
+    code: &str,
+) -> Result<(BobMessage, BobState, Digest), String> {
+    let alice_bytes = bincode::serialize(&message).unwrap();
+    let (response, state) = match message {
+        AliceMessage::Authenticated(public) => {
+            let (responder, response) = sigma::Responder::respond(identity(), &public);
+            (
+                BobMessage::Authenticated(response),
+                BobState::AwaitingFinish(responder),
+            )
+        }
+        AliceMessage::DiffieHellman(public) => {
+            let responder = dh::Responder::respond(&public);
+            let key = responder.extract_shared_secret();
+            (
+                BobMessage::DiffieHellman(responder.get_public().clone()),
+                BobState::Done(key),
+            )
+        }
+        AliceMessage::X25519(public) => {
+            let responder = x25519::Responder::respond(&public);
+            let key = responder.extract_shared_secret();
+            (
+                BobMessage::X25519(responder.get_public().clone()),
+                BobState::Done(key),
+            )
+        }
+        AliceMessage::ElGamal(public) => {
+            let bob = Bob::generate();
+            let eph = bob.encrypt_for_alice(&public);
+            (
+                BobMessage::ElGamal(eph),
+                BobState::Done(bob.extract_shared_secret()),
+            )
+        }
+        AliceMessage::Spake2(share) => {
+            if code.is_empty() {
+                return Err("Alice started a SPAKE2 exchange, enter the shared code".to_owned());
+            }
+            let (responder, response) = spake2::Responder::respond(code.as_bytes(), &share)
+                .map_err(|e| format!("invalid input: {e}"))?;
+            (
+                BobMessage::Spake2(response),
+                BobState::AwaitingConfirmation(responder),
+            )
+        }
+    };
+
+    let transcript = sas::transcript_hash(&alice_bytes, &bincode::serialize(&response).unwrap());
+    Ok((response, state, transcript))
+}
+
+// a coin flip by commit-reveal, over the same copy-paste channel as the key exchange. the
+// commitments are hashes, which keeps the texts short.
+enum CoinFlip {
+    Choosing,
+    // Alice has sent a commitment, and waits for Bob's bit
+    AliceWaiting(
+        Committer<HashCommitments>,
+        &'static str,
+        String,
+        Option<String>,
+    ),
+    BobWaitingForCommitment(String, Option<String>),
+    // Bob has sent a bit, and waits for Alice to open the commitment
+    BobWaitingForOpening(
+        Responder<HashCommitments>,
+        &'static str,
+        String,
+        Option<String>,
+    ),
+    // heads or tails, and the opening if Alice still has to send it
+    Done(bool, Option<&'static str>),
+}
+
+enum ParamGen {
+    Choosing(usize),
+    Running {
+        bits: usize,
+        started: Instant,
+        progress: Progress,
+        rx: mpsc::Receiver<ParamGenUpdate>,
+    },
+    Done(String),
+}
+
+// group parameters from paramgen, pasted here or passed on from the generator. both sides load
+// them before starting a session in the group.
+enum LoadGroup {
+    Entering(String, Option<String>),
+    // keeps the input, to return to it if the parameters are rejected
+    Loading(mpsc::Receiver<Result<GroupId, String>>, String),
+}
+
+impl LoadGroup {
+    // the full check includes primality tests, so it runs in the background
+    fn start(input: String) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let remote_input = input.clone();
+        thread::spawn(move || {
+            let group = decode_message::<GroupParams>(&remote_input).and_then(|params| {
+                group::load_group(&params).map_err(|e| format!("invalid parameters: {e}"))
+            });
+            tx.send(group).unwrap();
+        });
+
+        Self::Loading(rx, input)
+    }
+}
+
+enum ParamGenUpdate {
+    Progress(Progress),
+    Done(GroupParams),
+}
+
+// the sizes offered for new groups, which have to meet the same minimum as the built-in ones
+const PARAMGEN_BITS: [usize; 2] = [3072, 4096];
+
+impl Default for MyEguiApp {
+    fn default() -> Self {
+        Self::Initial {
+            group: GroupId::default(),
+            exchange: KeyExchange::default(),
+            code: String::new(),
+            error: None,
+        }
+    }
 }
 
 impl MyEguiApp {
@@ -73,59 +572,67 @@ impl MyEguiApp {
         // for e.g. egui::PaintCallback.
         #[cfg(debug_assertions)]
         if env::var("SKIP_SETUP").is_ok() {
-            return Self::new_final(SymmetricKey::generate());
+            return Self::new_final(Established {
+                key: SymmetricKey::generate(),
+                peer: None,
+                transcript: [0; sha256::DIGEST_BYTES],
+            });
         }
 
         Self::default()
     }
 
-    fn new_final(key: SymmetricKey) -> Self {
-        let (etx, remote_erx) = mpsc::channel();
+    fn new_final(
+        Established {
+            key,
+            peer,
+            transcript,
+        }: Established,
+    ) -> Self {
+        let (etx, remote_erx) = mpsc::channel::<String>();
         let (remote_etx, erx) = mpsc::channel();
 
         let ekey = key.clone();
-        thread::spawn(move || loop {
-            let Some(input): Option<String> = remote_erx.recv().ok() else {
-                break;
-            };
-            let bytes = ekey.encrypt(input.as_bytes());
-            let b64 = STANDARD_NO_PAD.encode(&bytes);
-            if remote_etx.send(b64).is_err() {
-                break;
+        thread::spawn(move || {
+            while let Ok(input) = remote_erx.recv() {
+                let bytes = ekey.encrypt(input.as_bytes());
+                let b64 = STANDARD_NO_PAD.encode(&bytes);
+                if remote_etx.send(b64).is_err() {
+                    break;
+                }
             }
         });
 
-        let (dtx, remote_drx) = mpsc::channel();
+        let (dtx, remote_drx) = mpsc::channel::<String>();
         let (remote_dtx, drx) = mpsc::channel();
 
-        thread::spawn(move || loop {
-            let Some(input): Option<String> = remote_drx.recv().ok() else {
-                break;
-            };
-            let Ok(decoded) = STANDARD_NO_PAD.decode(input) else {
-                if remote_dtx.send(None).is_ok() {
-                    continue;
-                } else {
-                    break;
-                }
-            };
+        thread::spawn(move || {
+            while let Ok(input) = remote_drx.recv() {
+                let Ok(decoded) = STANDARD_NO_PAD.decode(input) else {
+                    if remote_dtx.send(None).is_ok() {
+                        continue;
+                    } else {
+                        break;
+                    }
+                };
+
+                let Some(plaintext) = key.decrypt(&decoded) else {
+                    if remote_dtx.send(None).is_ok() {
+                        continue;
+                    } else {
+                        break;
+                    }
+                };
 
-            let Some(plaintext) = key.decrypt(&decoded) else {
-                if remote_dtx.send(None).is_ok() {
-                    continue;
+                let res = if let Ok(string) = String::from_utf8(plaintext) {
+                    remote_dtx.send(Some(string))
                 } else {
+                    remote_dtx.send(None)
+                };
+
+                if res.is_err() {
                     break;
                 }
-            };
-
-            let res = if let Ok(string) = String::from_utf8(plaintext) {
-                remote_dtx.send(Some(string))
-            } else {
-                remote_dtx.send(None)
-            };
-
-            if res.is_err() {
-                break;
             }
         });
 
@@ -142,6 +649,11 @@ impl MyEguiApp {
             decrypting: false,
             failed_to_decrypt: false,
             decrypt_channel: (dtx, drx),
+
+            peer,
+            transcript,
+            sas_style: sas::Style::default(),
+            verified: false,
         }
     }
 }
@@ -155,32 +667,161 @@ impl eframe::App for MyEguiApp {
             ui.style_mut().spacing.item_spacing = (10.0, 10.0).into();
 
             match self {
-                MyEguiApp::Initial => {
-                    ui.vertical_centered_justified(|ui| {
-                        if ui
-                            .button(RichText::new("Start New Session (Alice)").size(25.0))
-                            .clicked()
-                        {
-                            let (tx, rx) = mpsc::channel();
-                            thread::spawn(move || {
-                                let alice = Alice::generate();
-                                tx.send(alice).unwrap()
+                MyEguiApp::Initial {
+                    group,
+                    exchange,
+                    code,
+                    error,
+                } => {
+                    let next = ui
+                        .vertical_centered_justified(|ui| {
+                            if let Some(error) = error {
+                                ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
+                            }
+
+                            // SPAKE2 has nothing to authenticate with until a code is typed in
+                            let ready = *exchange != KeyExchange::Spake2 || !code.is_empty();
+                            if ui
+                                .add_enabled(
+                                    ready,
+                                    Button::new(
+                                        RichText::new("Start New Session (Alice)").size(25.0),
+                                    ),
+                                )
+                                .clicked()
+                            {
+                                let (tx, rx) = mpsc::channel();
+                                let (exchange, group, code) = (*exchange, *group, code.clone());
+                                thread::spawn(move || {
+                                    let alice = AliceSecret::generate(exchange, group, &code);
+                                    tx.send(alice).unwrap()
+                                });
+                                return Some(MyEguiApp::AliceSetup(AliceSetup::Generating(rx)));
+                            }
+
+                            // Bob learns both of these from Alice's public key
+                            ui.horizontal(|ui| {
+                                ui.radio_value(
+                                    exchange,
+                                    KeyExchange::DiffieHellman,
+                                    "Diffie-Hellman",
+                                );
+                                ui.radio_value(
+                                    exchange,
+                                    KeyExchange::Authenticated,
+                                    "Authenticated",
+                                );
+                                ui.radio_value(exchange, KeyExchange::X25519, "X25519");
+                                ui.radio_value(
+                                    exchange,
+                                    KeyExchange::ElGamal,
+                                    "ElGamal key transport",
+                                );
+                                ui.radio_value(exchange, KeyExchange::Spake2, "Shared code");
                             });
-                            *self = MyEguiApp::AliceSetup(AliceSetup::Generating(rx));
-                        }
+                            egui::ComboBox::from_label("Group for new sessions")
+                                .selected_text(group.name())
+                                .show_ui(ui, |ui| {
+                                    for option in
+                                        GroupId::ALL.into_iter().chain(group::custom_groups())
+                                    {
+                                        ui.selectable_value(group, option, option.name());
+                                    }
+                                });
+                            // unlike the rest, Bob has to be told this some other way
+                            if *exchange == KeyExchange::Spake2 {
+                                ui.horizontal(|ui| {
+                                    ui.label("Code agreed with Bob:");
+                                    ui.text_edit_singleline(code);
+                                });
+                            }
 
-                        if ui
-                            .button(RichText::new("Continue New Session (Bob)").size(25.0))
-                            .clicked()
-                        {
-                            // generating bob should be relatively cheap compared to alice
-                            *self = MyEguiApp::BobSetup(BobSetup::WaitingForAlice(
-                                Bob::generate(),
-                                String::new(),
-                                false,
-                            ));
-                        }
-                    });
+                            // what the other side will see as our identity
+                            match IDENTITY.get() {
+                                Some(key) => ui.label(format!(
+                                    "Your identity: {}",
+                                    key.verifying_key().fingerprint()
+                                )),
+                                None => {
+                                    ctx.request_repaint_after(Duration::from_millis(250));
+                                    ui.label("Loading your identity...")
+                                }
+                            };
+                            if let Some(problem) = IDENTITY_PROBLEM.get() {
+                                ui.colored_label(
+                                    ui.style().visuals.warn_fg_color,
+                                    format!("{problem}. This run uses a temporary identity."),
+                                );
+                            }
+
+                            if ui
+                                .button(RichText::new("Continue New Session (Bob)").size(25.0))
+                                .clicked()
+                            {
+                                return Some(MyEguiApp::BobSetup(BobSetup::WaitingForAlice(
+                                    String::new(),
+                                    String::new(),
+                                    None,
+                                )));
+                            }
+
+                            if ui
+                                .button(RichText::new("One-Time Passwords").size(25.0))
+                                .clicked()
+                            {
+                                return Some(MyEguiApp::Otp {
+                                    entries: Vec::new(),
+                                    input: String::new(),
+                                    error: None,
+                                });
+                            }
+
+                            if ui
+                                .button(RichText::new("Generate Group Parameters").size(25.0))
+                                .clicked()
+                            {
+                                return Some(MyEguiApp::ParamGen(ParamGen::Choosing(
+                                    PARAMGEN_BITS[0],
+                                )));
+                            }
+
+                            if ui.button(RichText::new("Flip a Coin").size(25.0)).clicked() {
+                                return Some(MyEguiApp::CoinFlip(CoinFlip::Choosing));
+                            }
+
+                            let (diagnostics, load_group) = ui.columns(2, |columns| {
+                                (
+                                    columns[0].button("Diagnostics").clicked(),
+                                    columns[1].button("Load Group Parameters").clicked(),
+                                )
+                            });
+                            if diagnostics {
+                                return Some(MyEguiApp::Diagnostics);
+                            }
+                            if load_group {
+                                return Some(MyEguiApp::LoadGroup(LoadGroup::Entering(
+                                    String::new(),
+                                    None,
+                                )));
+                            }
+
+                            let failed = SELF_CHECK
+                                .get()
+                                .is_some_and(|checks| checks.iter().any(|c| c.result.is_err()));
+                            if failed {
+                                ui.colored_label(
+                                    ui.style().visuals.error_fg_color,
+                                    "The group parameter self-check failed, see Diagnostics",
+                                );
+                            }
+
+                            None
+                        })
+                        .inner;
+
+                    if let Some(next) = next {
+                        *self = next;
+                    }
                 }
                 MyEguiApp::AliceSetup(AliceSetup::Generating(rx)) => {
                     ui.horizontal(|ui| {
@@ -190,13 +831,13 @@ impl eframe::App for MyEguiApp {
 
                     match rx.try_recv() {
                         Ok(alice) => {
-                            let bytes = bincode::serialize(alice.get_public()).unwrap();
+                            let bytes = bincode::serialize(&alice.message()).unwrap();
                             let public_text = STANDARD_NO_PAD.encode(bytes);
                             *self = MyEguiApp::AliceSetup(AliceSetup::WaitingForBob(
                                 alice,
                                 public_text.leak(),
                                 String::new(),
-                                false,
+                                None,
                             ))
                         }
                         Err(mpsc::TryRecvError::Empty) => {}
@@ -207,9 +848,12 @@ impl eframe::App for MyEguiApp {
                     alice,
                     public_text,
                     input,
-                    invalid_input,
+                    input_error,
                 )) => {
                     ui.heading("Copy your public key and send it to Bob:");
+                    // Bob's screen shows the same fingerprint once the key is pasted
+                    let bytes = STANDARD_NO_PAD.decode(*public_text).unwrap();
+                    ui.label(format!("Fingerprint: {}", sas::fingerprint(&bytes)));
 
                     ScrollArea::vertical()
                         .id_source("first scroll area")
@@ -236,50 +880,102 @@ impl eframe::App for MyEguiApp {
                         .inner;
 
                     if textedit.changed() {
-                        *invalid_input = false;
+                        *input_error = None;
                     }
 
                     let button = ui.button("Continue");
 
-                    if *invalid_input {
-                        ui.colored_label(ui.style().visuals.error_fg_color, "invalid input");
+                    if let Some(error) = input_error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
                     }
 
                     if button.clicked() {
-                        let Some(bytes) = STANDARD_NO_PAD.decode(input).ok() else {
-                            *invalid_input = true;
-                            return;
-                        };
-                        let Some(eph): Option<BobEphemeral> = bincode::deserialize(&bytes).ok()
-                        else {
-                            *invalid_input = true;
-                            return;
+                        let response = decode_message(input)
+                            .and_then(|response| alice.check_response(&response).map(|_| response));
+                        let response: BobMessage = match response {
+                            Ok(response) => response,
+                            Err(error) => {
+                                *input_error = Some(error);
+                                return;
+                            }
                         };
 
                         let (tx, rx) = mpsc::channel();
-                        let alice = alice.clone();
+                        let remote_alice = alice.clone();
                         thread::spawn(move || {
-                            let secret = alice.extract_shared_secret(eph);
+                            let secret = remote_alice.extract_shared_secret(response);
                             tx.send(secret).unwrap();
                         });
 
-                        *self = MyEguiApp::AliceSetup(AliceSetup::Computing(rx));
+                        *self = MyEguiApp::AliceSetup(AliceSetup::Computing(
+                            rx,
+                            alice.clone(),
+                            public_text,
+                            input.clone(),
+                        ));
                     }
                 }
-                MyEguiApp::AliceSetup(AliceSetup::Computing(rx)) => {
+                MyEguiApp::AliceSetup(AliceSetup::Computing(rx, alice, public_text, input)) => {
                     ui.horizontal(|ui| {
                         ui.heading("Please wait...");
                         ui.spinner();
                     });
 
                     match rx.try_recv() {
-                        Ok(key) => *self = MyEguiApp::new_final(key),
+                        Ok(Ok((established, None))) => *self = MyEguiApp::new_final(established),
+                        Ok(Ok((established, Some(finish)))) => {
+                            let bytes = bincode::serialize(&finish).unwrap();
+                            let text = STANDARD_NO_PAD.encode(bytes).leak();
+                            *self = MyEguiApp::AliceSetup(AliceSetup::Finishing(established, text))
+                        }
+                        // every response checked against a SPAKE2 share is a guess at the code, so
+                        // the share is dropped instead of offered to the next response
+                        Ok(Err(error)) => match alice {
+                            AliceSecret::Spake2(initiator) => {
+                                *self = MyEguiApp::Initial {
+                                    group: initiator.get_share().group(),
+                                    exchange: KeyExchange::Spake2,
+                                    code: String::new(),
+                                    error: Some(format!("{error}, start a new session")),
+                                }
+                            }
+                            _ => {
+                                *self = MyEguiApp::AliceSetup(AliceSetup::WaitingForBob(
+                                    alice.clone(),
+                                    public_text,
+                                    input.clone(),
+                                    Some(error),
+                                ))
+                            }
+                        },
                         Err(mpsc::TryRecvError::Empty) => {}
                         Err(mpsc::TryRecvError::Disconnected) => unreachable!(),
                     }
                 }
-                MyEguiApp::BobSetup(BobSetup::WaitingForAlice(bob, input, invalid_input)) => {
+                MyEguiApp::AliceSetup(AliceSetup::Finishing(established, text)) => {
+                    if let Some(peer) = &established.peer {
+                        ui.label(format!("Talking to: {peer}"));
+                    }
+                    ui.heading("Send this confirmation to Bob:");
+                    ScrollArea::vertical()
+                        .max_height(TEXT_SCROLLER_MAX_HEIGHT)
+                        .show(ui, |ui| {
+                            TextEdit::multiline(text)
+                                .desired_rows(TEXT_DESIRED_ROWS)
+                                .layouter(&mut my_layouter)
+                                .show(ui);
+                        });
+
+                    if ui.button("Continue").clicked() {
+                        *self = MyEguiApp::new_final(established.clone());
+                    }
+                }
+                MyEguiApp::BobSetup(BobSetup::WaitingForAlice(input, code, input_error)) => {
                     ui.heading("Enter Alice's public key:");
+                    // only the base64 is decoded here, the full check is on Continue
+                    if let Ok(bytes) = STANDARD_NO_PAD.decode(input.trim()) {
+                        ui.label(format!("Fingerprint: {}", sas::fingerprint(&bytes)));
+                    }
 
                     let textedit = ScrollArea::vertical()
                         .max_height(TEXT_SCROLLER_MAX_HEIGHT)
@@ -292,59 +988,84 @@ impl eframe::App for MyEguiApp {
                         })
                         .inner;
 
-                    if textedit.changed() {
-                        *invalid_input = false;
+                    let code_edit = ui
+                        .horizontal(|ui| {
+                            ui.label("Code agreed with Alice, if the exchange uses one:");
+                            ui.text_edit_singleline(code)
+                        })
+                        .inner;
+
+                    if textedit.changed() || code_edit.changed() {
+                        *input_error = None;
                     }
 
                     let button = ui.button("Continue");
 
-                    if *invalid_input {
-                        ui.colored_label(ui.style().visuals.error_fg_color, "invalid input");
+                    if let Some(error) = input_error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
                     }
 
                     if button.clicked() {
-                        // TODO: verify input correctness
-                        let Some(bytes) = STANDARD_NO_PAD.decode(input).ok() else {
-                            *invalid_input = true;
-                            return;
-                        };
-                        let Some(public): Option<AlicePub> = bincode::deserialize(&bytes).ok()
-                        else {
-                            *invalid_input = true;
-                            return;
+                        let message: AliceMessage = match decode_message(input) {
+                            Ok(message) => message,
+                            Err(error) => {
+                                *input_error = Some(error);
+                                return;
+                            }
                         };
 
                         let (tx, rx) = mpsc::channel();
-                        {
-                            let bob = bob.clone();
-                            thread::spawn(move || {
-                                let eph = bob.encrypt_for_alice(&public);
-                                tx.send(eph).unwrap();
-                            });
-                        }
+                        let remote_code = code.clone();
+                        thread::spawn(move || {
+                            tx.send(respond_to_alice(message, &remote_code)).unwrap();
+                        });
 
-                        *self = MyEguiApp::BobSetup(BobSetup::Generating(bob.clone(), rx));
+                        *self = MyEguiApp::BobSetup(BobSetup::Generating(
+                            rx,
+                            input.clone(),
+                            code.clone(),
+                        ));
                     }
                 }
-                MyEguiApp::BobSetup(BobSetup::Generating(bob, rx)) => {
+                MyEguiApp::BobSetup(BobSetup::Generating(rx, input, code)) => {
                     ui.horizontal(|ui| {
                         ui.heading("Please wait...");
                         ui.spinner();
                     });
 
                     match rx.try_recv() {
-                        Ok(eph) => {
-                            let bytes = bincode::serialize(&eph).unwrap();
+                        Ok(Err(error)) => {
+                            *self = MyEguiApp::BobSetup(BobSetup::WaitingForAlice(
+                                input.clone(),
+                                code.clone(),
+                                Some(error),
+                            ))
+                        }
+                        Ok(Ok((response, state, transcript))) => {
+                            let bytes = bincode::serialize(&response).unwrap();
                             let text = STANDARD_NO_PAD.encode(bytes).leak();
-                            *self = MyEguiApp::BobSetup(BobSetup::Final(bob.clone(), text))
+                            *self = MyEguiApp::BobSetup(BobSetup::Final(
+                                state,
+                                transcript,
+                                text,
+                                String::new(),
+                                None,
+                            ))
                         }
                         Err(mpsc::TryRecvError::Empty) => {}
                         Err(mpsc::TryRecvError::Disconnected) => unreachable!(),
                     }
                 }
-                MyEguiApp::BobSetup(BobSetup::Final(bob, text)) => {
-                    ui.heading("Send the encrypted shared secret to Alice:");
+                MyEguiApp::BobSetup(BobSetup::Final(
+                    state,
+                    transcript,
+                    text,
+                    input,
+                    input_error,
+                )) => {
+                    ui.heading("Send your response to Alice:");
                     ScrollArea::vertical()
+                        .id_source("first scroll area")
                         .max_height(TEXT_SCROLLER_MAX_HEIGHT)
                         .show(ui, |ui| {
                             TextEdit::multiline(text)
@@ -353,9 +1074,54 @@ impl eframe::App for MyEguiApp {
                                 .show(ui);
                         });
 
-                    if ui.button("Continue").clicked() {
-                        let key = bob.extract_shared_secret();
-                        *self = MyEguiApp::new_final(key);
+                    if let BobState::Done(key) = state {
+                        if ui.button("Continue").clicked() {
+                            *self = MyEguiApp::new_final(Established {
+                                key: key.clone(),
+                                peer: None,
+                                transcript: *transcript,
+                            });
+                        }
+                        return;
+                    }
+
+                    ui.heading("Enter Alice's confirmation:");
+
+                    let textedit = ScrollArea::vertical()
+                        .id_source("second scroll area")
+                        .max_height(TEXT_SCROLLER_MAX_HEIGHT)
+                        .show(ui, |ui| {
+                            TextEdit::multiline(input)
+                                .desired_rows(TEXT_DESIRED_ROWS)
+                                .layouter(&mut my_layouter)
+                                .show(ui)
+                                .response
+                        })
+                        .inner;
+
+                    if textedit.changed() {
+                        *input_error = None;
+                    }
+
+                    let button = ui.button("Continue");
+
+                    if let Some(error) = input_error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
+                    }
+
+                    if button.clicked() {
+                        // a signature check and a MAC, which are quick enough to do right here
+                        let session = decode_message(input).and_then(|finish| state.finish(finish));
+                        match session {
+                            Ok((key, peer)) => {
+                                *self = MyEguiApp::new_final(Established {
+                                    key,
+                                    peer: Some(peer),
+                                    transcript: *transcript,
+                                })
+                            }
+                            Err(error) => *input_error = Some(error),
+                        }
                     }
                 }
                 MyEguiApp::Final {
@@ -371,6 +1137,11 @@ impl eframe::App for MyEguiApp {
                     decrypting,
                     failed_to_decrypt,
                     decrypt_channel,
+
+                    peer,
+                    transcript,
+                    sas_style,
+                    verified,
                 } => {
                     if *encrypting {
                         match encrypt_channel.1.try_recv() {
@@ -398,6 +1169,34 @@ impl eframe::App for MyEguiApp {
                         }
                     }
 
+                    match peer {
+                        Some(peer) => ui.label(format!("Talking to: {peer}")),
+                        None => ui.label("The other side is not authenticated"),
+                    };
+
+                    // a swapped key shows up as a different code on each side
+                    let header = if *verified {
+                        RichText::new("Session verified")
+                    } else {
+                        RichText::new("Not verified: compare the session code with the other side")
+                            .color(ui.style().visuals.warn_fg_color)
+                    };
+                    egui::CollapsingHeader::new(header)
+                        .id_source("session code")
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                for style in sas::Style::ALL {
+                                    ui.radio_value(sas_style, style, style.name());
+                                }
+                            });
+                            let code = RichText::new(sas::render(transcript, *sas_style));
+                            match sas_style {
+                                sas::Style::Randomart => ui.label(code.monospace()),
+                                _ => ui.label(code.size(16.0)),
+                            };
+                            ui.checkbox(verified, "This matches what the other side sees");
+                        });
+
                     ui.columns(2, |columns| {
                         columns[0].heading("Encrypt Text");
                         let encrypt_input_response = ScrollArea::vertical()
@@ -493,11 +1292,504 @@ impl eframe::App for MyEguiApp {
                         }
                     });
                 }
+                MyEguiApp::Otp {
+                    entries,
+                    input,
+                    error,
+                } => {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    // keep the codes and countdowns current
+                    ctx.request_repaint_after(Duration::from_millis(250));
+
+                    ui.heading("Add an otpauth:// URI or a base32 secret:");
+                    let textedit = ui.add(
+                        TextEdit::singleline(input)
+                            .desired_width(f32::INFINITY)
+                            .font(egui::TextStyle::Monospace),
+                    );
+                    if textedit.changed() {
+                        *error = None;
+                    }
+
+                    let back = ui
+                        .horizontal(|ui| {
+                            if ui.button("Add").clicked() {
+                                match parse_otp_input(input, entries.len() + 1) {
+                                    Ok(entry) => {
+                                        entries.push(entry);
+                                        input.clear();
+                                    }
+                                    Err(e) => *error = Some(e),
+                                }
+                            }
+
+                            ui.button("Back").clicked()
+                        })
+                        .inner;
+
+                    if let Some(error) = error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
+                    }
+
+                    ScrollArea::vertical().show(ui, |ui| {
+                        for entry in entries.iter_mut() {
+                            ui.separator();
+                            let name = match &entry.issuer {
+                                Some(issuer) if !entry.label.starts_with(issuer.as_str()) => {
+                                    format!("{issuer} ({})", entry.label)
+                                }
+                                _ => entry.label.clone(),
+                            };
+                            ui.label(name);
+
+                            let code = otp::format_code(entry.code(now), entry.digits);
+                            ui.horizontal(|ui| {
+                                ui.label(RichText::new(code).monospace().size(25.0));
+
+                                match &mut entry.kind {
+                                    OtpKind::Totp { period } => {
+                                        let remaining = otp::totp_remaining(now, *period);
+                                        ui.add(
+                                            ProgressBar::new(remaining as f32 / *period as f32)
+                                                .text(format!("{remaining}s")),
+                                        );
+                                    }
+                                    OtpKind::Hotp { counter } => {
+                                        if ui.button("Next").clicked() {
+                                            *counter += 1;
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                    });
+
+                    if back {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::ParamGen(ParamGen::Choosing(bits)) => {
+                    ui.heading("Generate a new safe-prime group");
+                    ui.label(
+                        "This searches for a random safe prime p = 2q + 1 and a generator of its \
+                         order-q subgroup. It can take several minutes.",
+                    );
+
+                    ui.horizontal(|ui| {
+                        for option in PARAMGEN_BITS {
+                            ui.radio_value(bits, option, format!("{option} bits"));
+                        }
+                    });
+
+                    let (generate, back) = ui
+                        .horizontal(|ui| {
+                            (ui.button("Generate").clicked(), ui.button("Back").clicked())
+                        })
+                        .inner;
+
+                    if generate {
+                        let bits = *bits;
+                        let (tx, rx) = mpsc::channel();
+                        thread::spawn(move || {
+                            // the receiver is dropped when the user leaves this screen, which
+                            // stops the search
+                            let params = paramgen::generate(bits, |step| {
+                                match tx.send(ParamGenUpdate::Progress(step)) {
+                                    Ok(()) => ControlFlow::Continue(()),
+                                    Err(_) => ControlFlow::Break(()),
+                                }
+                            });
+                            if let Some(params) = params {
+                                let _ = tx.send(ParamGenUpdate::Done(params));
+                            }
+                        });
+
+                        *self = MyEguiApp::ParamGen(ParamGen::Running {
+                            bits,
+                            started: Instant::now(),
+                            progress: Progress::Searching {
+                                sieved: 0,
+                                tested: 0,
+                            },
+                            rx,
+                        });
+                    } else if back {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::ParamGen(ParamGen::Running {
+                    bits,
+                    started,
+                    progress,
+                    rx,
+                }) => {
+                    ctx.request_repaint_after(Duration::from_millis(250));
+
+                    let mut done = None;
+                    for update in rx.try_iter() {
+                        match update {
+                            ParamGenUpdate::Progress(step) => *progress = step,
+                            ParamGenUpdate::Done(params) => done = Some(params),
+                        }
+                    }
+                    if let Some(params) = done {
+                        let bytes = bincode::serialize(&params).unwrap();
+                        *self = MyEguiApp::ParamGen(ParamGen::Done(STANDARD_NO_PAD.encode(bytes)));
+                        return;
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.heading(format!("Generating a {bits}-bit group..."));
+                        ui.spinner();
+                    });
+
+                    let status = match progress {
+                        Progress::Searching { sieved, tested } => format!(
+                            "{tested} candidates tested, {sieved} ruled out by small factors"
+                        ),
+                        Progress::Verifying => "verifying a candidate".to_owned(),
+                        Progress::SelectingGenerator => "selecting a generator".to_owned(),
+                        Progress::Done => "done".to_owned(),
+                    };
+                    ui.label(status);
+                    ui.label(format!("{} seconds elapsed", started.elapsed().as_secs()));
+
+                    if ui.button("Cancel").clicked() {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::Diagnostics => {
+                    ui.heading("Group parameter self-check");
+                    ui.label(
+                        "Checks that p and q = (p - 1) / 2 are prime and that g generates the \
+                         order-q subgroup, for every built-in group.",
+                    );
+
+                    match SELF_CHECK.get() {
+                        Some(checks) => {
+                            egui::Grid::new("self check").striped(true).show(ui, |ui| {
+                                for check in checks {
+                                    ui.label(check.name);
+                                    ui.label(format!("{} bits", check.bits));
+                                    match check.result {
+                                        Ok(()) => ui.label("ok"),
+                                        Err(error) => ui.colored_label(
+                                            ui.style().visuals.error_fg_color,
+                                            error.to_string(),
+                                        ),
+                                    };
+                                    ui.end_row();
+                                }
+                            });
+                        }
+                        None => {
+                            ctx.request_repaint_after(Duration::from_millis(250));
+                            ui.horizontal(|ui| {
+                                ui.label("Checking...");
+                                ui.spinner();
+                            });
+                        }
+                    }
+
+                    if ui.button("Back").clicked() {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::CoinFlip(CoinFlip::Choosing) => {
+                    ui.heading("Flip a coin that neither side can bias");
+                    ui.label(
+                        "Alice commits to a random bit, Bob answers with another, and Alice then \
+                         reveals the first. The coin is the xor of both bits.",
+                    );
+
+                    let (alice, bob, back) = ui
+                        .horizontal(|ui| {
+                            (
+                                ui.button("Start (Alice)").clicked(),
+                                ui.button("Join (Bob)").clicked(),
+                                ui.button("Back").clicked(),
+                            )
+                        })
+                        .inner;
+
+                    if alice {
+                        let (committer, commitment) =
+                            Committer::start(&HashCommitments, &commitment::coin_flip_value());
+                        let bytes = bincode::serialize(&commitment).unwrap();
+                        *self = MyEguiApp::CoinFlip(CoinFlip::AliceWaiting(
+                            committer,
+                            STANDARD_NO_PAD.encode(bytes).leak(),
+                            String::new(),
+                            None,
+                        ));
+                    } else if bob {
+                        *self = MyEguiApp::CoinFlip(CoinFlip::BobWaitingForCommitment(
+                            String::new(),
+                            None,
+                        ));
+                    } else if back {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::CoinFlip(CoinFlip::AliceWaiting(
+                    committer,
+                    text,
+                    input,
+                    input_error,
+                )) => {
+                    ui.heading("Send your commitment to Bob:");
+                    TextEdit::multiline(text)
+                        .desired_rows(2)
+                        .layouter(&mut my_layouter)
+                        .show(ui);
+
+                    ui.heading("Enter Bob's answer:");
+                    if TextEdit::singleline(input)
+                        .desired_width(f32::INFINITY)
+                        .show(ui)
+                        .response
+                        .changed()
+                    {
+                        *input_error = None;
+                    }
+
+                    let button = ui.button("Continue");
+                    if let Some(error) = input_error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
+                    }
+
+                    if button.clicked() {
+                        match decode_message::<Vec<u8>>(input) {
+                            Ok(bit) => {
+                                let (opening, outcome) = committer.finish(&bit);
+                                let bytes = bincode::serialize(&opening).unwrap();
+                                *self = MyEguiApp::CoinFlip(CoinFlip::Done(
+                                    commitment::coin_flip(&outcome),
+                                    Some(STANDARD_NO_PAD.encode(bytes).leak()),
+                                ));
+                            }
+                            Err(error) => *input_error = Some(error),
+                        }
+                    }
+                }
+                MyEguiApp::CoinFlip(CoinFlip::BobWaitingForCommitment(input, input_error)) => {
+                    ui.heading("Enter Alice's commitment:");
+                    if TextEdit::multiline(input)
+                        .desired_rows(2)
+                        .layouter(&mut my_layouter)
+                        .show(ui)
+                        .response
+                        .changed()
+                    {
+                        *input_error = None;
+                    }
+
+                    let button = ui.button("Continue");
+                    if let Some(error) = input_error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
+                    }
+
+                    if button.clicked() {
+                        match decode_message(input) {
+                            Ok(commitment) => {
+                                let responder =
+                                    Responder::respond(commitment, &commitment::coin_flip_value());
+                                let bytes = bincode::serialize(responder.value()).unwrap();
+                                *self = MyEguiApp::CoinFlip(CoinFlip::BobWaitingForOpening(
+                                    responder,
+                                    STANDARD_NO_PAD.encode(bytes).leak(),
+                                    String::new(),
+                                    None,
+                                ));
+                            }
+                            Err(error) => *input_error = Some(error),
+                        }
+                    }
+                }
+                MyEguiApp::CoinFlip(CoinFlip::BobWaitingForOpening(
+                    responder,
+                    text,
+                    input,
+                    input_error,
+                )) => {
+                    ui.heading("Send your answer to Alice:");
+                    TextEdit::singleline(text)
+                        .desired_width(f32::INFINITY)
+                        .show(ui);
+
+                    ui.heading("Enter what Alice reveals:");
+                    if TextEdit::multiline(input)
+                        .desired_rows(3)
+                        .layouter(&mut my_layouter)
+                        .show(ui)
+                        .response
+                        .changed()
+                    {
+                        *input_error = None;
+                    }
+
+                    let button = ui.button("Continue");
+                    if let Some(error) = input_error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
+                    }
+
+                    if button.clicked() {
+                        let outcome = decode_message(input).and_then(|opening| {
+                            responder.finish(&HashCommitments, &opening).ok_or_else(|| {
+                                "invalid input: this doesn't open Alice's commitment".to_owned()
+                            })
+                        });
+                        match outcome {
+                            Ok(outcome) => {
+                                *self = MyEguiApp::CoinFlip(CoinFlip::Done(
+                                    commitment::coin_flip(&outcome),
+                                    None,
+                                ))
+                            }
+                            Err(error) => *input_error = Some(error),
+                        }
+                    }
+                }
+                MyEguiApp::CoinFlip(CoinFlip::Done(heads, opening)) => {
+                    if let Some(opening) = opening {
+                        ui.heading("Send this to Bob, to check the result with:");
+                        TextEdit::multiline(opening)
+                            .desired_rows(3)
+                            .layouter(&mut my_layouter)
+                            .show(ui);
+                    }
+
+                    let result = if *heads { "Heads" } else { "Tails" };
+                    ui.label(RichText::new(result).size(40.0));
+
+                    if ui.button("Back").clicked() {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::ParamGen(ParamGen::Done(text)) => {
+                    ui.heading("Your new group parameters:");
+
+                    ScrollArea::vertical()
+                        .max_height(TEXT_SCROLLER_MAX_HEIGHT)
+                        .show(ui, |ui| {
+                            TextEdit::multiline(&mut text.as_str())
+                                .desired_rows(TEXT_DESIRED_ROWS)
+                                .layouter(&mut my_layouter)
+                                .show(ui);
+                        });
+
+                    let (load, back) = ui
+                        .horizontal(|ui| {
+                            (
+                                ui.button("Use for a new session").clicked(),
+                                ui.button("Back").clicked(),
+                            )
+                        })
+                        .inner;
+
+                    if load {
+                        *self = MyEguiApp::LoadGroup(LoadGroup::start(text.clone()));
+                    } else if back {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::LoadGroup(LoadGroup::Entering(input, input_error)) => {
+                    ui.heading("Enter the group parameters:");
+                    ui.label("The other side has to load the same parameters before a session.");
+
+                    let textedit = ScrollArea::vertical()
+                        .max_height(TEXT_SCROLLER_MAX_HEIGHT)
+                        .show(ui, |ui| {
+                            TextEdit::multiline(input)
+                                .desired_rows(TEXT_DESIRED_ROWS)
+                                .layouter(&mut my_layouter)
+                                .show(ui)
+                                .response
+                        })
+                        .inner;
+
+                    if textedit.changed() {
+                        *input_error = None;
+                    }
+
+                    let (load, back) = ui
+                        .horizontal(|ui| (ui.button("Load").clicked(), ui.button("Back").clicked()))
+                        .inner;
+
+                    if let Some(error) = input_error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error.as_str());
+                    }
+
+                    if load {
+                        *self = MyEguiApp::LoadGroup(LoadGroup::start(input.clone()));
+                    } else if back {
+                        *self = MyEguiApp::default();
+                    }
+                }
+                MyEguiApp::LoadGroup(LoadGroup::Loading(rx, input)) => {
+                    ui.horizontal(|ui| {
+                        ui.heading("Checking the parameters...");
+                        ui.spinner();
+                    });
+
+                    match rx.try_recv() {
+                        Ok(Ok(group)) => {
+                            *self = MyEguiApp::Initial {
+                                group,
+                                exchange: KeyExchange::default(),
+                                code: String::new(),
+                                error: None,
+                            }
+                        }
+                        Ok(Err(error)) => {
+                            *self = MyEguiApp::LoadGroup(LoadGroup::Entering(
+                                input.clone(),
+                                Some(error),
+                            ))
+                        }
+                        Err(mpsc::TryRecvError::Empty) => {}
+                        Err(mpsc::TryRecvError::Disconnected) => unreachable!(),
+                    }
+                }
             }
         });
     }
 }
 
+// decodes a message pasted by the other side. deserializing also validates the group elements,
+// so the error says what exactly is wrong with the input.
+fn decode_message<T: DeserializeOwned>(input: &str) -> Result<T, String> {
+    let bytes = STANDARD_NO_PAD
+        .decode(input.trim())
+        .map_err(|_| "invalid input: not valid base64".to_owned())?;
+    bincode::deserialize(&bytes).map_err(|e| format!("invalid input: {e}"))
+}
+
+fn parse_otp_input(input: &str, entry_num: usize) -> Result<OtpAuth, String> {
+    let input = input.trim();
+    if input.starts_with("otpauth://") {
+        return OtpAuth::parse(input).map_err(|e| e.to_string());
+    }
+
+    // a bare base32 secret gets the usual TOTP parameters
+    match otp::decode_base32(input) {
+        Some(secret) if !secret.is_empty() => Ok(OtpAuth {
+            kind: OtpKind::Totp {
+                period: otp::DEFAULT_PERIOD,
+            },
+            algorithm: otp::Algorithm::default(),
+            label: format!("Secret #{entry_num}"),
+            issuer: None,
+            secret,
+            digits: otp::DEFAULT_DIGITS,
+        }),
+        _ => Err("expected an otpauth:// uri or a base32 secret".to_owned()),
+    }
+}
+
 fn my_layouter(ui: &egui::Ui, string: &str, wrap_width: f32) -> Arc<Galley> {
     let mut layout_job: egui::text::LayoutJob = LayoutJob::default();
 